@@ -1,6 +1,13 @@
 use clap::{Parser, Subcommand, ValueEnum};
 use mers_lib::prelude_compile::*;
-use std::{path::PathBuf, process::exit, sync::Arc};
+use mers_lib::program::run::CheckedTypes;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::exit,
+    sync::{atomic::Ordering, Arc},
+};
 
 mod cfg_globals;
 
@@ -31,6 +38,10 @@ enum Command {
     },
     /// Check and then run code. Exit status is 255 if checks fail.
     Run {
+        /// after every statement, verify that the type of its value is included in the type the checker computed for it.
+        /// This is slow, but helps find bugs in mers itself. A mismatch is reported as a runtime error.
+        #[arg(long)]
+        verify_types: bool,
         #[command(subcommand)]
         source: FromArgs,
     },
@@ -39,6 +50,16 @@ enum Command {
         #[command(subcommand)]
         source: FromArgs,
     },
+    /// Run tests: every file given directly, and every `*.test.mers` file in the given directories (recursively).
+    /// Each file is checked and run on its own, with a fresh config, and passes if it runs without errors (use `assert`, `assert_eq` and `assert_type`).
+    /// Calling `exit` fails the test. The working directory and environment variables are restored after each test.
+    ///
+    /// Exit status is 1 if any test failed.
+    Test {
+        /// files or directories to search for tests, by default the current directory
+        #[arg(num_args=0..)]
+        paths: Vec<PathBuf>,
+    },
     /// Not available, because the colored-output default feature was disabled when building mers!
     #[cfg(not(feature = "colored-output"))]
     PrettyPrint {
@@ -90,17 +111,24 @@ enum Configs {
     Std,
 }
 
-fn main() {
-    let mut args = Args::parse();
-    let config = cfg_globals::add_general(
-        match args.config {
+fn make_config(configs: Configs, args: Vec<String>) -> Config {
+    cfg_globals::add_general(
+        match configs {
             Configs::None => Config::new(),
             Configs::Base => Config::new().bundle_base(),
             Configs::Pure => Config::new().bundle_pure(),
             Configs::Std => Config::new().bundle_std(),
         },
+        args,
+    )
+}
+
+fn main() {
+    let mut args = Args::parse();
+    let config = make_config(
+        args.config,
         match &mut args.command {
-            Command::Run { source } | Command::RunUnchecked { source } => match source {
+            Command::Run { source, .. } | Command::RunUnchecked { source } => match source {
                 FromArgs::File { file: _, args } | FromArgs::Arg { source: _, args } => {
                     std::mem::replace(args, vec![])
                 }
@@ -148,7 +176,10 @@ fn main() {
                 }
             }
         }
-        Command::Run { source } => {
+        Command::Run {
+            source,
+            verify_types,
+        } => {
             let mut src = get_source(source.to());
             let srca = Arc::new(src.clone());
             match parse(&mut src, &srca) {
@@ -157,7 +188,12 @@ fn main() {
                     exit(255);
                 }
                 Ok(parsed) => {
-                    let (i1, mut i2, i3) = config.infos();
+                    let (i1, mut i2, mut i3) = config.infos();
+                    if verify_types {
+                        let checked_types = CheckedTypes::default();
+                        i3.global.checked_types = Some(checked_types.clone());
+                        i2.global.verify_types = Some(checked_types);
+                    }
                     match compile(&*parsed, i1) {
                         Err(e) => {
                             eprintln!("{e:?}");
@@ -204,6 +240,61 @@ fn main() {
                 }
             }
         }
+        Command::Test { mut paths } => {
+            if paths.is_empty() {
+                paths.push(PathBuf::from("."));
+            }
+            let mut tests = vec![];
+            for path in paths {
+                if path.is_dir() {
+                    find_tests(&path, &mut tests);
+                } else {
+                    tests.push(path);
+                }
+            }
+            // tests may change the working directory, so relative paths must be resolved first
+            let tests = tests
+                .into_iter()
+                .map(|test| {
+                    let path = std::fs::canonicalize(&test).unwrap_or_else(|_| test.clone());
+                    (test, path)
+                })
+                .collect::<Vec<_>>();
+            eprintln!(
+                "running {} test{}",
+                tests.len(),
+                if tests.len() == 1 { "" } else { "s" }
+            );
+            let process_state = ProcessState::save();
+            let mut failures = vec![];
+            for (test, path) in tests.iter() {
+                // every test gets its own config, so tests can't affect each other
+                let result = run_test(path, make_config(args.config, vec![]));
+                process_state.restore();
+                match result {
+                    Ok(()) => eprintln!("test {} ... ok", test.display()),
+                    Err(e) => {
+                        eprintln!("test {} ... FAILED", test.display());
+                        failures.push((test, e));
+                    }
+                }
+            }
+            if !failures.is_empty() {
+                eprintln!("\nfailures:");
+                for (test, e) in failures.iter() {
+                    eprintln!("\n---- {} ----\n{e}", test.display());
+                }
+            }
+            eprintln!(
+                "\ntest result: {}. {} passed; {} failed",
+                if failures.is_empty() { "ok" } else { "FAILED" },
+                tests.len() - failures.len(),
+                failures.len()
+            );
+            if !failures.is_empty() {
+                exit(1);
+            }
+        }
         #[cfg(feature = "colored-output")]
         Command::PrettyPrint { source } => {
             mers_lib::pretty_print::pretty_print(get_source(source));
@@ -215,3 +306,76 @@ fn main() {
         }
     }
 }
+
+/// adds all `*.test.mers` files in `dir` and its subdirectories (except hidden ones) to `tests`, sorted by path
+fn find_tests(dir: &Path, tests: &mut Vec<PathBuf>) {
+    let mut entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).collect::<Vec<_>>(),
+        Err(e) => {
+            eprintln!("Can't read directory {dir:?}: {e}");
+            exit(10);
+        }
+    };
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            find_tests(&path, tests);
+        } else if name.ends_with(".test.mers") {
+            tests.push(path);
+        }
+    }
+}
+
+/// parses, compiles, checks and runs a test file, returning the error if any step fails
+fn run_test(file: &Path, config: Config) -> Result<(), String> {
+    let mut src = Source::new_from_file(file.to_path_buf())
+        .map_err(|e| format!("Can't read file {file:?}: {e}"))?;
+    let srca = Arc::new(src.clone());
+    let parsed = parse(&mut src, &srca).map_err(|e| format!("{e:?}"))?;
+    let (i1, mut i2, i3) = config.infos();
+    // `exit` would end the test runner, so it returns an error instead
+    i2.global
+        .allow_process_exit_via_exit
+        .store(false, Ordering::Relaxed);
+    let compiled = compile(&*parsed, i1).map_err(|e| format!("{e:?}"))?;
+    check(&*compiled, i3).map_err(|e| format!("{e:?}"))?;
+    compiled
+        .run(&mut i2)
+        .map_err(|e| format!("Error while running:\n{e:?}"))?;
+    Ok(())
+}
+
+/// The working directory and environment variables of the process, which tests can change using `with_env`
+struct ProcessState {
+    dir: Option<PathBuf>,
+    vars: HashMap<OsString, OsString>,
+}
+impl ProcessState {
+    fn save() -> Self {
+        Self {
+            dir: std::env::current_dir().ok(),
+            vars: std::env::vars_os().collect(),
+        }
+    }
+    fn restore(&self) {
+        if let Some(dir) = &self.dir {
+            _ = std::env::set_current_dir(dir);
+        }
+        for (name, _) in std::env::vars_os() {
+            if !self.vars.contains_key(&name) {
+                std::env::remove_var(name);
+            }
+        }
+        for (name, value) in self.vars.iter() {
+            if std::env::var_os(name).as_ref() != Some(value) {
+                std::env::set_var(name, value);
+            }
+        }
+    }
+}
@@ -160,10 +160,10 @@ impl Function {
         let info = self.info_check.lock().unwrap().clone();
         if self.fixed_type.is_some() {
             match &*self.fixed_type_out.lock().unwrap() {
-                Some(Ok(types)) => return FunctionT(Err(types.clone()), info),
+                Some(Ok(types)) => return FunctionT::new(Err(types.clone()), info),
                 Some(Err(e)) => {
                     let e = e.clone();
-                    return FunctionT(
+                    return FunctionT::new(
                         Ok(Arc::new(move |_, _| Err(e.clone()))),
                         crate::info::Info::neverused(),
                     );
@@ -173,10 +173,15 @@ impl Function {
         }
         match &self.out {
             Ok(out) => {
+                let origin = Arc::clone(out);
                 let out = Arc::clone(out);
-                FunctionT(Ok(Arc::new(move |a, i| out(a, &mut i.clone()))), info)
+                FunctionT(
+                    Ok(Arc::new(move |a, i| out(a, &mut i.clone()))),
+                    info,
+                    Some(origin),
+                )
             }
-            Err(types) => FunctionT(Err(Arc::clone(types)), info),
+            Err(types) => FunctionT::new(Err(Arc::clone(types)), info),
         }
     }
 
@@ -247,15 +252,34 @@ impl MersData for Function {
     }
 }
 
+/// The `out` of a generic `Function`, which computes its output type from its argument type
+pub type FunctionOut = Arc<dyn Fn(&Type, &mut CheckInfo) -> Result<Type, CheckError> + Send + Sync>;
+/// The output type of a generic `FunctionT`, computed from the argument type
+pub type FunctionTOut = Arc<dyn Fn(&Type, &CheckInfo) -> Result<Type, CheckError> + Send + Sync>;
+
+/// The type of a function. Use `FunctionT::new` or `Function::get_as_type` to create one.
 #[derive(Clone)]
 pub struct FunctionT(
-    pub  Result<
-        Arc<dyn Fn(&Type, &CheckInfo) -> Result<Type, CheckError> + Send + Sync>,
-        Arc<Vec<(Type, Type)>>,
-    >,
+    pub Result<FunctionTOut, Arc<Vec<(Type, Type)>>>,
     pub CheckInfo,
+    /// The `out` of the function this type was created from, if any.
+    /// Generic function types can't be compared, but two types of the same function are equal.
+    /// Only `Function::get_as_type` sets this.
+    Option<FunctionOut>,
 );
 impl FunctionT {
+    /// The type of a function which computes its output type from its argument type (`Ok`)
+    /// or which has fixed `(argument, output)` types (`Err`).
+    pub fn new(out: Result<FunctionTOut, Arc<Vec<(Type, Type)>>>, info: CheckInfo) -> Self {
+        Self(out, info, None)
+    }
+    /// true if both types were created from the same function
+    fn same_origin(&self, other: &Self) -> bool {
+        match (&self.2, &other.2) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
     /// get output type
     pub fn o(&self, i: &Type) -> Result<Type, CheckError> {
         self.o_try(i).map_err(|(e, _)| e)
@@ -347,7 +371,10 @@ impl MersType for FunctionT {
                 false
             }
         } else {
-            false
+            other
+                .as_any()
+                .downcast_ref::<Self>()
+                .is_some_and(|other| self.same_origin(other))
         }
     }
     fn is_included_in(&self, target: &dyn MersType) -> bool {
@@ -356,7 +383,7 @@ impl MersType for FunctionT {
                 s.iter()
                     .all(|(i, o)| self.o(i).is_ok_and(|r| r.is_included_in(o)))
             } else {
-                false
+                self.same_origin(target)
             }
         } else {
             false
@@ -152,6 +152,17 @@ pub trait MersType: Any + Debug + Send + Sync {
     fn is_reference_to(&self) -> Option<&Type> {
         None
     }
+    /// Called when a value of this type is called with `arg` in a chain, after the call was checked.
+    /// Can be used to check things which aren't part of the argument's type, like the value of a literal.
+    #[allow(unused)]
+    fn check_call(
+        &self,
+        arg: &dyn crate::program::run::MersStatement,
+        arg_type: &Type,
+        info: &crate::program::run::CheckInfo,
+    ) -> Result<(), CheckError> {
+        Ok(())
+    }
     /// may mutate `self` to simplify it
     #[allow(unused)]
     fn simplify_for_display(&self, info: &crate::program::run::CheckInfo) -> Option<Type> {
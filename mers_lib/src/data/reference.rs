@@ -152,7 +152,7 @@ impl MersType for ReferenceT {
                     .downcast_ref::<crate::data::function::FunctionT>()?,
             ));
         }
-        Some(super::function::FunctionT(
+        Some(super::function::FunctionT::new(
             Ok(Arc::new(move |a, _| {
                 let mut out = Type::empty();
                 for func in funcs.iter() {
@@ -92,11 +92,13 @@ pub enum EColor {
     TryUnusedFunction1,
     TryUnusedFunction2,
     CustomTypeTestFailed,
+    FormatTemplate,
 
     StacktraceDescend,
     StacktraceDescendHashInclude,
 
     MaximumRuntimeExceeded,
+    TypeVerificationFailed,
 
     InCodePositionLine,
 
@@ -328,6 +330,34 @@ impl CheckError {
     pub(crate) fn src_mut(&mut self, s: Vec<(SourceRange, Option<EColor>)>) -> &mut Self {
         self.add_mut(CheckErrorComponent::Source(s))
     }
+    /// The call sites this error passed through while propagating out of function calls
+    /// (sources marked `StacktraceDescend` or `StacktraceDescendHashInclude`), innermost call first.
+    pub fn stack_trace(&self) -> Vec<SourceRange> {
+        let mut o = vec![];
+        self.stack_trace_into(&mut o);
+        o
+    }
+    fn stack_trace_into(&self, o: &mut Vec<SourceRange>) {
+        for component in &self.0 {
+            if let CheckErrorComponent::Error(e)
+            | CheckErrorComponent::ErrorWithDifferentSource(e) = component
+            {
+                e.stack_trace_into(o);
+            }
+        }
+        for component in &self.0 {
+            if let CheckErrorComponent::Source(src) = component {
+                for (range, color) in src {
+                    if matches!(
+                        color,
+                        Some(EColor::StacktraceDescend | EColor::StacktraceDescendHashInclude)
+                    ) {
+                        o.push(range.clone());
+                    }
+                }
+            }
+        }
+    }
     #[cfg(feature = "parse")]
     pub fn display<'a>(&'a self, theme: impl ETheme + 'static) -> CheckErrorDisplay<'a> {
         CheckErrorDisplay {
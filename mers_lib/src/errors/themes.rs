@@ -88,6 +88,7 @@ pub fn default_theme<C>(
         TryUnusedFunction2 => unused_b,
 
         CustomTypeTestFailed => hard_err,
+        FormatTemplate => type_wrong,
 
         ChainWithNonFunction => type_wrong,
 
@@ -103,6 +104,7 @@ pub fn default_theme<C>(
         // -- runtime-errors --
         StacktraceDescend => runtime,
         MaximumRuntimeExceeded => runtime_b,
+        TypeVerificationFailed => hard_err,
 
         InCodePositionLine => gray,
     })
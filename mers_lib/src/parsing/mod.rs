@@ -330,7 +330,7 @@ impl Source {
     /// Returns a SectionMarker which, when dropped, indicates the end of this section.
     /// Useful for debugging the parser.
     pub fn section_begin(&mut self, section: String) -> Arc<String> {
-        #[cfg(debug_assertions)]
+        #[cfg(feature = "debug-parser")]
         println!("[mers:parse] Section begin: {}", &section);
         let arc = Arc::new(section);
         self.sections.push(SectionMarker {
@@ -463,7 +463,7 @@ impl SectionMarker {
         if self.end.is_none() {
             if Arc::strong_count(&self.section) == 1 {
                 self.end = Some(end);
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "debug-parser")]
                 println!("[mers:parse] Section end  : {}", &self.section);
             }
         }
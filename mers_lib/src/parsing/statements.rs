@@ -342,6 +342,24 @@ pub fn parse_multiple(
     }
     Ok(statements)
 }
+/// the absolute path of the source file (or its directory) as a `String`, or `()` if the source isn't a file.
+fn source_path(srca: &Arc<Source>, dir: bool) -> Data {
+    let path = match srca.src_from() {
+        SourceFrom::File(path) => std::path::absolute(path).unwrap_or_else(|_| path.clone()),
+        SourceFrom::Unspecified => return Data::empty_tuple(),
+    };
+    let path = if dir {
+        match path.parent() {
+            Some(p) => p.to_path_buf(),
+            None => return Data::empty_tuple(),
+        }
+    } else {
+        path
+    };
+    Data::new(crate::data::string::String(
+        path.to_string_lossy().into_owned(),
+    ))
+}
 pub fn parse_no_chain(
     src: &mut Source,
     srca: &Arc<Source>,
@@ -428,6 +446,18 @@ pub fn parse_no_chain(
                             )));
                     }
                 }
+                "source_file" => {
+                    return Ok(Some(Box::new(program::parsed::value::Value {
+                        pos_in_src: (pos_in_src, src.get_pos(), srca).into(),
+                        data: source_path(srca, false),
+                    })));
+                }
+                "source_dir" => {
+                    return Ok(Some(Box::new(program::parsed::value::Value {
+                        pos_in_src: (pos_in_src, src.get_pos(), srca).into(),
+                        data: source_path(srca, true),
+                    })));
+                }
                 other => {
                     let msg = format!("Unknown #statement: {other}");
                     return Err(CheckError::new()
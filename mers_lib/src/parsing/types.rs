@@ -262,7 +262,7 @@ pub fn type_from_parsed(
                     })
                     .collect::<Result<_, _>>()?,
             ))),
-            ParsedType::Function(v) => as_type.add(Arc::new(data::function::FunctionT(
+            ParsedType::Function(v) => as_type.add(Arc::new(data::function::FunctionT::new(
                 Err(Arc::new(
                     v.iter()
                         .map(|(i, o)| Ok((type_from_parsed(i, info)?, type_from_parsed(o, info)?)))
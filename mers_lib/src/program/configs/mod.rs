@@ -1,4 +1,8 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use crate::{
     data::{
@@ -15,14 +19,23 @@ pub mod gen;
 pub mod util;
 pub mod with_base;
 pub mod with_command_running;
+pub mod with_csv;
+pub mod with_env;
 pub mod with_fs;
 pub mod with_get;
+pub mod with_hash;
+pub mod with_http;
 pub mod with_iters;
 pub mod with_list;
+pub mod with_log;
 pub mod with_math;
 pub mod with_multithreading;
+pub mod with_net;
+pub mod with_random;
 pub mod with_stdio;
 pub mod with_string;
+pub mod with_time;
+pub mod with_toml;
 
 /// Usage: create an empty Config using Config::new(), use the methods to customize it, then get the Infos using Config::infos()
 /// bundle_* for bundles (combines multiple groups or even bundles)
@@ -43,8 +56,18 @@ impl Config {
     /// - `with_command_running()`
     /// - `with_multithreading()`
     /// - `with_fs()`
+    /// - `with_time()`
+    /// - `with_env()`
+    /// - `with_net()`
+    /// - `with_http()`
+    /// - `with_log()`
     pub fn bundle_std(self) -> Self {
-        self.with_fs()
+        self.with_log()
+            .with_http()
+            .with_net()
+            .with_env()
+            .with_time()
+            .with_fs()
             .with_multithreading()
             .with_command_running()
             .with_stdio()
@@ -52,12 +75,23 @@ impl Config {
     }
     /// standard utilities, but don't allow code to do any I/O.
     /// (multithreading can be added using `.with_multithreading()`)
+    /// The only exception is `().rng_new`, which reads the OS's randomness and the current time to get a seed.
     ///
     /// - `bundle_base()`
     /// - `with_list()`
     /// - `with_string()`
+    /// - `with_random()`
+    /// - `with_csv()`
+    /// - `with_toml()`
+    /// - `with_hash()`
     pub fn bundle_pure(self) -> Self {
-        self.with_string().with_list().bundle_base()
+        self.with_hash()
+            .with_toml()
+            .with_csv()
+            .with_random()
+            .with_string()
+            .with_list()
+            .bundle_base()
     }
     /// base utilities used in most programs
     ///
@@ -204,6 +238,27 @@ impl Config {
         self
     }
 
+    /// Limits how many threads `par_map` and `par_for_each` use at once.
+    /// By default, they use `std::thread::available_parallelism()` threads.
+    pub fn set_parallelism(mut self, threads: NonZeroUsize) -> Self {
+        self.info_run.global.parallelism = Some(threads);
+        self
+    }
+
+    /// Limits the size of request and response bodies the functions from `with_http` read.
+    /// By default, this is `with_http::DEFAULT_MAX_BODY_SIZE`.
+    pub fn set_http_max_body_size(mut self, bytes: usize) -> Self {
+        self.info_run.global.http_max_body_size = bytes;
+        self
+    }
+
+    /// Sets how long `http_serve` waits for a client to send or receive data before giving up on the connection.
+    /// By default, this is `with_http::DEFAULT_SERVE_TIMEOUT`. The timeout must not be zero.
+    pub fn set_http_serve_timeout(mut self, timeout: Duration) -> Self {
+        self.info_run.global.http_serve_timeout = timeout;
+        self
+    }
+
     pub fn infos(self) -> (super::parsed::Info, super::run::Info, super::run::CheckInfo) {
         (self.info_parsed, self.info_run, self.info_check)
     }
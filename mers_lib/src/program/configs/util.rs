@@ -1,11 +1,26 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
 
 use crate::{
-    data::{self, Data, Type},
+    data::{self, object::ObjectFieldsMap, Data, Type},
     errors::CheckError,
     info::Info,
+    program::run::CheckInfo,
 };
 
+/// The argument type of a function with a type annotation, like `x [Int] -> x`.
+/// `None` if the function doesn't have a type annotation.
+pub fn fixed_arg_type(f: &data::function::FunctionT) -> Option<Type> {
+    let types = f.0.as_ref().err()?;
+    let mut arg = Type::empty();
+    for (t, _) in types.iter() {
+        arg.add_all(t);
+    }
+    Some(arg)
+}
+
 pub fn to_mers_func(
     out: impl Fn(&Type, &mut crate::program::run::CheckInfo) -> Result<Type, CheckError>
         + Send
@@ -119,3 +134,102 @@ pub fn to_mers_func_concrete_string_string_to_any(
         },
     )
 }
+
+/// the elements of a tuple with at least `N` elements
+pub(crate) fn tuple_args<const N: usize>(a: &Data) -> [Data; N] {
+    let a = a.get();
+    let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+    std::array::from_fn(|n| a.get(n).unwrap())
+}
+/// the tuples in `a`, which must all have length `len`
+pub(crate) fn tuple_arg_types<'a>(
+    a: &'a Type,
+    len: usize,
+    func: &str,
+    i: &CheckInfo,
+) -> Result<Vec<&'a data::tuple::TupleT>, CheckError> {
+    a.types
+        .iter()
+        .map(|t| {
+            t.as_any()
+                .downcast_ref::<data::tuple::TupleT>()
+                .filter(|t| t.0.len() == len)
+                .ok_or_else(|| {
+                    format!(
+                        "{func}: expected a tuple of length {len}, but got {}",
+                        a.with_info(i)
+                    )
+                    .into()
+                })
+        })
+        .collect()
+}
+/// a clone of the `String` `a`
+pub(crate) fn string_arg(a: &Data) -> String {
+    a.get()
+        .as_any()
+        .downcast_ref::<data::string::String>()
+        .unwrap()
+        .0
+        .clone()
+}
+
+/// `(t)/()`
+pub(crate) fn maybe_type(t: Type) -> Type {
+    Type::newm(vec![
+        Arc::new(data::tuple::TupleT(vec![t])),
+        Arc::new(data::tuple::TupleT(vec![])),
+    ])
+}
+/// `(v)` or `()`
+pub(crate) fn maybe(v: Option<Data>) -> Data {
+    v.map_or_else(Data::empty_tuple, Data::one_tuple)
+}
+
+/// `t/{field: String}`
+pub(crate) fn or_error(t: Type, field: &str, i: &CheckInfo) -> Type {
+    let mut t = t;
+    t.add(Arc::new(data::object::ObjectT::new(vec![(
+        i.global.object_fields.get_or_add_field(field),
+        Type::new(data::string::StringT),
+    )])));
+    t
+}
+/// `{field: e}`
+pub(crate) fn error_object(field: &str, e: impl Display, i: &crate::program::run::Info) -> Data {
+    Data::new(data::object::Object::new(vec![(
+        i.global.object_fields.get_or_add_field(field),
+        Data::new(data::string::String(e.to_string())),
+    )]))
+}
+
+/// checks that `a` is an iterable over `Byte`s
+pub(crate) fn check_bytes_arg(a: &Type, func: &str, i: &CheckInfo) -> Result<(), CheckError> {
+    if a.iterable()
+        .is_some_and(|t| t.is_included_in_single(&data::byte::ByteT))
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "{func}: expected an iterable over Bytes, but got {}",
+            a.with_info(i)
+        )
+        .into())
+    }
+}
+/// the bytes in `a`, see `check_bytes_arg`
+pub(crate) fn bytes_arg(a: &Data, i: &crate::program::run::Info) -> Result<Vec<u8>, CheckError> {
+    a.get()
+        .iterable(&i.global)
+        .unwrap()
+        .map(|v| {
+            v.map(|v| {
+                v.get()
+                    .as_any()
+                    .downcast_ref::<data::byte::Byte>()
+                    .unwrap()
+                    .0
+            })
+        })
+        .collect()
+}
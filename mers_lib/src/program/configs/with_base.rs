@@ -4,9 +4,9 @@ use std::{
 };
 
 use crate::{
-    data::{self, bool::bool_type, int::INT_MAX, Data, MersTypeWInfo, Type},
+    data::{self, bool::bool_type, int::INT_MAX, Data, MersDataWInfo, MersTypeWInfo, Type},
     errors::CheckError,
-    program::run::{CheckInfo, Info},
+    program::run::{chain::run_callback, CheckInfo, Info},
 };
 
 use super::{
@@ -14,7 +14,7 @@ use super::{
         function::{func, func_err},
         IntR, OneOf,
     },
-    Config,
+    util, Config,
 };
 
 impl Config {
@@ -24,6 +24,10 @@ impl Config {
     /// `len: fn` gets the length of strings or tuples
     /// `sleep: fn` sleeps for n seconds (pauses the current thread)
     /// `panic: fn` exits the program with the given exit code
+    /// `assert: fn` causes a runtime error if the argument is false: cond.assert or (cond, message).assert
+    /// `assert_eq: fn` (left, right).assert_eq causes a runtime error, which shows both values and their types, if they aren't equal
+    /// `assert_type: fn` (value, handler).assert_type causes a runtime error if the value doesn't have the type of the handler's type annotation,
+    ///   and otherwise calls the handler with it: (x, x [Int] -> x).assert_type returns an `Int`, even if `x` could also be a `String`.
     /// `lock_update: fn` locks the value of a reference so you can exclusively modify it: &var.lock_update(v -> (v, 1).sum)
     pub fn with_base(self) -> Self {
         self
@@ -74,7 +78,7 @@ impl Config {
                     let mut arg = arg_ref.write();
                     let func = a.0[1].read();
                     let func = func.get();
-                    *arg = func.execute(arg.clone(), &i.global).unwrap()?;
+                    *arg = run_callback(&**func, arg.clone(), &i.global, i.global.call_site.as_ref()).unwrap()?;
                     Ok(Data::empty_tuple())
                 }),
                 inner_statements: None,
@@ -94,6 +98,9 @@ impl Config {
             .add_var("panic", func_err(|message: &str, _| {
                 CheckError::from(message)
             }))
+            .add_var("assert", assert_func())
+            .add_var("assert_eq", assert_eq_func())
+            .add_var("assert_type", assert_type_func())
             .add_var(
             "len",
             data::function::Function {
@@ -200,3 +207,160 @@ impl Config {
         )
     }
 }
+
+fn assert_func() -> data::function::Function {
+    util::to_mers_func(
+        |a, i| {
+            for t in a.types.iter() {
+                let ok = t.as_any().is::<data::bool::TrueT>()
+                    || t.as_any().is::<data::bool::FalseT>()
+                    || t.as_any()
+                        .downcast_ref::<data::tuple::TupleT>()
+                        .is_some_and(|t| {
+                            t.0.len() == 2
+                                && t.0[0].is_included_in(&bool_type())
+                                && t.0[1].is_included_in_single(&data::string::StringT)
+                        });
+                if !ok {
+                    return Err(format!(
+                        "assert: expected Bool or (Bool, String), but got {}",
+                        a.with_info(i)
+                    )
+                    .into());
+                }
+            }
+            Ok(Type::empty_tuple())
+        },
+        |a, _i| {
+            let a = a.get();
+            let (cond, message) = if let Some(t) = a.as_any().downcast_ref::<data::tuple::Tuple>() {
+                let message = t.0[1].read();
+                let message = message.get();
+                let message = &message
+                    .as_any()
+                    .downcast_ref::<data::string::String>()
+                    .unwrap()
+                    .0;
+                (
+                    t.0[0]
+                        .read()
+                        .get()
+                        .as_any()
+                        .downcast_ref::<data::bool::Bool>()
+                        .unwrap()
+                        .0,
+                    format!("assertion failed: {message}"),
+                )
+            } else {
+                (
+                    a.as_any().downcast_ref::<data::bool::Bool>().unwrap().0,
+                    "assertion failed".to_owned(),
+                )
+            };
+            if cond {
+                Ok(Data::empty_tuple())
+            } else {
+                Err(message.into())
+            }
+        },
+    )
+}
+
+fn assert_eq_func() -> data::function::Function {
+    util::to_mers_func(
+        |a, i| {
+            for t in a.types.iter() {
+                if t.as_any()
+                    .downcast_ref::<data::tuple::TupleT>()
+                    .filter(|t| t.0.len() == 2)
+                    .is_none()
+                {
+                    return Err(format!(
+                        "assert_eq: expected (left, right), but got {}",
+                        a.with_info(i)
+                    )
+                    .into());
+                }
+            }
+            Ok(Type::empty_tuple())
+        },
+        |a, i| {
+            let a = a.get();
+            let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+            let (left, right) = (a.0[0].read(), a.0[1].read());
+            let (left, right) = (left.get(), right.get());
+            if left.is_eq(right.as_ref()) {
+                Ok(Data::empty_tuple())
+            } else {
+                Err(format!(
+                    "assertion failed: values are not equal\n  left: {} :: {}\n right: {} :: {}",
+                    left.with_info(i),
+                    left.as_type().with_info(i),
+                    right.with_info(i),
+                    right.as_type().with_info(i),
+                )
+                .into())
+            }
+        },
+    )
+}
+
+fn assert_type_func() -> data::function::Function {
+    util::to_mers_func(
+        |a, i| {
+            let mut out = Type::empty();
+            for t in a.types.iter() {
+                let t = t
+                    .as_any()
+                    .downcast_ref::<data::tuple::TupleT>()
+                    .filter(|t| t.0.len() == 2)
+                    .ok_or_else(|| {
+                        format!(
+                            "assert_type: expected (value, handler), but got {}",
+                            a.with_info(i)
+                        )
+                    })?;
+                for f in t.0[1].types.iter() {
+                    let f = f
+                        .as_any()
+                        .downcast_ref::<data::function::FunctionT>()
+                        .and_then(|f| Some((f, util::fixed_arg_type(f)?)))
+                        .ok_or_else(|| {
+                            format!(
+                                "assert_type: the handler must be a function with a type annotation, like `x [Int] -> x`, but got {}",
+                                f.with_info(i)
+                            )
+                        })?;
+                    out.add_all(&f.0.o(&f.1)?);
+                }
+            }
+            Ok(out)
+        },
+        |a, i| {
+            let a = a.get();
+            let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+            let value = a.0[0].read().clone();
+            let handler = a.0[1].read();
+            let handler = handler.get();
+            let expected = util::fixed_arg_type(
+                &handler
+                    .as_any()
+                    .downcast_ref::<data::function::Function>()
+                    .unwrap()
+                    .get_as_type(),
+            )
+            .unwrap();
+            let actual = value.get().as_type();
+            if !actual.is_included_in(&expected) {
+                return Err(format!(
+                    "assertion failed: expected a value of type {}, but got {} :: {}",
+                    expected.with_info(i),
+                    value.get().with_info(i),
+                    actual.with_info(i),
+                )
+                .into());
+            }
+            run_callback(&**handler, value, &i.global, i.global.call_site.as_ref()).unwrap()
+        },
+    )
+}
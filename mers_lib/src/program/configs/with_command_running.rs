@@ -1,8 +1,9 @@
 use std::{
     fmt::Display,
-    io::{BufRead, BufReader, Read, Write},
-    process::{ChildStderr, ChildStdin, ChildStdout, Command, Stdio},
+    io::{self, BufRead, BufReader, PipeReader, Read, Write},
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio},
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use crate::{
@@ -10,8 +11,9 @@ use crate::{
         self,
         int::{INT_MAX, INT_MIN},
         object::ObjectFieldsMap,
-        Data, MersData, MersDataWInfo, MersType, Type,
+        Data, MersData, MersDataWInfo, MersType, MersTypeWInfo, Type,
     },
+    errors::CheckError,
     info::DisplayInfo,
     program::{self, run::CheckInfo},
 };
@@ -22,8 +24,17 @@ impl Config {
     /// adds utilities to run commands installed on the system and get their output.
     /// `run_command: fn` runs a command with arguments.
     /// Args: (cmd, args) where cmd is a string and args is an Iterable over strings
+    ///   or (cmd, args, options), where options is an object with any of these fields:
+    ///   `cwd: String` (working directory), `env: Iter<(String, String)>` (additional environment variables),
+    ///   `stdin: String/ChildProcess` (content for stdin, or a process whose stdout should be piped into the command's stdin. nothing should have been read from that process's stdout yet.),
+    ///   `timeout: Int/Float` (seconds, `run_command` only: kills the command if it takes longer),
+    ///   `stderr: String` (`"pipe"` (default), `"merge"` to redirect it to stdout, or `"discard"`)
     /// `RunCommandError` holds the error if the command can't be executed
     /// returns (int/(), string, string) on success (status code, stdout, stderr)
+    /// `spawn_command: fn` like `run_command`, but returns a `ChildProcess` instead of waiting for the command to exit
+    /// `childproc_kill: fn` kills the child process. returns false if that failed (for example because it already exited)
+    /// `childproc_pid: fn` returns the child process's OS-assigned process identifier
+    /// `childproc_read_all: fn` reads stdout until the process closes it (usually when it exits) and returns it as a String
     pub fn with_command_running(self) -> Self {
         // data::object::ObjectT(vec![("run_command_error".to_owned(), Type::new(data::string::StringT))])
         // data::object::Object(vec![("run_command_error".to_owned(), Data::new(data::string::String(e.to_string())))])
@@ -37,43 +48,29 @@ impl Config {
                 fixed_type: None,
                 fixed_type_out: Arc::new(Mutex::new(None)),
                 out: Ok(Arc::new(|a, i| {
-                    if a.types.iter().all(|t| t.as_any().downcast_ref::<data::tuple::TupleT>().is_some_and(|t| t.0.len() == 2 && t.0[0].is_included_in_single(&data::string::StringT) && t.0[1].iterable().is_some_and(|t| t.is_included_in_single(&data::string::StringT)))) {
-                        Ok(Type::newm(vec![
-                            Arc::new(data::tuple::TupleT(vec![
-                                Type::newm(vec![Arc::new(data::int::IntT(INT_MIN, INT_MAX)), Arc::new(data::bool::TrueT), Arc::new(data::bool::FalseT)]),
-                                Type::new(data::string::StringT),
-                                Type::new(data::string::StringT),
-                            ])),
-                            Arc::new(data::object::ObjectT::new(vec![(i.global.object_fields.get_or_add_field("run_command_error"), Type::new(data::string::StringT))]))
-                        ]))
-                    } else {
-                        return Err(format!("run_command called with invalid arguments (must be (String, Iter<String>))").into());
-                    }
+                    check_command_args(a, "run_command", true, i)?;
+                    Ok(Type::newm(vec![
+                        Arc::new(data::tuple::TupleT(vec![
+                            Type::newm(vec![Arc::new(data::int::IntT(INT_MIN, INT_MAX)), Arc::new(data::bool::TrueT), Arc::new(data::bool::FalseT)]),
+                            Type::new(data::string::StringT),
+                            Type::new(data::string::StringT),
+                        ])),
+                        Arc::new(data::object::ObjectT::new(vec![(i.global.object_fields.get_or_add_field("run_command_error"), Type::new(data::string::StringT))]))
+                    ]))
                 })),
                 run: Arc::new(|a, i| {
-                    let a = a.get();
-                    let cmd = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
-                    let (cmd, args) = (&cmd.0[0].read(), &cmd.0[1].read());
-                    let cmd = cmd.get();
-                    let (cmd, args) = (
-                        cmd.as_any().downcast_ref::<data::string::String>().unwrap(),
-                        args.get().iterable(&i.global).unwrap(),
-                    );
-                    let args = args.map(|v| v.map(|v| v.get().with_info(i).to_string())).collect::<Result<Vec<_>, _>>()?;
-                    match Command::new(&cmd.0)
-                        .args(args)
-                        .output()
-                    {
-                        Ok(output) => {
-                            let status = if let Some(code) = output.status.code() {
+                    let (cmd, options) = command_from_args(&a, i)?;
+                    match run_command(cmd, options) {
+                        Ok((status, stdout, stderr)) => {
+                            let status = if let Some(code) = status.code() {
                                 Data::new(data::int::Int(code as _))
                             } else {
-                                Data::new(data::bool::Bool(output.status.success()))
+                                Data::new(data::bool::Bool(status.success()))
                             };
                             let stdout =
-                                String::from_utf8_lossy(&output.stdout).into_owned();
+                                String::from_utf8_lossy(&stdout).into_owned();
                             let stderr =
-                                String::from_utf8_lossy(&output.stderr).into_owned();
+                                String::from_utf8_lossy(&stderr).into_owned();
                             Ok(Data::new(data::tuple::Tuple::from([
                                 status,
                                 Data::new(data::string::String(stdout)),
@@ -94,37 +91,33 @@ impl Config {
                 fixed_type: None,
                 fixed_type_out: Arc::new(Mutex::new(None)),
                 out: Ok(Arc::new(|a, i| {
-                    if a.types.iter().all(|t| t.as_any().downcast_ref::<data::tuple::TupleT>().is_some_and(|t| t.0.len() == 2 && t.0[0].is_included_in_single(&data::string::StringT) && t.0[1].iterable().is_some_and(|t| t.is_included_in_single(&data::string::StringT)))) {
-                        Ok(Type::newm(vec![
-                            Arc::new(ChildProcessT),
-                            Arc::new(data::object::ObjectT::new(vec![(i.global.object_fields.get_or_add_field("run_command_error"), Type::new(data::string::StringT))]))
-                        ]))
-                    } else {
-                        return Err(format!("spawn_command called with invalid arguments (must be (String, Iter<String>))").into());
-                    }
+                    check_command_args(a, "spawn_command", false, i)?;
+                    Ok(Type::newm(vec![
+                        Arc::new(ChildProcessT),
+                        Arc::new(data::object::ObjectT::new(vec![(i.global.object_fields.get_or_add_field("run_command_error"), Type::new(data::string::StringT))]))
+                    ]))
                 })),
                 run: Arc::new(|a, i| {
-                    let a = a.get();
-                    let cmd = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
-                    let (cmd, args) = (&cmd.0[0].read(), &cmd.0[1].read());
-                    let cmd = cmd.get();
-                    let (cmd, args) = (
-                        cmd.as_any().downcast_ref::<data::string::String>().unwrap(),
-                        args.get().iterable(&i.global).unwrap(),
-                    );
-                    let args = args.map(|v| v.map(|v| v.get().with_info(i).to_string())).collect::<Result<Vec<_>, _>>()?;
-                    match Command::new(&cmd.0)
-                        .args(args)
-                        .stdin(Stdio::piped())
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .spawn()
-                    {
-                        Ok(mut child) => {
-                            let a = Some(child.stdin.take().unwrap());
-                            let b = BufReader::new(child.stdout.take().unwrap());
-                            let c = BufReader::new(child.stderr.take().unwrap());
-                            Ok(Data::new(ChildProcess(Arc::new(Mutex::new((child, a, b, c))))))
+                    let (cmd, options) = command_from_args(&a, i)?;
+                    match spawn(cmd, &options) {
+                        Ok((child, stdin, stdout, stderr)) => {
+                            let stdin = Arc::new(Mutex::new(stdin));
+                            if let Some(input) = options.stdin_content {
+                                // write from another thread, because the process may not read all of its input
+                                // before we read its output. the writes of `childproc_write_*` happen after this one.
+                                let (started, wait_for_start) = std::sync::mpsc::channel();
+                                let stdin = Arc::clone(&stdin);
+                                std::thread::spawn(move || {
+                                    let mut stdin = stdin.lock().unwrap();
+                                    _ = started.send(());
+                                    if let Some(stdin) = stdin.as_mut() {
+                                        // if this fails, the process doesn't read stdin, which is not an error
+                                        _ = stdin.write_all(input.as_bytes()).and_then(|()| stdin.flush());
+                                    }
+                                });
+                                _ = wait_for_start.recv();
+                            }
+                            Ok(Data::new(ChildProcess(Arc::new(Mutex::new((child, stdin, BufReader::new(stdout), BufReader::new(stderr)))))))
                         }
                         Err(e) => Ok(Data::new(data::object::Object::new(vec![(i.global.object_fields.get_or_add_field("run_command_error"), Data::new(data::string::String(e.to_string())))]))),
                     }
@@ -132,6 +125,78 @@ impl Config {
                 inner_statements: None,
             },
         )
+        .add_var(
+            "childproc_kill",
+            data::function::Function {
+                info: program::run::Info::neverused(),
+                info_check: Arc::new(Mutex::new( CheckInfo::neverused())),
+                fixed_type: None,
+                fixed_type_out: Arc::new(Mutex::new(None)),
+                out: Ok(Arc::new(|a, i| {
+                    if a.is_included_in_single(&ChildProcessT) {
+                        Ok(data::bool::bool_type())
+                    } else {
+                        Err(format!("childproc_kill called on non-ChildProcess type {}", a.with_info(i)).into())
+                    }
+                })),
+                run: Arc::new(|a, _i| {
+                    let a = a.get();
+                    let child = a.as_any().downcast_ref::<ChildProcess>().unwrap();
+                    let mut child = child.0.lock().unwrap();
+                    Ok(Data::new(data::bool::Bool(child.0.kill().is_ok())))
+                }),
+                inner_statements: None,
+            },
+        )
+        .add_var(
+            "childproc_pid",
+            data::function::Function {
+                info: program::run::Info::neverused(),
+                info_check: Arc::new(Mutex::new( CheckInfo::neverused())),
+                fixed_type: None,
+                fixed_type_out: Arc::new(Mutex::new(None)),
+                out: Ok(Arc::new(|a, i| {
+                    if a.is_included_in_single(&ChildProcessT) {
+                        Ok(Type::new(data::int::IntT(0, u32::MAX as _)))
+                    } else {
+                        Err(format!("childproc_pid called on non-ChildProcess type {}", a.with_info(i)).into())
+                    }
+                })),
+                run: Arc::new(|a, _i| {
+                    let a = a.get();
+                    let child = a.as_any().downcast_ref::<ChildProcess>().unwrap();
+                    let child = child.0.lock().unwrap();
+                    Ok(Data::new(data::int::Int(child.0.id() as _)))
+                }),
+                inner_statements: None,
+            },
+        )
+        .add_var(
+            "childproc_read_all",
+            data::function::Function {
+                info: program::run::Info::neverused(),
+                info_check: Arc::new(Mutex::new( CheckInfo::neverused())),
+                fixed_type: None,
+                fixed_type_out: Arc::new(Mutex::new(None)),
+                out: Ok(Arc::new(|a, i| {
+                    if a.is_included_in_single(&ChildProcessT) {
+                        Ok(Type::new(data::string::StringT))
+                    } else {
+                        Err(format!("childproc_read_all called on non-ChildProcess type {}", a.with_info(i)).into())
+                    }
+                })),
+                run: Arc::new(|a, _i| {
+                    let a = a.get();
+                    let child = a.as_any().downcast_ref::<ChildProcess>().unwrap();
+                    let mut child = child.0.lock().unwrap();
+                    let mut buf = vec![];
+                    // on error, return what was read until then
+                    _ = child.2.read_to_end(&mut buf);
+                    Ok(Data::new(data::string::String(String::from_utf8_lossy(&buf).into_owned())))
+                }),
+                inner_statements: None,
+            },
+        )
         .add_var(
             "childproc_exited",
             data::function::Function {
@@ -185,7 +250,7 @@ impl Config {
                     let a = a.get();
                     let child = a.as_any().downcast_ref::<ChildProcess>().unwrap();
                     let mut child = child.0.lock().unwrap();
-                    drop(child.1.take());
+                    drop(child.1.lock().unwrap().take());
                     Ok(match child.0.wait() {
                         Ok(s) => if let Some(s) = s.code() {
                             Data::new(data::int::Int(s as _))
@@ -220,9 +285,9 @@ impl Config {
                     let bytes = tuple.0[1].read();
                     let bytes = bytes.get();
                     let child = child.as_any().downcast_ref::<ChildProcess>().unwrap();
-                    let mut child = child.0.lock().unwrap();
+                    let stdin = Arc::clone(&child.0.lock().unwrap().1);
                     let buf = bytes.iterable(&i.global).unwrap().map(|v| v.map(|v| v.get().as_any().downcast_ref::<data::byte::Byte>().unwrap().0)).collect::<Result<Vec<_>, _>>()?;
-                    Ok(if child.1.as_mut().is_some_and(|v| v.write_all(&buf).is_ok() && v.flush().is_ok()) {
+                    Ok(if stdin.lock().unwrap().as_mut().is_some_and(|v| v.write_all(&buf).is_ok() && v.flush().is_ok()) {
                         Data::new(data::bool::Bool(true))
                     } else {
                         Data::new(data::bool::Bool(false))
@@ -253,9 +318,9 @@ impl Config {
                     let string = tuple.0[1].read();
                     let string = string.get();
                     let child = child.as_any().downcast_ref::<ChildProcess>().unwrap();
-                    let mut child = child.0.lock().unwrap();
+                    let stdin = Arc::clone(&child.0.lock().unwrap().1);
                     let buf = string.as_any().downcast_ref::<data::string::String>().unwrap().0.as_bytes();
-                    Ok(if child.1.as_mut().is_some_and(|v| v.write_all(buf).is_ok() && v.flush().is_ok()) {
+                    Ok(if stdin.lock().unwrap().as_mut().is_some_and(|v| v.write_all(buf).is_ok() && v.flush().is_ok()) {
                         Data::new(data::bool::Bool(true))
                     } else {
                         Data::new(data::bool::Bool(false))
@@ -396,9 +461,10 @@ pub struct ChildProcess(
     Arc<
         Mutex<(
             std::process::Child,
-            Option<ChildStdin>,
-            BufReader<ChildStdout>,
-            BufReader<ChildStderr>,
+            // separately locked, so writing doesn't block reading
+            Arc<Mutex<Option<ChildStdin>>>,
+            BufReader<ChildOut>,
+            BufReader<ChildOut>,
         )>,
     >,
 );
@@ -477,3 +543,287 @@ impl Display for ChildProcessT {
         write!(f, "ChildProcess")
     }
 }
+
+/// where a child process's stdout or stderr goes
+#[derive(Debug)]
+pub enum ChildOut {
+    Stdout(ChildStdout),
+    Stderr(ChildStderr),
+    /// used when stderr is merged into stdout
+    Pipe(PipeReader),
+    /// discarded, or piped into another process
+    Closed,
+}
+impl Read for ChildOut {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout(v) => v.read(buf),
+            Self::Stderr(v) => v.read(buf),
+            Self::Pipe(v) => v.read(buf),
+            Self::Closed => Ok(0),
+        }
+    }
+}
+impl From<ChildOut> for Stdio {
+    fn from(value: ChildOut) -> Self {
+        match value {
+            ChildOut::Stdout(v) => v.into(),
+            ChildOut::Stderr(v) => v.into(),
+            ChildOut::Pipe(v) => v.into(),
+            ChildOut::Closed => Stdio::null(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct CommandOptions {
+    stdin_content: Option<String>,
+    stdin_process: Option<ChildProcess>,
+    timeout: Option<Duration>,
+    stderr: StderrMode,
+}
+#[derive(Default)]
+enum StderrMode {
+    #[default]
+    Pipe,
+    Merge,
+    Discard,
+}
+
+/// checks `(String, Iter<String>)` or `(String, Iter<String>, options)`
+fn check_command_args(
+    a: &Type,
+    func: &str,
+    allow_timeout: bool,
+    i: &CheckInfo,
+) -> Result<(), CheckError> {
+    let field = |name: &str| i.global.object_fields.get_or_add_field(name);
+    let string = |t: &Type| t.is_included_in_single(&data::string::StringT);
+    for t in a.types.iter() {
+        let Some(t) = t
+            .as_any()
+            .downcast_ref::<data::tuple::TupleT>()
+            .filter(|t| {
+                (t.0.len() == 2 || t.0.len() == 3)
+                    && string(&t.0[0])
+                    && t.0[1].iterable().is_some_and(|t| string(&t))
+            })
+        else {
+            return Err(format!("{func} called with invalid arguments (must be (String, Iter<String>) or (String, Iter<String>, options))").into());
+        };
+        let Some(options) = t.0.get(2) else {
+            continue;
+        };
+        for o in options.types.iter() {
+            let Some(o) = o.as_any().downcast_ref::<data::object::ObjectT>() else {
+                return Err(format!(
+                    "{func}: options must be an object, but got {}",
+                    o.with_info(i)
+                )
+                .into());
+            };
+            for (name, t) in o.iter() {
+                let valid = if *name == field("cwd") || *name == field("stderr") {
+                    string(t)
+                } else if *name == field("env") {
+                    t.iterable().is_some_and(|t| {
+                        t.is_included_in_single(&data::tuple::TupleT(vec![
+                            Type::new(data::string::StringT),
+                            Type::new(data::string::StringT),
+                        ]))
+                    })
+                } else if *name == field("stdin") {
+                    t.types.iter().all(|t| {
+                        t.as_any().is::<data::string::StringT>() || t.as_any().is::<ChildProcessT>()
+                    })
+                } else if allow_timeout && *name == field("timeout") {
+                    t.is_included_in(&Type::newm(vec![
+                        Arc::new(data::int::IntT(0, INT_MAX)),
+                        Arc::new(data::float::FloatT),
+                    ]))
+                } else {
+                    false
+                };
+                if !valid {
+                    return Err(format!(
+                        "{func}: invalid options {}. valid options are cwd: String, env: Iter<(String, String)>, stdin: String/ChildProcess, {}stderr: String",
+                        o.with_info(i),
+                        if allow_timeout { "timeout: Int/Float, " } else { "" },
+                    )
+                    .into());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// builds the `Command` from `(cmd, args)` or `(cmd, args, options)`
+fn command_from_args(
+    a: &Data,
+    i: &program::run::Info,
+) -> Result<(Command, CommandOptions), CheckError> {
+    let a = a.get();
+    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+    let cmd = a[0].read();
+    let cmd = cmd.get();
+    let cmd = cmd.as_any().downcast_ref::<data::string::String>().unwrap();
+    let args = a[1]
+        .read()
+        .get()
+        .iterable(&i.global)
+        .unwrap()
+        .map(|v| v.map(|v| v.get().with_info(i).to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut command = Command::new(&cmd.0);
+    command.args(args);
+    let mut options = CommandOptions::default();
+    if let Some(o) = a.get(2) {
+        let o = o.read();
+        let o = o.get();
+        let o = o.as_any().downcast_ref::<data::object::Object>().unwrap();
+        let field = |name: &str| o.get(i.global.object_fields.get_or_add_field(name));
+        let string = |v: &Data| {
+            v.get()
+                .as_any()
+                .downcast_ref::<data::string::String>()
+                .map(|v| v.0.clone())
+        };
+        if let Some(cwd) = field("cwd") {
+            command.current_dir(string(&cwd).unwrap());
+        }
+        if let Some(env) = field("env") {
+            for v in env.get().iterable(&i.global).unwrap() {
+                let v = v?;
+                let v = v.get();
+                let v = &v.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                let (k, v) = (&*v[0].read(), &*v[1].read());
+                command.env(string(k).unwrap(), string(v).unwrap());
+            }
+        }
+        if let Some(stdin) = field("stdin") {
+            options.stdin_content = string(&stdin);
+            options.stdin_process = stdin.get().as_any().downcast_ref::<ChildProcess>().cloned();
+        }
+        if let Some(timeout) = field("timeout") {
+            let timeout = timeout.get();
+            options.timeout = Some(
+                if let Some(v) = timeout.as_any().downcast_ref::<data::int::Int>() {
+                    Duration::from_secs(v.0.max(0) as u64)
+                } else {
+                    let v = timeout
+                        .as_any()
+                        .downcast_ref::<data::float::Float>()
+                        .unwrap()
+                        .0;
+                    Duration::try_from_secs_f64(v.max(0.0)).unwrap_or(Duration::MAX)
+                },
+            );
+        }
+        if let Some(stderr) = field("stderr") {
+            options.stderr = match string(&stderr).unwrap().as_str() {
+                "pipe" => StderrMode::Pipe,
+                "merge" => StderrMode::Merge,
+                "discard" => StderrMode::Discard,
+                other => {
+                    return Err(format!(
+                    "invalid stderr option {other:?}, expected \"pipe\", \"merge\" or \"discard\""
+                )
+                    .into())
+                }
+            };
+        }
+    }
+    Ok((command, options))
+}
+
+/// spawns the command with piped stdin, stdout and stderr (or as configured in `options`)
+fn spawn(
+    mut cmd: Command,
+    options: &CommandOptions,
+) -> io::Result<(Child, Option<ChildStdin>, ChildOut, ChildOut)> {
+    if let Some(p) = &options.stdin_process {
+        let mut p = p.0.lock().unwrap();
+        let stdout = std::mem::replace(&mut p.2, BufReader::new(ChildOut::Closed));
+        cmd.stdin(stdout.into_inner());
+    } else {
+        cmd.stdin(Stdio::piped());
+    }
+    let merged = match options.stderr {
+        StderrMode::Pipe => {
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+            None
+        }
+        StderrMode::Discard => {
+            cmd.stdout(Stdio::piped()).stderr(Stdio::null());
+            None
+        }
+        StderrMode::Merge => {
+            let (reader, writer) = io::pipe()?;
+            cmd.stdout(writer.try_clone()?).stderr(writer);
+            Some(reader)
+        }
+    };
+    let mut child = cmd.spawn()?;
+    // drop the command, so the writing end of the merged pipe is only held by the child process
+    drop(cmd);
+    let stdin = child.stdin.take();
+    let (stdout, stderr) = match merged {
+        Some(reader) => (ChildOut::Pipe(reader), ChildOut::Closed),
+        None => (
+            child
+                .stdout
+                .take()
+                .map_or(ChildOut::Closed, ChildOut::Stdout),
+            child
+                .stderr
+                .take()
+                .map_or(ChildOut::Closed, ChildOut::Stderr),
+        ),
+    };
+    Ok((child, stdin, stdout, stderr))
+}
+
+/// runs the command to completion, returning its exit status, stdout and stderr.
+/// if `options.timeout` is set and the command takes longer, it is killed.
+fn run_command(
+    cmd: Command,
+    options: CommandOptions,
+) -> io::Result<(ExitStatus, Vec<u8>, Vec<u8>)> {
+    let (mut child, stdin, mut stdout, mut stderr) = spawn(cmd, &options)?;
+    if let Some(mut stdin) = stdin {
+        let input = options.stdin_content.unwrap_or_default();
+        // dropping stdin after writing closes it, so the command knows there is no more input
+        std::thread::spawn(move || _ = stdin.write_all(input.as_bytes()));
+    }
+    let stdout = std::thread::spawn(move || {
+        let mut buf = vec![];
+        stdout.read_to_end(&mut buf).map(|_| buf)
+    });
+    let stderr = std::thread::spawn(move || {
+        let mut buf = vec![];
+        stderr.read_to_end(&mut buf).map(|_| buf)
+    });
+    let status = if let Some(timeout) = options.timeout {
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                _ = child.kill();
+                _ = child.wait();
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("command timed out after {timeout:?}"),
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    } else {
+        child.wait()?
+    };
+    let stdout = stdout.join().unwrap()?;
+    let stderr = stderr.join().unwrap()?;
+    Ok((status, stdout, stderr))
+}
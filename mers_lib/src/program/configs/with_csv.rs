@@ -0,0 +1,654 @@
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    data::{
+        self,
+        int::{IntT, INT_MAX},
+        object::{Object, ObjectFieldsMap, ObjectT},
+        string::StringT,
+        tuple::{Tuple, TupleT},
+        Data, MersData, MersType, Type,
+    },
+    errors::CheckError,
+    info::DisplayInfo,
+    program::run::{CheckInfo, Info},
+};
+
+use super::{
+    util::{self, string_arg},
+    with_fs::{File, FileT, Lines, LinesT, ReadSource},
+    with_list::{List, ListT},
+    Config,
+};
+
+impl Config {
+    /// reading and writing comma-separated values (RFC 4180, with `"` for quoting).
+    /// every function takes an optional delimiter (a String containing a single character), which defaults to ",": `text.csv_parse` or `(text, ";").csv_parse`.
+    /// parse errors are returned as `{csv_error: String, line: Int}`, where `line` is the (1-based) line on which the error was found.
+    /// an invalid delimiter is reported in the same way, with `line: 1`. `csv_rows` and `csv_write` only return this error if a delimiter was given.
+    /// empty lines are skipped.
+    /// `csv_parse: fn` parses a String into a `List<List<String>>` (a list of rows)
+    /// `csv_parse_objects: fn` (text, template).csv_parse_objects uses the first row as a header and returns a list of objects with the fields of `template`.
+    ///   only the field names of the template are used, its values are ignored, and all fields in the output are Strings.
+    ///   example: (text, {name: "", age: ""}).csv_parse_objects returns `List<{name: String, age: String}>`. columns which aren't in the template are ignored.
+    /// `csv_rows: fn` returns a `CsvRows`, an iterable over the rows of a String, `File` or `Lines` (see `with_fs` and `with_stdio`).
+    ///   rows are parsed while iterating. if a row can't be parsed, the iterator returns the error object and then stops.
+    /// `csv_write: fn` turns an iterable over rows (iterables over Strings) into a String, quoting fields where necessary.
+    pub fn with_csv(self) -> Self {
+        let fields = CsvFields::new(&self.info_check.global.object_fields);
+        self.add_var(
+            "csv_parse",
+            util::to_mers_func(
+                |a, i| {
+                    for t in a.types.iter() {
+                        if !(t.as_any().is::<StringT>()
+                            || t.as_any()
+                                .downcast_ref::<TupleT>()
+                                .is_some_and(|t| t.0.len() == 2 && is_strings(&t.0)))
+                        {
+                            return Err(format!(
+                                "csv_parse: expected String or (String, delimiter: String), but got {}",
+                                a.with_info(i)
+                            )
+                            .into());
+                        }
+                    }
+                    let fields = CsvFields::check(i);
+                    Ok(fields.or_error(Type::new(ListT(Type::new(ListT(Type::new(StringT)))))))
+                },
+                |a, i| {
+                    let (text, delimiter) =
+                        if let Some(t) = a.get().as_any().downcast_ref::<Tuple>() {
+                            (string_arg(&t.0[0].read()), delimiter_arg(&t.0[1].read()))
+                        } else {
+                            (string_arg(&a), Ok(','))
+                        };
+                    let fields = CsvFields::run(i);
+                    let delimiter = match delimiter {
+                        Ok(d) => d,
+                        Err(e) => return Ok(fields.error_data(e)),
+                    };
+                    Ok(
+                        match CsvReader::new(text.lines().map(str::to_owned), delimiter)
+                            .collect::<Result<Vec<_>, _>>()
+                        {
+                            Ok(rows) => {
+                                Data::new(List(rows.into_iter().map(row_data).collect()))
+                            }
+                            Err(e) => fields.error_data(e),
+                        },
+                    )
+                },
+            ),
+        )
+        .add_var(
+            "csv_parse_objects",
+            util::to_mers_func(
+                |a, i| {
+                    let mut out: Option<ObjectT> = None;
+                    for t in a.types.iter() {
+                        let template = t
+                            .as_any()
+                            .downcast_ref::<TupleT>()
+                            .filter(|t| {
+                                (t.0.len() == 2 || t.0.len() == 3 && is_strings(&t.0[2..]))
+                                    && is_strings(&t.0[0..1])
+                            })
+                            .and_then(|t| {
+                                if let [o] = t.0[1].types.as_slice() {
+                                    o.as_any().downcast_ref::<ObjectT>()
+                                } else {
+                                    None
+                                }
+                            })
+                            .ok_or_else(|| {
+                                format!(
+                                    "csv_parse_objects: expected (String, template_object) or (String, template_object, delimiter: String), but got {}",
+                                    a.with_info(i)
+                                )
+                            })?;
+                        let template = ObjectT::new(
+                            template
+                                .iter()
+                                .map(|(f, _)| (*f, Type::new(StringT)))
+                                .collect(),
+                        );
+                        if let Some(out) = &out {
+                            if !out.is_same_type_as(&template) {
+                                return Err(format!(
+                                    "csv_parse_objects: all possible templates must have the same fields, but got {}",
+                                    a.with_info(i)
+                                )
+                                .into());
+                            }
+                        } else {
+                            out = Some(template);
+                        }
+                    }
+                    let out = out.ok_or("csv_parse_objects: argument can't be of type <unreachable>")?;
+                    let fields = CsvFields::check(i);
+                    Ok(fields.or_error(Type::new(ListT(Type::new(out)))))
+                },
+                |a, i| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<Tuple>().unwrap().0;
+                    let text = string_arg(&a[0].read());
+                    let template = a[1].read();
+                    let template = template.get();
+                    let template = template.as_any().downcast_ref::<Object>().unwrap();
+                    let fields = CsvFields::run(i);
+                    let delimiter = match a.get(2).map_or(Ok(','), |d| delimiter_arg(&d.read())) {
+                        Ok(d) => d,
+                        Err(e) => return Ok(fields.error_data(e)),
+                    };
+                    let mut rows = CsvReader::new(text.lines().map(str::to_owned), delimiter);
+                    let header = match rows.next() {
+                        Some(Ok(header)) => header,
+                        Some(Err(e)) => return Ok(fields.error_data(e)),
+                        None => vec![],
+                    };
+                    let header = header
+                        .iter()
+                        .map(|name| i.global.object_fields.get_or_add_field(name))
+                        .collect::<Vec<_>>();
+                    // for each field in the template, the column it is read from
+                    let mut columns = Vec::with_capacity(template.iter().len());
+                    for (field, _) in template.iter() {
+                        if let Some(col) = header.iter().position(|f| f == field) {
+                            columns.push((*field, col));
+                        } else {
+                            let name = i
+                                .global
+                                .object_fields
+                                .lock()
+                                .unwrap()
+                                .iter()
+                                .find(|(_, f)| *f == field)
+                                .map(|(name, _)| name.clone())
+                                .unwrap_or_default();
+                            return Ok(fields
+                                .error_data((format!("missing column `{name}` in header"), 1)));
+                        }
+                    }
+                    let mut out = vec![];
+                    while let Some(row) = rows.next() {
+                        let line = rows.line;
+                        let row = match row {
+                            Ok(row) => row,
+                            Err(e) => return Ok(fields.error_data(e)),
+                        };
+                        if row.len() != header.len() {
+                            return Ok(fields.error_data((
+                                format!(
+                                    "row has {} fields, but the header has {} columns",
+                                    row.len(),
+                                    header.len()
+                                ),
+                                line,
+                            )));
+                        }
+                        out.push(Data::new(Object::new(columns.iter().map(|(field, col)| {
+                            (*field, Data::new(data::string::String(row[*col].clone())))
+                        }))));
+                    }
+                    Ok(Data::new(List(out)))
+                },
+            ),
+        )
+        .add_var(
+            "csv_rows",
+            util::to_mers_func(
+                |a, i| {
+                    let mut with_delimiter = false;
+                    for t in a.types.iter() {
+                        let source = if let Some(t) = t.as_any().downcast_ref::<TupleT>() {
+                            with_delimiter = true;
+                            if t.0.len() == 2 && is_strings(&t.0[1..]) {
+                                Some(&t.0[0])
+                            } else {
+                                None
+                            }
+                        } else {
+                            None
+                        };
+                        let ok = match source {
+                            Some(s) => s.types.iter().all(|t| is_source(t.as_ref())),
+                            None => is_source(t.as_ref()),
+                        };
+                        if !ok {
+                            return Err(format!(
+                                "csv_rows: expected String/File/Lines or (String/File/Lines, delimiter: String), but got {}",
+                                a.with_info(i)
+                            )
+                            .into());
+                        }
+                    }
+                    let fields = CsvFields::check(i);
+                    let out = Type::new(CsvRowsT(fields));
+                    Ok(if with_delimiter { fields.or_error(out) } else { out })
+                },
+                |a, i| {
+                    let a = a.get();
+                    let fields = CsvFields::run(i);
+                    let (source, delimiter) = if let Some(t) = a.as_any().downcast_ref::<Tuple>() {
+                        let source = t.0[0].read();
+                        let source = source.get();
+                        match delimiter_arg(&t.0[1].read()) {
+                            Ok(d) => (source_arg(source.as_any()), d),
+                            Err(e) => return Ok(fields.error_data(e)),
+                        }
+                    } else {
+                        (source_arg(a.as_any()), ',')
+                    };
+                    Ok(Data::new(CsvRows(source, delimiter, fields)))
+                },
+            ),
+        )
+        .add_var(
+            "csv_write",
+            util::to_mers_func(
+                |a, i| {
+                    let mut with_delimiter = false;
+                    for t in a.types.iter() {
+                        let rows = match t.as_any().downcast_ref::<TupleT>() {
+                            Some(t) if t.0.len() == 2 && is_strings(&t.0[1..]) => {
+                                with_delimiter = true;
+                                t.0[0].clone()
+                            }
+                            _ => Type::newm(vec![t.clone()]),
+                        };
+                        if !rows.iterable().is_some_and(|row| {
+                            row.iterable()
+                                .is_some_and(|f| f.is_included_in_single(&StringT))
+                        }) {
+                            return Err(format!(
+                                "csv_write: expected an iterable over rows (iterables over Strings), optionally with a delimiter like (rows, \";\"), but got {}",
+                                a.with_info(i)
+                            )
+                            .into());
+                        }
+                    }
+                    Ok(if with_delimiter {
+                        CsvFields::check(i).or_error(Type::new(StringT))
+                    } else {
+                        Type::new(StringT)
+                    })
+                },
+                |a, i| {
+                    let (rows, delimiter) = {
+                        let g = a.get();
+                        let with_delimiter = g.as_any().downcast_ref::<Tuple>().filter(|t| {
+                            t.0.len() == 2
+                                && t.0[1]
+                                    .read()
+                                    .get()
+                                    .as_any()
+                                    .is::<data::string::String>()
+                        });
+                        if let Some(t) = with_delimiter {
+                            match delimiter_arg(&t.0[1].read()) {
+                                Ok(d) => (Data::clone(&t.0[0].read()), d),
+                                Err(e) => return Ok(CsvFields::run(i).error_data(e)),
+                            }
+                        } else {
+                            (a.clone(), ',')
+                        }
+                    };
+                    let mut out = String::new();
+                    for row in rows.get().iterable(&i.global).unwrap() {
+                        let row = row?
+                            .get()
+                            .iterable(&i.global)
+                            .unwrap()
+                            .map(|f| Ok(string_arg(&f?)))
+                            .collect::<Result<Vec<_>, CheckError>>()?;
+                        write_row(&mut out, &row, delimiter);
+                    }
+                    Ok(Data::new(data::string::String(out)))
+                },
+            ),
+        )
+        .add_type(
+            "CsvRows".to_owned(),
+            Ok(Arc::new(Type::new(CsvRowsT(fields)))),
+        )
+    }
+}
+
+/// the field ids of `{csv_error: String, line: Int}`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CsvFields {
+    pub csv_error: usize,
+    pub line: usize,
+}
+impl CsvFields {
+    fn new(object_fields: &impl ObjectFieldsMap) -> Self {
+        Self {
+            csv_error: object_fields.get_or_add_field("csv_error"),
+            line: object_fields.get_or_add_field("line"),
+        }
+    }
+    fn check(i: &CheckInfo) -> Self {
+        Self::new(&i.global.object_fields)
+    }
+    fn run(i: &Info) -> Self {
+        Self::new(&i.global.object_fields)
+    }
+    fn error_type(&self) -> ObjectT {
+        ObjectT::new(vec![
+            (self.csv_error, Type::new(StringT)),
+            (self.line, Type::new(IntT(1, INT_MAX))),
+        ])
+    }
+    fn or_error(&self, t: Type) -> Type {
+        let mut t = t;
+        t.add(Arc::new(self.error_type()));
+        t
+    }
+    fn error_data(&self, (e, line): (String, usize)) -> Data {
+        Data::new(Object::new(vec![
+            (self.csv_error, Data::new(data::string::String(e))),
+            (self.line, Data::new(data::int::Int(line as _))),
+        ]))
+    }
+}
+
+/// Parses rows from lines (without their line breaks). Errors contain the line number.
+struct CsvReader<L> {
+    lines: L,
+    line: usize,
+    delimiter: char,
+    failed: bool,
+}
+impl<L: Iterator<Item = String>> CsvReader<L> {
+    fn new(lines: L, delimiter: char) -> Self {
+        Self {
+            lines,
+            line: 0,
+            delimiter,
+            failed: false,
+        }
+    }
+    fn next_line(&mut self) -> Option<String> {
+        let line = self.lines.next()?;
+        self.line += 1;
+        Some(line)
+    }
+    fn parse_row(&mut self, first_line: String) -> Result<Vec<String>, (String, usize)> {
+        let start_line = self.line;
+        let mut row = vec![];
+        let mut field = String::new();
+        let mut chars = first_line
+            .chars()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable();
+        loop {
+            // at the start of a field
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('"') => match chars.peek() {
+                            Some('"') => {
+                                chars.next();
+                                field.push('"');
+                            }
+                            Some(ch) if *ch == self.delimiter => break,
+                            None => break,
+                            Some(ch) => {
+                                return Err((
+                                    format!("unexpected character `{ch}` after closing quote"),
+                                    self.line,
+                                ))
+                            }
+                        },
+                        Some(ch) => field.push(ch),
+                        None => {
+                            // the quoted field contains a line break
+                            if let Some(next) = self.next_line() {
+                                chars = next.chars().collect::<Vec<_>>().into_iter().peekable();
+                                field.push('\n');
+                            } else {
+                                return Err((
+                                    "quoted field is never closed".to_owned(),
+                                    start_line,
+                                ));
+                            }
+                        }
+                    }
+                }
+            } else {
+                while let Some(ch) = chars.peek() {
+                    if *ch == self.delimiter {
+                        break;
+                    } else if *ch == '"' {
+                        return Err((
+                            "quote in an unquoted field (fields containing quotes must be quoted, with quotes written as `\"\"`)".to_owned(),
+                            self.line,
+                        ));
+                    }
+                    field.push(*ch);
+                    chars.next();
+                }
+            }
+            row.push(std::mem::take(&mut field));
+            // either a delimiter or the end of the line
+            if chars.next().is_none() {
+                return Ok(row);
+            }
+        }
+    }
+}
+impl<L: Iterator<Item = String>> Iterator for CsvReader<L> {
+    type Item = Result<Vec<String>, (String, usize)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let line = loop {
+            let line = self.next_line()?;
+            if !line.is_empty() {
+                break line;
+            }
+        };
+        let row = self.parse_row(line);
+        self.failed = row.is_err();
+        Some(row)
+    }
+}
+
+fn write_row(out: &mut String, row: &[String], delimiter: char) {
+    for (i, field) in row.iter().enumerate() {
+        if i > 0 {
+            out.push(delimiter);
+        }
+        if field.contains([delimiter, '"', '\n', '\r']) || (row.len() == 1 && field.is_empty()) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push('\n');
+}
+
+fn is_strings(t: &[Type]) -> bool {
+    t.iter().all(|t| t.is_included_in_single(&StringT))
+}
+fn is_source(t: &dyn MersType) -> bool {
+    t.as_any().is::<StringT>() || t.as_any().is::<FileT>() || t.as_any().is::<LinesT>()
+}
+/// returns an error (for `CsvFields::error_data`) if the delimiter is invalid
+fn delimiter_arg(a: &Data) -> Result<char, (String, usize)> {
+    let d = string_arg(a);
+    let mut chars = d.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) if ch != '"' && ch != '\n' && ch != '\r' => Ok(ch),
+        _ => Err((
+            format!(
+                "csv delimiter must be a single character (and not a quote or line break), but got {d:?}"
+            ),
+            1,
+        )),
+    }
+}
+fn source_arg(a: &dyn std::any::Any) -> CsvSource {
+    if let Some(s) = a.downcast_ref::<data::string::String>() {
+        CsvSource::Text(Arc::from(s.0.as_str()))
+    } else if let Some(f) = a.downcast_ref::<File>() {
+        CsvSource::Read(ReadSource::File(Arc::clone(&f.0)))
+    } else {
+        CsvSource::Read(a.downcast_ref::<Lines>().unwrap().0.clone())
+    }
+}
+fn row_data(row: Vec<String>) -> Data {
+    Data::new(List(
+        row.into_iter()
+            .map(|f| Data::new(data::string::String(f)))
+            .collect(),
+    ))
+}
+
+/// What a `CsvRows` reads from
+#[derive(Clone, Debug)]
+pub enum CsvSource {
+    /// iterating starts at the beginning of the text every time
+    Text(Arc<str>),
+    /// iterating continues where the previous iteration stopped
+    Read(ReadSource),
+}
+/// Iterates over the rows of a csv source, parsing them only when they are needed
+#[derive(Clone, Debug)]
+pub struct CsvRows(pub CsvSource, pub char, pub CsvFields);
+#[derive(Clone, Debug)]
+pub struct CsvRowsT(pub CsvFields);
+
+impl MersData for CsvRows {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn iterable(
+        &self,
+        _gi: &crate::program::run::RunLocalGlobalInfo,
+    ) -> Option<Box<dyn Iterator<Item = Result<Data, CheckError>>>> {
+        // set when reading from a `ReadSource` fails, reported after the last row
+        let read_error = Arc::new(Mutex::new(None));
+        let lines: Box<dyn Iterator<Item = String>> = match &self.0 {
+            CsvSource::Text(text) => {
+                let text = Arc::clone(text);
+                let pos = Mutex::new(0);
+                Box::new(std::iter::from_fn(move || {
+                    let mut pos = pos.lock().unwrap();
+                    let rest = &text[*pos..];
+                    if rest.is_empty() {
+                        return None;
+                    }
+                    let line = rest.split('\n').next().unwrap_or(rest);
+                    *pos += (line.len() + 1).min(rest.len());
+                    Some(line.strip_suffix('\r').unwrap_or(line).to_owned())
+                }))
+            }
+            CsvSource::Read(source) => {
+                let source = source.clone();
+                let read_error = Arc::clone(&read_error);
+                Box::new(std::iter::from_fn(move || match source.read_line() {
+                    Ok(line) => line,
+                    Err(e) => {
+                        *read_error.lock().unwrap() = Some(e);
+                        None
+                    }
+                }))
+            }
+        };
+        let fields = self.2;
+        Some(Box::new(
+            CsvReader::new(lines, self.1)
+                .map(move |row| match row {
+                    Ok(row) => Ok(row_data(row)),
+                    Err(e) => Ok(fields.error_data(e)),
+                })
+                .chain(std::iter::from_fn(move || {
+                    read_error
+                        .lock()
+                        .unwrap()
+                        .take()
+                        .map(|e| Err(format!("Error while reading a line: {e}").into()))
+                })),
+        ))
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other.as_any().downcast_ref::<Self>().is_some_and(|other| {
+            self.1 == other.1
+                && match (&self.0, &other.0) {
+                    (CsvSource::Text(a), CsvSource::Text(b)) => a == b,
+                    (CsvSource::Read(a), CsvSource::Read(b)) => a.ptr_eq(b),
+                    _ => false,
+                }
+        })
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(CsvRowsT(self.2))
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+
+impl MersType for CsvRowsT {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn iterable(&self) -> Option<Type> {
+        Some(self.0.or_error(Type::new(ListT(Type::new(StringT)))))
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| self.0 == other.0)
+    }
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        self.is_same_type_as(target)
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+
+impl Display for CsvRows {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<CsvRows>")
+    }
+}
+impl Display for CsvRowsT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CsvRows")
+    }
+}
@@ -0,0 +1,243 @@
+use std::{collections::BTreeMap, path::PathBuf, sync::RwLock};
+
+use crate::data::{self, Data, Type};
+
+use super::{
+    gen::{function::func, IntR, IterToList, OneOrNone},
+    util::{self, error_object, or_error},
+    Config,
+};
+
+/// An environment which embedders can use instead of the real process environment,
+/// by setting `RunLocalGlobalInfo::env` to `Some(_)`.
+/// Only the functions from `with_env` use this, other functions (like `fs_read_text`) still use the real environment.
+#[derive(Clone, Debug, Default)]
+pub struct VirtualEnv {
+    pub vars: BTreeMap<String, String>,
+    pub current_dir: PathBuf,
+    pub pid: u32,
+    pub hostname: Option<String>,
+}
+
+impl Config {
+    /// adds functions to access the environment of the process.
+    /// embedders can replace the real environment with a `VirtualEnv`.
+    /// `env_get: fn` returns the value of an environment variable: (String) if it is set, () otherwise. if the value isn't valid unicode, invalid parts are replaced with `�`
+    /// `env_vars: fn` returns all environment variables as a list of (name, value) pairs
+    ///   (like in `env_get`, names and values which aren't valid unicode are converted lossily)
+    /// `env_set: fn` sets an environment variable: (name, value).env_set, returns () or `{env_set_error: String}` if the name is empty or contains `=` or a NUL character, or the value contains a NUL character
+    /// `env_remove: fn` removes an environment variable, returns () or `{env_remove_error: String}` if the name is invalid
+    /// `current_dir: fn` returns the current working directory, or `{current_dir_error: String}`
+    /// `set_current_dir: fn` changes the current working directory, returns () or `{set_current_dir_error: String}`
+    /// `pid: fn` returns the id of the current process
+    /// `hostname: fn` returns the name of the computer: (String), or () if it can't be determined
+    /// `os_name: fn` returns the name of the operating system mers was compiled for, like `linux` or `windows`
+    ///
+    /// Without a `VirtualEnv`, `env_set` and `env_remove` change the environment of the whole process.
+    /// The functions in this group never access the environment at the same time, even from different threads,
+    /// but other code which reads the environment while mers code changes it (like a process being spawned on another thread) may see inconsistent values.
+    /// Embedders which run other code next to mers should use a `VirtualEnv`.
+    pub fn with_env(self) -> Self {
+        self.add_var(
+            "env_get",
+            func(|name: &str, i| {
+                Ok(OneOrNone(
+                    if let Some(env) = &*i.global.env.lock().unwrap() {
+                        env.vars.get(name).cloned()
+                    } else {
+                        let _lock = ENV_LOCK.read().unwrap();
+                        std::env::var_os(name).map(|v| v.to_string_lossy().into_owned())
+                    },
+                ))
+            }),
+        )
+        .add_var(
+            "env_vars",
+            func(|_: (), i| {
+                Ok(IterToList(
+                    if let Some(env) = &*i.global.env.lock().unwrap() {
+                        env.vars.clone().into_iter().collect::<Vec<_>>()
+                    } else {
+                        let _lock = ENV_LOCK.read().unwrap();
+                        // `std::env::vars` would panic if a name or value isn't valid unicode
+                        std::env::vars_os()
+                            .map(|(k, v)| {
+                                (
+                                    k.to_string_lossy().into_owned(),
+                                    v.to_string_lossy().into_owned(),
+                                )
+                            })
+                            .collect()
+                    }
+                    .into_iter(),
+                ))
+            }),
+        )
+        .add_var("env_set", env_change_func("env_set", true))
+        .add_var("env_remove", env_change_func("env_remove", false))
+        .add_var(
+            "current_dir",
+            util::to_mers_func_with_in_type(
+                Type::empty_tuple(),
+                |_a, i| {
+                    Ok(or_error(
+                        Type::new(data::string::StringT),
+                        "current_dir_error",
+                        i,
+                    ))
+                },
+                |_a, i| {
+                    let dir = if let Some(env) = &*i.global.env.lock().unwrap() {
+                        Ok(env.current_dir.clone())
+                    } else {
+                        std::env::current_dir()
+                    };
+                    Ok(match dir {
+                        Ok(dir) => {
+                            Data::new(data::string::String(dir.to_string_lossy().into_owned()))
+                        }
+                        Err(e) => error_object("current_dir_error", e, i),
+                    })
+                },
+            ),
+        )
+        .add_var(
+            "set_current_dir",
+            util::to_mers_func_with_in_type(
+                Type::new(data::string::StringT),
+                |_a, i| Ok(or_error(Type::empty_tuple(), "set_current_dir_error", i)),
+                |a, i| {
+                    let a = a.get();
+                    let dir = &a
+                        .as_any()
+                        .downcast_ref::<data::string::String>()
+                        .expect("got non-string argument to set_current_dir")
+                        .0;
+                    let result = if let Some(env) = &mut *i.global.env.lock().unwrap() {
+                        env.current_dir = env.current_dir.join(dir);
+                        Ok(())
+                    } else {
+                        std::env::set_current_dir(dir)
+                    };
+                    Ok(match result {
+                        Ok(()) => Data::empty_tuple(),
+                        Err(e) => error_object("set_current_dir_error", e, i),
+                    })
+                },
+            ),
+        )
+        .add_var(
+            "pid",
+            func(|_: (), i| {
+                let pid = if let Some(env) = &*i.global.env.lock().unwrap() {
+                    env.pid
+                } else {
+                    std::process::id()
+                };
+                Ok(IntR::<0, { u32::MAX as isize }>(pid as isize))
+            }),
+        )
+        .add_var(
+            "hostname",
+            func(|_: (), i| {
+                Ok(OneOrNone(
+                    if let Some(env) = &*i.global.env.lock().unwrap() {
+                        env.hostname.clone()
+                    } else {
+                        hostname()
+                    },
+                ))
+            }),
+        )
+        .add_var(
+            "os_name",
+            func(|_: (), _| Ok(std::env::consts::OS.to_owned())),
+        )
+    }
+}
+
+/// `with_env`'s functions hold this while they access the real environment of the process
+static ENV_LOCK: RwLock<()> = RwLock::new(());
+
+/// `env_set` (`(name, value)`, if `set`) or `env_remove` (`name`, if not `set`)
+fn env_change_func(name: &'static str, set: bool) -> data::function::Function {
+    let error_field = format!("{name}_error");
+    util::to_mers_func_with_in_type(
+        if set {
+            Type::new(data::tuple::TupleT(vec![
+                Type::new(data::string::StringT),
+                Type::new(data::string::StringT),
+            ]))
+        } else {
+            Type::new(data::string::StringT)
+        },
+        {
+            let error_field = error_field.clone();
+            move |_a, i| Ok(or_error(Type::empty_tuple(), &error_field, i))
+        },
+        move |a, i| {
+            let a = a.get();
+            let string = |d: &dyn data::MersData| {
+                d.as_any()
+                    .downcast_ref::<data::string::String>()
+                    .unwrap()
+                    .0
+                    .clone()
+            };
+            let (var, value) = if let Some(t) = a.as_any().downcast_ref::<data::tuple::Tuple>() {
+                (
+                    string(t.0[0].read().get().as_ref()),
+                    Some(string(t.0[1].read().get().as_ref())),
+                )
+            } else {
+                (string(a.as_ref()), None)
+            };
+            if let Err(e) = check_env_var(&var, value.as_deref()) {
+                return Ok(error_object(&error_field, e, i));
+            }
+            if let Some(env) = &mut *i.global.env.lock().unwrap() {
+                if let Some(value) = value {
+                    env.vars.insert(var, value);
+                } else {
+                    env.vars.remove(&var);
+                }
+            } else {
+                let _lock = ENV_LOCK.write().unwrap();
+                if let Some(value) = value {
+                    std::env::set_var(var, value);
+                } else {
+                    std::env::remove_var(var);
+                }
+            }
+            Ok(Data::empty_tuple())
+        },
+    )
+}
+
+/// `set_var` and `remove_var` panic for these names and values
+fn check_env_var(name: &str, value: Option<&str>) -> Result<(), String> {
+    if name.is_empty() {
+        Err("the name of an environment variable can't be empty".to_owned())
+    } else if name.contains(['=', '\0']) {
+        Err(format!(
+            "the name of an environment variable can't contain `=` or NUL characters, but was {name:?}"
+        ))
+    } else if value.is_some_and(|v| v.contains('\0')) {
+        Err(format!(
+            "the value of the environment variable {name} can't contain NUL characters"
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn hostname() -> Option<String> {
+    // the standard library can't get the hostname, but these work on most systems
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+}
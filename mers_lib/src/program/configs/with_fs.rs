@@ -1,17 +1,46 @@
-use std::sync::Arc;
+use std::{
+    fmt::{Debug, Display},
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
-use crate::data::{
-    self,
-    function::Function,
-    object::{Object, ObjectFieldsMap, ObjectT},
-    string::StringT,
-    tuple::{Tuple, TupleT},
-    Data, Type,
+use crate::{
+    data::{
+        self,
+        function::Function,
+        int::{IntT, INT_MAX},
+        object::{Object, ObjectFieldsMap, ObjectT},
+        string::StringT,
+        tuple::{Tuple, TupleT},
+        Data, MersData, MersType, Type,
+    },
+    errors::CheckError,
+    info::DisplayInfo,
 };
 
-use super::Config;
+use super::{
+    gen::{function::func, IterToList, OneOrNone},
+    with_list::{List, ListT},
+    Config,
+};
 
 impl Config {
+    /// `fs_read_text: fn` reads a file and returns its contents as a String
+    /// `fs_write: fn` (path, content).fs_write writes a String to a file, replacing its contents
+    /// `fs_open: fn` opens a file for reading and returns a `File`
+    /// `fs_lines: fn` returns an iterable over the lines of a `File`. lines are read lazily, while iterating.
+    /// `fs_chunks: fn` (file, n).fs_chunks returns an iterable over the bytes of a `File`, n bytes (a `List<Byte>`) at a time. only the last chunk can be shorter.
+    ///   (`fs_lines` and `fs_chunks` both read from the same `File`, so iterating twice continues where the previous iteration stopped)
+    /// `path_join: fn` (a, b).path_join joins two paths. if b is absolute, returns b.
+    /// `path_parent: fn` returns (parent) or () if the path has no parent
+    /// `path_file_name: fn` returns (file name) or () if the path ends in `..` or is a root
+    /// `path_extension: fn` returns (extension) (without the `.`), or () if the file name has no extension
+    /// `path_with_extension: fn` (path, ext).path_with_extension replaces the extension (or adds one) and returns (path), or () if ext contains a path separator. use "" to remove the extension.
+    /// `path_is_absolute: fn` returns true for absolute paths
+    /// `path_canonicalize: fn` returns the absolute path with all `.`, `..` and symlinks resolved, or `{path_error: String}` (for example if the path doesn't exist)
+    /// `path_components: fn` returns a `List<String>` of the parts of a path
+    /// (to find files relative to the script, use `#source_dir`, which is the directory containing the source file)
     pub fn with_fs(self) -> Self {
         self.add_var(
             "fs_read_text",
@@ -96,5 +125,563 @@ impl Config {
                 },
             ),
         )
+        .add_var(
+            "path_join",
+            func(|(a, b): (&str, &str), _| Ok(Path::new(a).join(b).to_string_lossy().into_owned())),
+        )
+        .add_var(
+            "path_parent",
+            func(|p: &str, _| {
+                Ok(OneOrNone(
+                    Path::new(p)
+                        .parent()
+                        .map(|p| p.to_string_lossy().into_owned()),
+                ))
+            }),
+        )
+        .add_var(
+            "path_file_name",
+            func(|p: &str, _| {
+                Ok(OneOrNone(
+                    Path::new(p)
+                        .file_name()
+                        .map(|p| p.to_string_lossy().into_owned()),
+                ))
+            }),
+        )
+        .add_var(
+            "path_extension",
+            func(|p: &str, _| {
+                Ok(OneOrNone(
+                    Path::new(p)
+                        .extension()
+                        .map(|p| p.to_string_lossy().into_owned()),
+                ))
+            }),
+        )
+        .add_var(
+            "path_with_extension",
+            func(|(p, ext): (&str, &str), _| {
+                // `Path::with_extension` panics if the extension contains a separator
+                Ok(OneOrNone(if ext.chars().any(std::path::is_separator) {
+                    None
+                } else {
+                    Some(
+                        Path::new(p)
+                            .with_extension(ext)
+                            .to_string_lossy()
+                            .into_owned(),
+                    )
+                }))
+            }),
+        )
+        .add_var(
+            "path_is_absolute",
+            func(|p: &str, _| Ok(Path::new(p).is_absolute())),
+        )
+        .add_var(
+            "path_components",
+            func(|p: &str, _| {
+                Ok(IterToList(
+                    Path::new(p)
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                ))
+            }),
+        )
+        .add_var(
+            "path_canonicalize",
+            Function::new_generic(
+                |a, i| {
+                    if a.is_included_in_single(&StringT) {
+                        Ok(Type::newm(vec![
+                            Arc::new(StringT),
+                            Arc::new(ObjectT::new(vec![(
+                                i.global.object_fields.get_or_add_field("path_error"),
+                                Type::new(data::string::StringT),
+                            )])),
+                        ]))
+                    } else {
+                        Err(format!(
+                            "Called path_canonicalize with argument type {}, but expected String",
+                            a.with_info(i)
+                        ))?
+                    }
+                },
+                |a, i| {
+                    let a = a.get();
+                    let a = a
+                        .as_any()
+                        .downcast_ref::<data::string::String>()
+                        .expect("got non-string argument to path_canonicalize");
+                    Ok(match std::fs::canonicalize(&a.0) {
+                        Ok(path) => {
+                            Data::new(data::string::String(path.to_string_lossy().into_owned()))
+                        }
+                        Err(e) => Data::new(Object::new(vec![(
+                            i.global.object_fields.get_or_add_field("path_error"),
+                            Data::new(data::string::String(e.to_string())),
+                        )])),
+                    })
+                },
+            ),
+        )
+        .add_type("File".to_owned(), Ok(Arc::new(Type::new(FileT))))
+        .add_type("Lines".to_owned(), Ok(Arc::new(Type::new(LinesT))))
+        .add_type("Chunks".to_owned(), Ok(Arc::new(Type::new(ChunksT))))
+        .add_var(
+            "fs_open",
+            Function::new_generic(
+                |a, i| {
+                    if a.is_included_in_single(&StringT) {
+                        Ok(Type::newm(vec![
+                            Arc::new(FileT),
+                            Arc::new(ObjectT::new(vec![(
+                                i.global.object_fields.get_or_add_field("fs_read_error"),
+                                Type::new(data::string::StringT),
+                            )])),
+                        ]))
+                    } else {
+                        Err(format!(
+                            "Called fs_open with argument type {}, but expected String",
+                            a.with_info(i)
+                        ))?
+                    }
+                },
+                |a, i| {
+                    let a = a.get();
+                    let a = a
+                        .as_any()
+                        .downcast_ref::<data::string::String>()
+                        .expect("got non-string argument to fs_open");
+                    Ok(match std::fs::File::open(&a.0) {
+                        Ok(file) => Data::new(File(Arc::new(Mutex::new(BufReader::new(file))))),
+                        Err(e) => Data::new(Object::new(vec![(
+                            i.global.object_fields.get_or_add_field("fs_read_error"),
+                            Data::new(data::string::String(e.to_string())),
+                        )])),
+                    })
+                },
+            ),
+        )
+        .add_var(
+            "fs_lines",
+            Function::new_generic(
+                |a, i| {
+                    if a.is_included_in_single(&FileT) {
+                        Ok(Type::new(LinesT))
+                    } else {
+                        Err(format!(
+                            "Called fs_lines with argument type {}, but expected File",
+                            a.with_info(i)
+                        ))?
+                    }
+                },
+                |a, _i| {
+                    let a = a.get();
+                    let file = a
+                        .as_any()
+                        .downcast_ref::<File>()
+                        .expect("got non-file argument to fs_lines");
+                    Ok(Data::new(Lines(ReadSource::File(Arc::clone(&file.0)))))
+                },
+            ),
+        )
+        .add_var(
+            "fs_chunks",
+            Function::new_generic(
+                |a, i| {
+                    if a.is_included_in_single(&TupleT(vec![
+                        Type::new(FileT),
+                        Type::new(IntT(1, INT_MAX)),
+                    ])) {
+                        Ok(Type::new(ChunksT))
+                    } else {
+                        Err(format!(
+                            "Called fs_chunks with argument type {}, but expected (File, Int<1..>)",
+                            a.with_info(i)
+                        ))?
+                    }
+                },
+                |a, _i| {
+                    let a = a.get();
+                    let a = a
+                        .as_any()
+                        .downcast_ref::<Tuple>()
+                        .expect("got non-tuple argument to fs_chunks");
+                    let (file, n) = (a.0[0].read(), a.0[1].read());
+                    let (file, n) = (file.get(), n.get());
+                    let file = file
+                        .as_any()
+                        .downcast_ref::<File>()
+                        .expect("got non-file argument to fs_chunks");
+                    let n = n
+                        .as_any()
+                        .downcast_ref::<data::int::Int>()
+                        .expect("chunk size was not an int in fs_chunks");
+                    Ok(Data::new(Chunks(
+                        ReadSource::File(Arc::clone(&file.0)),
+                        n.0 as usize,
+                    )))
+                },
+            ),
+        )
+    }
+}
+
+/// Something `Lines` and `Chunks` can read from
+#[derive(Clone)]
+pub enum ReadSource {
+    /// the stdin from `RunLocalGlobalInfo`, or the process's stdin if that is `None`
+    Stdin(Arc<Mutex<Option<Box<dyn Read + Send + Sync>>>>),
+    File(Arc<Mutex<BufReader<std::fs::File>>>),
+}
+impl ReadSource {
+    /// reads one line, without the line break. returns `None` at the end of the input.
+    pub fn read_line(&self) -> std::io::Result<Option<String>> {
+        let mut buf = vec![];
+        match self {
+            Self::Stdin(stdin) => {
+                if let Some(stdin) = &mut *stdin.lock().unwrap() {
+                    // no BufReader, because it could read (and then drop) more than one line
+                    let mut byte = [0];
+                    loop {
+                        match stdin.read(&mut byte) {
+                            Ok(0) => break,
+                            Ok(_) => {
+                                buf.push(byte[0]);
+                                if byte[0] == b'\n' {
+                                    break;
+                                }
+                            }
+                            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                            Err(e) => return Err(e),
+                        }
+                    }
+                } else {
+                    std::io::stdin().lock().read_until(b'\n', &mut buf)?;
+                }
+            }
+            Self::File(file) => {
+                file.lock().unwrap().read_until(b'\n', &mut buf)?;
+            }
+        }
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&buf).into_owned()))
+    }
+    /// reads up to `max` bytes, less only at the end of the input
+    pub fn read_chunk(&self, max: usize) -> std::io::Result<Vec<u8>> {
+        let mut buf = vec![];
+        match self {
+            Self::Stdin(stdin) => {
+                if let Some(stdin) = &mut *stdin.lock().unwrap() {
+                    stdin.take(max as u64).read_to_end(&mut buf)?;
+                } else {
+                    std::io::stdin()
+                        .lock()
+                        .take(max as u64)
+                        .read_to_end(&mut buf)?;
+                }
+            }
+            Self::File(file) => {
+                file.lock()
+                    .unwrap()
+                    .by_ref()
+                    .take(max as u64)
+                    .read_to_end(&mut buf)?;
+            }
+        }
+        Ok(buf)
+    }
+    /// reads everything until the end of the input
+    pub fn read_all(&self) -> std::io::Result<Vec<u8>> {
+        self.read_chunk(usize::MAX)
+    }
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Stdin(a), Self::Stdin(b)) => Arc::ptr_eq(a, b),
+            (Self::File(a), Self::File(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+impl Debug for ReadSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stdin(_) => write!(f, "Stdin"),
+            Self::File(_) => write!(f, "File"),
+        }
+    }
+}
+
+/// A file opened for reading
+#[derive(Clone, Debug)]
+pub struct File(pub Arc<Mutex<BufReader<std::fs::File>>>);
+#[derive(Clone, Debug)]
+pub struct FileT;
+/// Iterates over lines, reading them only when they are needed
+#[derive(Clone, Debug)]
+pub struct Lines(pub ReadSource);
+#[derive(Clone, Debug)]
+pub struct LinesT;
+/// Iterates over chunks of up to `.1` bytes, reading them only when they are needed
+#[derive(Clone, Debug)]
+pub struct Chunks(pub ReadSource, pub usize);
+#[derive(Clone, Debug)]
+pub struct ChunksT;
+
+impl MersData for File {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| Arc::ptr_eq(&self.0, &other.0))
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(FileT)
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl MersData for Lines {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn iterable(
+        &self,
+        _gi: &crate::program::run::RunLocalGlobalInfo,
+    ) -> Option<Box<dyn Iterator<Item = Result<Data, CheckError>>>> {
+        let source = self.0.clone();
+        let mut failed = false;
+        Some(Box::new(std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            match source.read_line() {
+                Ok(line) => line.map(|line| Ok(Data::new(data::string::String(line)))),
+                Err(e) => {
+                    failed = true;
+                    Some(Err(format!("Error while reading a line: {e}").into()))
+                }
+            }
+        })))
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| self.0.ptr_eq(&other.0))
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(LinesT)
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl MersData for Chunks {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn iterable(
+        &self,
+        _gi: &crate::program::run::RunLocalGlobalInfo,
+    ) -> Option<Box<dyn Iterator<Item = Result<Data, CheckError>>>> {
+        let (source, size) = (self.0.clone(), self.1);
+        let mut failed = false;
+        Some(Box::new(std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let chunk = match source.read_chunk(size) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    failed = true;
+                    return Some(Err(format!("Error while reading a chunk: {e}").into()));
+                }
+            };
+            if chunk.is_empty() {
+                None
+            } else {
+                Some(Ok(Data::new(List(
+                    chunk
+                        .into_iter()
+                        .map(|b| Data::new(data::byte::Byte(b)))
+                        .collect(),
+                ))))
+            }
+        })))
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| self.0.ptr_eq(&other.0) && self.1 == other.1)
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(ChunksT)
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+
+impl MersType for FileT {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other.as_any().is::<Self>()
+    }
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        target.as_any().is::<Self>()
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl MersType for LinesT {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn iterable(&self) -> Option<Type> {
+        Some(Type::new(StringT))
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other.as_any().is::<Self>()
+    }
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        target.as_any().is::<Self>()
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl MersType for ChunksT {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn iterable(&self) -> Option<Type> {
+        Some(Type::new(ListT(Type::new(data::byte::ByteT))))
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other.as_any().is::<Self>()
+    }
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        target.as_any().is::<Self>()
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+
+impl Display for File {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<File>")
+    }
+}
+impl Display for Lines {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Lines>")
+    }
+}
+impl Display for Chunks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Chunks>")
+    }
+}
+impl Display for FileT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "File")
+    }
+}
+impl Display for LinesT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Lines")
+    }
+}
+impl Display for ChunksT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Chunks")
     }
 }
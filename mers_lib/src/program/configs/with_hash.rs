@@ -0,0 +1,105 @@
+use crate::{
+    data::{self, Data, Type},
+    errors::CheckError,
+    program::run::{CheckInfo, Info},
+};
+
+use super::{
+    util::{self, bytes_arg, check_bytes_arg},
+    with_list::{List, ListT},
+    Config,
+};
+
+impl Config {
+    /// hashes and checksums. every function accepts a `String` (hashing its UTF-8 bytes) or an iterable over `Byte`s,
+    /// and returns the hash as a string of lowercase hex digits. the `_bytes` variants return a `List<Byte>` instead.
+    /// `hash_sha256: fn`, `hash_sha256_bytes: fn` SHA-256
+    /// `hash_sha1: fn`, `hash_sha1_bytes: fn` SHA-1 (not secure, only use it for compatibility with existing checksums)
+    /// `hash_md5: fn`, `hash_md5_bytes: fn` MD5 (not secure, only use it for compatibility with existing checksums)
+    /// `crc32: fn`, `crc32_bytes: fn` CRC-32 (as used by zip and png), big-endian
+    /// `hash_fnv1a: fn`, `hash_fnv1a_bytes: fn` 64-bit FNV-1a, a fast non-cryptographic hash. its output never changes between mers versions or platforms, so it can be used for caching.
+    pub fn with_hash(self) -> Self {
+        self.add_hash("hash_sha256", |b| {
+            use sha2::Digest;
+            sha2::Sha256::digest(b).to_vec()
+        })
+        .add_hash("hash_sha1", |b| {
+            use sha1::Digest;
+            sha1::Sha1::digest(b).to_vec()
+        })
+        .add_hash("hash_md5", |b| {
+            use md5::Digest;
+            md5::Md5::digest(b).to_vec()
+        })
+        .add_hash("crc32", |b| crc32fast::hash(b).to_be_bytes().to_vec())
+        .add_hash("hash_fnv1a", |b| fnv1a(b).to_be_bytes().to_vec())
+    }
+
+    /// adds `name` (returning a hex string) and `name_bytes` (returning a `List<Byte>`)
+    fn add_hash(self, name: &'static str, hash: fn(&[u8]) -> Vec<u8>) -> Self {
+        self.add_var(
+            name,
+            util::to_mers_func(
+                move |a, i| {
+                    check_hash_input(a, name, i)?;
+                    Ok(Type::new(data::string::StringT))
+                },
+                move |a, i| {
+                    let mut out = String::new();
+                    for b in hash(&hash_input(&a, i)?) {
+                        out.push_str(&format!("{b:02x}"));
+                    }
+                    Ok(Data::new(data::string::String(out)))
+                },
+            ),
+        )
+        .add_var(
+            format!("{name}_bytes"),
+            util::to_mers_func(
+                move |a, i| {
+                    check_hash_input(a, name, i)?;
+                    Ok(Type::new(ListT(Type::new(data::byte::ByteT))))
+                },
+                move |a, i| {
+                    Ok(Data::new(List(
+                        hash(&hash_input(&a, i)?)
+                            .into_iter()
+                            .map(|b| Data::new(data::byte::Byte(b)))
+                            .collect(),
+                    )))
+                },
+            ),
+        )
+    }
+}
+
+/// `String` or an iterable over `Byte`s
+fn check_hash_input(a: &Type, func: &str, i: &CheckInfo) -> Result<(), CheckError> {
+    let not_strings = Type::newm(
+        a.types
+            .iter()
+            .filter(|t| !t.as_any().is::<data::string::StringT>())
+            .cloned()
+            .collect(),
+    );
+    if not_strings.types.is_empty() {
+        Ok(())
+    } else {
+        check_bytes_arg(&not_strings, func, i)
+    }
+}
+fn hash_input(a: &Data, i: &Info) -> Result<Vec<u8>, CheckError> {
+    if let Some(s) = a.get().as_any().downcast_ref::<data::string::String>() {
+        return Ok(s.0.as_bytes().to_vec());
+    }
+    bytes_arg(a, i)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
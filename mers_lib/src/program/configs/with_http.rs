@@ -0,0 +1,608 @@
+use std::{
+    fmt::Display,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use crate::{
+    data::{
+        self,
+        int::{INT_MAX, INT_MIN},
+        object::{Object, ObjectFieldsMap, ObjectT},
+        Data, MersData, MersTypeWInfo, Type,
+    },
+    errors::CheckError,
+    program::run::{chain::run_callback, CheckInfo, Info, RunLocalGlobalInfo},
+};
+
+use super::{
+    util::{self, error_object, or_error, string_arg},
+    with_list::{List, ListT},
+    with_net::{TcpListener, TcpListenerT},
+    Config,
+};
+
+/// the default for `Config::set_http_max_body_size`: 16 MiB
+pub const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+/// the default for `Config::set_http_serve_timeout`: 30 seconds
+pub const DEFAULT_SERVE_TIMEOUT: Duration = Duration::from_secs(30);
+/// the maximum size of the request/status line and headers of a message: 64 KiB
+pub const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+impl Config {
+    /// a minimal HTTP/1.1 client and server (plain http only, no https). functions which can fail return `{http_error: String}` on failure.
+    /// `http_request: fn` (method, url, headers, body).http_request sends a request and waits for the response.
+    ///   headers is an iterable over (name, value) tuples. returns `{status: Int, headers: List<(String, String)>, body: String}`.
+    ///   example: ("GET", "http://127.0.0.1:8080/", (), "").http_request
+    /// `http_serve: fn` (address, handler).http_serve accepts connections and calls the handler for each request.
+    ///   the handler gets `{method: String, path: String, headers: List<(String, String)>, body: String}`
+    ///   and must return `{status: Int, body: String}`, optionally with a `headers` field containing an iterable over (name, value) tuples.
+    ///   address can be a String or a `TcpListener` (see `with_net`). if it is a `TcpListener`, `http_serve` returns once the listener is closed,
+    ///   so a handler can stop the server after responding by calling `net_close` on the listener.
+    ///   requests with a body larger than `Config::set_http_max_body_size` get a `413 Payload Too Large` response without calling the handler,
+    ///   requests whose headers are larger than `MAX_HEAD_SIZE` get a `431 Request Header Fields Too Large` response,
+    ///   and clients which don't send their request within `Config::set_http_serve_timeout` get a `408 Request Timeout` response.
+    ///   if the handler returns an invalid header name, a value which contains a line break, or a status outside of `100..=999`,
+    ///   the client gets a `500 Internal Server Error` response.
+    /// header names and values must not contain line breaks, and bodies of responses are limited to the same size as bodies of requests.
+    pub fn with_http(self) -> Self {
+        self.add_var(
+            "http_request",
+            util::to_mers_func(
+                |a, i| {
+                    for t in a.types.iter() {
+                        let t = t
+                            .as_any()
+                            .downcast_ref::<data::tuple::TupleT>()
+                            .filter(|t| {
+                                t.0.len() == 4
+                                    && t.0[0].is_included_in_single(&data::string::StringT)
+                                    && t.0[1].is_included_in_single(&data::string::StringT)
+                                    && t.0[3].is_included_in_single(&data::string::StringT)
+                            })
+                            .ok_or_else(|| {
+                                format!(
+                                    "http_request: expected (method: String, url: String, headers, body: String), but got {}",
+                                    a.with_info(i)
+                                )
+                            })?;
+                        check_headers(&t.0[2], "http_request", i)?;
+                    }
+                    Ok(or_error(
+                        Type::new(ObjectT::new(vec![
+                            (
+                                i.global.object_fields.get_or_add_field("status"),
+                                Type::new(data::int::IntT(INT_MIN, INT_MAX)),
+                            ),
+                            (
+                                i.global.object_fields.get_or_add_field("headers"),
+                                headers_type(),
+                            ),
+                            (
+                                i.global.object_fields.get_or_add_field("body"),
+                                Type::new(data::string::StringT),
+                            ),
+                        ])),
+                        "http_error",
+                        i,
+                    ))
+                },
+                |a, i| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                    let method = string_arg(&a[0].read());
+                    let url = string_arg(&a[1].read());
+                    let headers = headers_arg(&a[2].read(), &i.global)?;
+                    let body = string_arg(&a[3].read());
+                    Ok(match request(&method, &url, &headers, &body, i.global.http_max_body_size) {
+                        Ok((status, headers, body)) => Data::new(Object::new(vec![
+                            (
+                                i.global.object_fields.get_or_add_field("status"),
+                                Data::new(data::int::Int(status)),
+                            ),
+                            (
+                                i.global.object_fields.get_or_add_field("headers"),
+                                headers_data(headers),
+                            ),
+                            (
+                                i.global.object_fields.get_or_add_field("body"),
+                                Data::new(data::string::String(body)),
+                            ),
+                        ])),
+                        Err(e) => error_object("http_error", e, i),
+                    })
+                },
+            ),
+        )
+        .add_var(
+            "http_serve",
+            util::to_mers_func(
+                |a, i| {
+                    let request_type = Type::new(ObjectT::new(vec![
+                        (
+                            i.global.object_fields.get_or_add_field("method"),
+                            Type::new(data::string::StringT),
+                        ),
+                        (
+                            i.global.object_fields.get_or_add_field("path"),
+                            Type::new(data::string::StringT),
+                        ),
+                        (
+                            i.global.object_fields.get_or_add_field("headers"),
+                            headers_type(),
+                        ),
+                        (
+                            i.global.object_fields.get_or_add_field("body"),
+                            Type::new(data::string::StringT),
+                        ),
+                    ]));
+                    for t in a.types.iter() {
+                        let t = t
+                            .as_any()
+                            .downcast_ref::<data::tuple::TupleT>()
+                            .filter(|t| {
+                                t.0.len() == 2
+                                    && t.0[0].types.iter().all(|t| {
+                                        t.as_any().is::<data::string::StringT>()
+                                            || t.as_any().is::<TcpListenerT>()
+                                    })
+                            })
+                            .ok_or_else(|| {
+                                format!(
+                                    "http_serve: expected (String/TcpListener, handler), but got {}",
+                                    a.with_info(i)
+                                )
+                            })?;
+                        for f in t.0[1].types.iter() {
+                            let f = f.executable().ok_or_else(|| {
+                                format!("http_serve: handler {} is not a function", f.with_info(i))
+                            })?;
+                            check_response(&f.o(&request_type)?, i)?;
+                        }
+                    }
+                    Ok(or_error(Type::empty_tuple(), "http_error", i))
+                },
+                |a, i| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                    let listener = a[0].read();
+                    let listener = listener.get();
+                    let listener = if let Some(addr) =
+                        listener.as_any().downcast_ref::<data::string::String>()
+                    {
+                        match std::net::TcpListener::bind(&addr.0) {
+                            Ok(l) => TcpListener::new(l),
+                            Err(e) => return Ok(error_object("http_error", e, i)),
+                        }
+                    } else {
+                        listener.as_any().downcast_ref::<TcpListener>().cloned().unwrap()
+                    };
+                    let handler = a[1].read();
+                    let handler = handler.get();
+                    // if accepting fails (for example, because there are too many open files), wait before trying again
+                    let mut backoff = Duration::ZERO;
+                    // stop once the listener was closed
+                    while let Ok(l) = listener.with(|l| l.try_clone()) {
+                        match l.accept() {
+                            Ok((stream, _)) => {
+                                backoff = Duration::ZERO;
+                                serve_connection(stream, &**handler, i)?;
+                            }
+                            Err(_) => {
+                                backoff = (backoff * 2).clamp(Duration::from_millis(10), Duration::from_secs(1));
+                                std::thread::sleep(backoff);
+                            }
+                        }
+                    }
+                    Ok(Data::empty_tuple())
+                },
+            ),
+        )
+    }
+}
+
+/// `List<(String, String)>`
+fn headers_type() -> Type {
+    Type::new(ListT(Type::new(data::tuple::TupleT(vec![
+        Type::new(data::string::StringT),
+        Type::new(data::string::StringT),
+    ]))))
+}
+fn check_headers(t: &Type, func: &str, i: &CheckInfo) -> Result<(), CheckError> {
+    if t.iterable().is_some_and(|t| {
+        t.is_included_in_single(&data::tuple::TupleT(vec![
+            Type::new(data::string::StringT),
+            Type::new(data::string::StringT),
+        ]))
+    }) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{func}: headers must be an iterable over (String, String), but got {}",
+            t.with_info(i)
+        )
+        .into())
+    }
+}
+/// checks that the handler's return type is `{status: Int, body: String}`, with an optional `headers` field
+fn check_response(t: &Type, i: &CheckInfo) -> Result<(), CheckError> {
+    let status = i.global.object_fields.get_or_add_field("status");
+    let body = i.global.object_fields.get_or_add_field("body");
+    let headers = i.global.object_fields.get_or_add_field("headers");
+    for r in t.types.iter() {
+        let ok = r.as_any().downcast_ref::<ObjectT>().is_some_and(|r| {
+            r.get(status)
+                .is_some_and(|t| t.is_included_in_single(&data::int::IntT(INT_MIN, INT_MAX)))
+                && r.get(body)
+                    .is_some_and(|t| t.is_included_in_single(&data::string::StringT))
+        });
+        if !ok {
+            return Err(format!(
+                "http_serve: handler must return {{status: Int, body: String}} (and optionally headers), but may return {}",
+                r.with_info(i)
+            )
+            .into());
+        }
+        if let Some(h) = r.as_any().downcast_ref::<ObjectT>().unwrap().get(headers) {
+            check_headers(h, "http_serve", i)?;
+        }
+    }
+    Ok(())
+}
+fn headers_arg(a: &Data, gi: &RunLocalGlobalInfo) -> Result<Vec<(String, String)>, CheckError> {
+    a.get()
+        .iterable(gi)
+        .unwrap()
+        .map(|h| {
+            let h = h?;
+            let h = h.get();
+            let h = &h.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+            let name = string_arg(&h[0].read());
+            let value = string_arg(&h[1].read());
+            Ok((name, value))
+        })
+        .collect()
+}
+fn headers_data(headers: Vec<(String, String)>) -> Data {
+    Data::new(List(
+        headers
+            .into_iter()
+            .map(|(k, v)| {
+                Data::new(data::tuple::Tuple::from([
+                    Data::new(data::string::String(k)),
+                    Data::new(data::string::String(v)),
+                ]))
+            })
+            .collect(),
+    ))
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+/// the error `read_body` returns if the body is larger than the limit
+#[derive(Debug)]
+struct BodyTooLarge(usize);
+impl Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the body is larger than the limit of {} bytes", self.0)
+    }
+}
+impl std::error::Error for BodyTooLarge {}
+/// the error `read_head` returns if the first line and the headers are larger than `MAX_HEAD_SIZE`
+#[derive(Debug)]
+struct HeadTooLarge;
+impl Display for HeadTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the headers are larger than the limit of {MAX_HEAD_SIZE} bytes"
+        )
+    }
+}
+impl std::error::Error for HeadTooLarge {}
+/// a read timeout is reported as `WouldBlock` on unix and as `TimedOut` on windows
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+/// header names and values which would change the structure of a message (request or response splitting) are rejected
+fn check_header(name: &str, value: &str) -> io::Result<()> {
+    if name.is_empty()
+        || name.contains(|ch: char| ch == ':' || ch.is_whitespace() || ch.is_control())
+    {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid header name {name:?}"),
+        ))
+    } else if value.contains(['\r', '\n', '\0']) {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the value of the header {name} contains a line break or NUL character"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+type Headers = Vec<(String, String)>;
+
+/// sends a request and returns (status, headers, body)
+fn request(
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: &str,
+    max_body_size: usize,
+) -> io::Result<(isize, Headers, String)> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("only http:// urls are supported, not {url:?}"),
+        )
+    })?;
+    let (host, path) = match rest.find(['/', '?']) {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let path = if path.starts_with('?') {
+        format!("/{path}")
+    } else {
+        path.to_owned()
+    };
+    // `host:port`, `[::1]:port`, or just a host, which uses port 80
+    let addr = if host.rsplit_once(':').is_some_and(|(_, p)| !p.contains(']')) {
+        host.to_owned()
+    } else {
+        format!("{host}:80")
+    };
+    if method.is_empty() || method.contains(|ch: char| ch.is_whitespace() || ch.is_control()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid method {method:?}"),
+        ));
+    }
+    if rest.contains(|ch: char| ch.is_whitespace() || ch.is_control()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid url {url:?}"),
+        ));
+    }
+    for (k, v) in headers {
+        check_header(k, v)?;
+    }
+    let mut stream = TcpStream::connect(addr)?;
+    let mut head = format!("{method} {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n");
+    let has_header = |name: &str| headers.iter().any(|(k, _)| k.eq_ignore_ascii_case(name));
+    if (!body.is_empty() || !matches!(method, "GET" | "HEAD")) && !has_header("content-length") {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    for (k, v) in headers {
+        head.push_str(&format!("{k}: {v}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()?;
+    let mut reader = BufReader::new(stream);
+    let (status_line, headers) = read_head(&mut reader)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid(format!("invalid status line {status_line:?}")))?;
+    let body = if method == "HEAD" || status == 204 || status == 304 || (100..200).contains(&status)
+    {
+        vec![]
+    } else {
+        read_body(&mut reader, &headers, true, max_body_size)?
+    };
+    Ok((status, headers, String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// reads the first line and the headers of a request or response.
+/// if they are larger than `MAX_HEAD_SIZE` bytes, returns a `HeadTooLarge` error.
+fn read_head(reader: &mut impl BufRead) -> io::Result<(String, Vec<(String, String)>)> {
+    let mut reader = Read::take(reader, MAX_HEAD_SIZE as u64);
+    let mut read_line = |line: &mut String| {
+        let len = reader.read_line(line)?;
+        if reader.limit() == 0 && !line.ends_with('\n') {
+            Err(io::Error::new(io::ErrorKind::InvalidData, HeadTooLarge))
+        } else {
+            Ok(len)
+        }
+    };
+    let mut first = String::new();
+    if read_line(&mut first)? == 0 {
+        return Err(invalid("connection closed before a message was received"));
+    }
+    let mut headers = vec![];
+    loop {
+        let mut line = String::new();
+        if read_line(&mut line)? == 0 {
+            return Err(invalid("connection closed while reading headers"));
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        let (k, v) = line
+            .split_once(':')
+            .ok_or_else(|| invalid(format!("invalid header line {line:?}")))?;
+        headers.push((k.trim().to_owned(), v.trim().to_owned()));
+    }
+    Ok((first.trim_end_matches(['\r', '\n']).to_owned(), headers))
+}
+/// reads a chunked or `Content-Length` body. if there is neither and `until_eof` is true, reads until the connection is closed.
+/// bodies larger than `max_size` bytes cause a `BodyTooLarge` error.
+fn read_body(
+    reader: &mut impl BufRead,
+    headers: &[(String, String)],
+    until_eof: bool,
+    max_size: usize,
+) -> io::Result<Vec<u8>> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    };
+    let too_large = || io::Error::new(io::ErrorKind::InvalidData, BodyTooLarge(max_size));
+    // reads exactly `len` bytes, without allocating more than what was actually received
+    let read_exact = |reader: &mut _, body: &mut Vec<u8>, len: usize| {
+        let end = body
+            .len()
+            .checked_add(len)
+            .filter(|end| *end <= max_size)
+            .ok_or_else(too_large)?;
+        Read::take(reader, len as u64).read_to_end(body)?;
+        if body.len() == end {
+            Ok(())
+        } else {
+            Err(invalid("connection closed while reading the body"))
+        }
+    };
+    let mut body = vec![];
+    if header("transfer-encoding").is_some_and(|v| v.eq_ignore_ascii_case("chunked")) {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size)?;
+            let size = size.trim_end_matches(['\r', '\n']);
+            let size = size.split(';').next().unwrap_or_default().trim();
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|_| invalid(format!("invalid chunk size {size:?}")))?;
+            if size == 0 {
+                // skip trailers
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line)? == 0
+                        || line.trim_end_matches(['\r', '\n']).is_empty()
+                    {
+                        break;
+                    }
+                }
+                break;
+            }
+            read_exact(&mut *reader, &mut body, size)?;
+            let mut crlf = String::new();
+            reader.read_line(&mut crlf)?;
+        }
+    } else if let Some(len) = header("content-length") {
+        let len = len
+            .parse()
+            .map_err(|_| invalid(format!("invalid Content-Length {len:?}")))?;
+        read_exact(&mut *reader, &mut body, len)?;
+    } else if until_eof {
+        Read::take(&mut *reader, max_size as u64 + 1).read_to_end(&mut body)?;
+        if body.len() > max_size {
+            return Err(too_large());
+        }
+    }
+    Ok(body)
+}
+
+/// reads one request, calls the handler, and sends the response. invalid requests get a `400 Bad Request` response,
+/// requests which are too large a `413 Payload Too Large` or `431 Request Header Fields Too Large` response, and requests which take too long a `408 Request Timeout` response.
+fn serve_connection(stream: TcpStream, handler: &dyn MersData, i: &Info) -> Result<(), CheckError> {
+    // so that a client which doesn't send (or receive) anything can't block the server forever
+    _ = stream.set_read_timeout(Some(i.global.http_serve_timeout));
+    _ = stream.set_write_timeout(Some(i.global.http_serve_timeout));
+    let mut reader = BufReader::new(stream);
+    let request = read_head(&mut reader).and_then(|(first, headers)| {
+        let mut parts = first.split_whitespace();
+        let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+            return Err(invalid(format!("invalid request line {first:?}")));
+        };
+        let body = read_body(&mut reader, &headers, false, i.global.http_max_body_size)?;
+        Ok((method.to_owned(), path.to_owned(), headers, body))
+    });
+    let (status, headers, body) = match request {
+        Ok((method, path, headers, body)) => {
+            let field = |name: &str| i.global.object_fields.get_or_add_field(name);
+            let request = Data::new(Object::new(vec![
+                (field("method"), Data::new(data::string::String(method))),
+                (field("path"), Data::new(data::string::String(path))),
+                (field("headers"), headers_data(headers)),
+                (
+                    field("body"),
+                    Data::new(data::string::String(
+                        String::from_utf8_lossy(&body).into_owned(),
+                    )),
+                ),
+            ]));
+            let response =
+                run_callback(handler, request, &i.global, i.global.call_site.as_ref()).unwrap()?;
+            let response = response.get();
+            let response = response.as_any().downcast_ref::<Object>().unwrap();
+            let status = response
+                .get(field("status"))
+                .unwrap()
+                .get()
+                .as_any()
+                .downcast_ref::<data::int::Int>()
+                .unwrap()
+                .0;
+            let headers = match response.get(field("headers")) {
+                Some(h) => headers_arg(&h, &i.global)?,
+                None => vec![],
+            };
+            let body = string_arg(&response.get(field("body")).unwrap());
+            if !(100..=999).contains(&status) {
+                (500, vec![], format!("invalid status {status}\n"))
+            } else {
+                match headers.iter().try_for_each(|(k, v)| check_header(k, v)) {
+                    Ok(()) => (status, headers, body),
+                    Err(e) => (500, vec![], format!("{e}\n")),
+                }
+            }
+        }
+        Err(e) if e.get_ref().is_some_and(|e| e.is::<BodyTooLarge>()) => {
+            (413, vec![], format!("{e}\n"))
+        }
+        Err(e) if e.get_ref().is_some_and(|e| e.is::<HeadTooLarge>()) => {
+            (431, vec![], format!("{e}\n"))
+        }
+        Err(e) if is_timeout(&e) => (408, vec![], "request timed out\n".to_owned()),
+        Err(e) => (400, vec![], format!("{e}\n")),
+    };
+    let mut head = format!("HTTP/1.1 {status} {}\r\n", reason(status));
+    for (k, v) in &headers {
+        head.push_str(&format!("{k}: {v}\r\n"));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    ));
+    let stream = reader.get_mut();
+    // the client may have disconnected already, which isn't the server's problem
+    _ = stream
+        .write_all(head.as_bytes())
+        .and_then(|()| stream.write_all(body.as_bytes()))
+        .and_then(|()| stream.flush());
+    Ok(())
+}
+fn reason(status: isize) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
@@ -1,4 +1,6 @@
 use std::{
+    cmp::Ordering,
+    collections::VecDeque,
     fmt::Display,
     sync::{Arc, Mutex},
 };
@@ -7,15 +9,22 @@ use crate::{
     data::{
         self,
         function::{Function, FunctionT},
-        int::{Int, IntT, INT_MAX},
+        int::{Int, IntT, INT_MAX, INT_MIN},
         Data, MersData, MersType, MersTypeWInfo, Type,
     },
-    errors::CheckError,
+    errors::{CheckError, SourceRange},
     info::DisplayInfo,
-    program::{self, run::CheckInfo},
+    program::{
+        self,
+        run::{chain::run_callback, CheckInfo},
+    },
 };
 
-use super::Config;
+use super::{
+    util::{maybe_type, tuple_args},
+    with_list::{compare, sort_kind, List, ListT},
+    Config,
+};
 
 impl Config {
     /// Adds functions to deal with iterables
@@ -25,9 +34,32 @@ impl Config {
     /// `filter_map: fn` combines filter and map. requires that the function returns ()/(t).
     /// `map_while: fn` maps while the map-function returns (d), ends the iterator once () is returned.
     /// `take: fn` takes at most so many elements from the iterator.
+    /// `skip: fn` skips the first so many elements of the iterator: (iter, 2).skip
+    /// `skip_while: fn` skips elements as long as the function returns true: (iter, v -> (v, 3).lt).skip_while
+    /// `take_while: fn` takes elements as long as the function returns true: (iter, v -> (v, 3).lt).take_while
+    /// `step_by: fn` takes every nth element, starting with the first one: (iter, 2).step_by. step sizes < 1 are treated as 1.
+    /// `zip: fn` combines two iterators into one over (a, b) pairs, which ends when either of the iterators ends: (iter_a, iter_b).zip
+    /// `flat_map: fn` maps each element to an iterable, then iterates over the elements of all these iterables: (iter, v -> (v, v)).flat_map
+    /// `flatten: fn` turns an iterator over iterables into one over the elements of those iterables (same as `chain`)
+    /// `windows: fn` returns an iterator over all overlapping windows of n elements as Lists: (iter, 2).windows. sizes < 1 are treated as 1.
+    /// `chunks: fn` returns an iterator over Lists of n elements (the last one may be shorter): (iter, 2).chunks. sizes < 1 are treated as 1.
+    /// `group_by: fn` groups consecutive elements for which the function returns equal keys, returns an iterator over (key, List) pairs: (iter, v -> v.len).group_by
     /// `enumerate: fn` transforms an iterator over T into one over (Int, T), where Int is the index of the element
     /// `any: fn` returns true if any element of the iterator are true
     /// `all: fn` returns true if all elements of the iterator are true
+    /// `fold: fn` combines all elements into one value, starting with an initial value: (iter, 0, (acc, v) -> ...).fold
+    /// `reduce: fn` like fold, but uses the first element as the initial value. returns (value), or () if the iterator is empty: (iter, (acc, v) -> ...).reduce
+    /// `sum: fn` returns the sum of all numbers in the iterator, or () if it is empty or an Int overflowed. if any Float is found, returns a Float.
+    /// `product: fn` returns the product of all numbers in the iterator, or () if it is empty or an Int overflowed. if any Float is found, returns a Float.
+    /// `count: fn` returns the number of elements in the iterator
+    /// `min_by_key: fn` returns the first element for which the key function returns the smallest number, string or byte: (iter, v -> v.len).min_by_key returns (value) or () if the iterator is empty.
+    /// `max_by_key: fn` returns the last element for which the key function returns the largest number, string or byte: (iter, v -> v.len).max_by_key returns (value) or () if the iterator is empty.
+    /// `last: fn` returns the last element of the iterator: (value), or () if it is empty
+    /// `nth: fn` returns the element at the given index: (iter, 2).nth returns (value), or () if the iterator is too short
+    /// `position: fn` returns the index of the first element for which the function returns true: (iter, v -> ...).position returns (index) or ()
+    /// `find: fn` returns the first element for which the function returns true: (iter, v -> ...).find returns (value) or ()
+    /// `collect: fn` collects the elements of an iterator into a List
+    /// `collect_string: fn` concatenates an iterator over Strings into one String
     /// `range_inc: fn` returns an iterable `Range` starting at the first argument, counting up to the second one (inclusive).
     /// `range_exc: fn` returns an iterable `Range` starting at the first argument, counting up to the second one (exclusive).
     pub fn with_iters(self) -> Self {
@@ -191,7 +223,7 @@ impl Config {
                             if let Some(iter) = v.get().iterable(&i.global) {
                                 let f = f.get();
                                 for v in iter {
-                                    f.execute(v?, &i.global).unwrap()?;
+                                    run_callback(&**f, v?, &i.global, i.global.call_site.as_ref()).unwrap()?;
                                 }
                                 Ok(Data::empty_tuple())
                             } else {
@@ -229,46 +261,478 @@ impl Config {
             Iters::Take(v.0.max(0).try_into().unwrap_or(usize::MAX))
         }, &data::int::IntT(0, INT_MAX)))
         .add_var(
-            "enumerate",
-            data::function::Function {
-                info: program::run::Info::neverused(),
-                info_check: Arc::new(Mutex::new(CheckInfo::neverused())),
-                fixed_type: None,
-                fixed_type_out: Arc::new(Mutex::new(None)),
-                out: Ok(Arc::new(|a, i| {
-                    let data = if let Some(a) = a.iterable() {
-                        a
-                    } else {
-                        return Err(format!("cannot call enumerate on non-iterable type {}.", a.with_info(i)).into());
+            "skip",
+            genfunc_iter_and_arg("skip", |_: &data::int::IntT| ItersT::Skip, |v: &data::int::Int| {
+                Iters::Skip(v.0.max(0).try_into().unwrap_or(usize::MAX))
+            }, &data::int::IntT(0, INT_MAX)),
+        )
+        .add_var(
+            "skip_while",
+            genfunc_iter_and_func("skip_while", ItersT::SkipWhile, Iters::SkipWhile),
+        )
+        .add_var(
+            "take_while",
+            genfunc_iter_and_func("take_while", ItersT::TakeWhile, Iters::TakeWhile),
+        )
+        .add_var("step_by", genfunc_iter_and_arg("step_by", |_: &data::int::IntT| ItersT::StepBy, |v: &data::int::Int| {
+            Iters::StepBy(v.0.max(1).try_into().unwrap_or(usize::MAX))
+        }, &data::int::IntT(1, INT_MAX)))
+        .add_var(
+            "zip",
+            Function::new_generic(
+                |a, i| {
+                    let mut o = Type::empty();
+                    for (elem, t) in iter_args(a, 2, "zip", i)? {
+                        if let Some(other) = t.0[1].iterable() {
+                            o.add(Arc::new(IterT::new(ItersT::Zip(other), elem, i)?));
+                        } else {
+                            return Err(format!("cannot call zip on tuple that isn't (iterable, iterable): {} is not iterable", t.0[1].with_info(i)).into());
+                        }
+                    }
+                    Ok(o)
+                },
+                |a, i| {
+                    let [a, b] = tuple_args(&a);
+                    Ok(Data::new(Iter(Iters::Zip(b), a, i.global.call_site.clone())))
+                },
+            ),
+        )
+        .add_var(
+            "flat_map",
+            genfunc_iter_and_func("flat_map", ItersT::FlatMap, Iters::FlatMap),
+        )
+        .add_var("windows", genfunc_iter_and_arg("windows", |_: &data::int::IntT| ItersT::Windows, |v: &data::int::Int| {
+            Iters::Windows(v.0.max(1).try_into().unwrap_or(usize::MAX))
+        }, &data::int::IntT(1, INT_MAX)))
+        .add_var("chunks", genfunc_iter_and_arg("chunks", |_: &data::int::IntT| ItersT::Chunks, |v: &data::int::Int| {
+            Iters::Chunks(v.0.max(1).try_into().unwrap_or(usize::MAX))
+        }, &data::int::IntT(1, INT_MAX)))
+        .add_var(
+            "group_by",
+            genfunc_iter_and_func("group_by", ItersT::GroupBy, Iters::GroupBy),
+        )
+        .add_var("enumerate", genfunc_iter("enumerate", ItersT::Enumerate, Iters::Enumerate))
+        .add_var("chain", genfunc_iter("chain", ItersT::Chained, Iters::Chained))
+        .add_var("flatten", genfunc_iter("flatten", ItersT::Chained, Iters::Chained))
+        .add_var(
+            "fold",
+            Function::new_generic(
+                |a, i| {
+                    let mut o = Type::empty();
+                    for (elem, t) in iter_args(a, 3, "fold", i)? {
+                        o.add_all(&fold_type(t.0[1].clone(), &elem, &t.0[2], "fold", i)?);
+                    }
+                    Ok(o)
+                },
+                |a, i| {
+                    let [iter, init, f] = tuple_args(&a);
+                    let f = f.get();
+                    let mut acc = init;
+                    for v in iter.get().iterable(&i.global).unwrap() {
+                        acc = run_callback(&**f, Data::new(data::tuple::Tuple::from([acc, v?])), &i.global, i.global.call_site.as_ref()).unwrap()?;
+                    }
+                    Ok(acc)
+                },
+            ),
+        )
+        .add_var(
+            "reduce",
+            Function::new_generic(
+                |a, i| {
+                    let mut o = Type::empty();
+                    for (elem, t) in iter_args(a, 2, "reduce", i)? {
+                        o.add_all(&fold_type(elem.clone(), &elem, &t.0[1], "reduce", i)?);
+                    }
+                    Ok(maybe_type(o))
+                },
+                |a, i| {
+                    let [iter, f] = tuple_args(&a);
+                    let f = f.get();
+                    let mut iter = iter.get().iterable(&i.global).unwrap();
+                    let Some(mut acc) = iter.next().transpose()? else {
+                        return Ok(Data::empty_tuple());
                     };
-                    Ok(Type::new(IterT::new(ItersT::Enumerate, data, i)?))
-                })),
-                run: Arc::new(|a, _i| Ok(Data::new(Iter(Iters::Enumerate, a.clone())))),
-                inner_statements: None,
-            },
+                    for v in iter {
+                        acc = run_callback(&**f, Data::new(data::tuple::Tuple::from([acc, v?])), &i.global, i.global.call_site.as_ref()).unwrap()?;
+                    }
+                    Ok(Data::one_tuple(acc))
+                },
+            ),
         )
+        .add_var("sum", genfunc_sum_or_product("sum", 0, isize::checked_add, |a, b| a + b))
+        .add_var("product", genfunc_sum_or_product("product", 1, isize::checked_mul, |a, b| a * b))
         .add_var(
-            "chain",
-            data::function::Function {
-                info: program::run::Info::neverused(),
-                info_check: Arc::new(Mutex::new(CheckInfo::neverused())),
-                fixed_type: None,
-                fixed_type_out: Arc::new(Mutex::new(None)),
-                out: Ok(Arc::new(|a, i| {
-                    let data = if let Some(a) = a.iterable() {
-                        a
+            "count",
+            Function::new_generic(
+                |a, i| {
+                    iter_elem_type(a, "count", i)?;
+                    Ok(Type::new(IntT(0, INT_MAX)))
+                },
+                |a, i| {
+                    let mut count = 0;
+                    for v in a.get().iterable(&i.global).unwrap() {
+                        v?;
+                        count += 1;
+                    }
+                    Ok(Data::new(Int(count)))
+                },
+            ),
+        )
+        .add_var("min_by_key", genfunc_by_key("min_by_key", |new_to_best| new_to_best == Ordering::Less))
+        .add_var("max_by_key", genfunc_by_key("max_by_key", |new_to_best| new_to_best != Ordering::Less))
+        .add_var(
+            "last",
+            Function::new_generic(
+                |a, i| Ok(maybe_type(iter_elem_type(a, "last", i)?)),
+                |a, i| {
+                    let mut last = None;
+                    for v in a.get().iterable(&i.global).unwrap() {
+                        last = Some(v?);
+                    }
+                    Ok(last.map_or_else(Data::empty_tuple, Data::one_tuple))
+                },
+            ),
+        )
+        .add_var(
+            "nth",
+            Function::new_generic(
+                |a, i| {
+                    let mut o = Type::empty();
+                    for (elem, t) in iter_args(a, 2, "nth", i)? {
+                        if !t.0[1].is_included_in_single(&IntT(0, INT_MAX)) {
+                            return Err(format!("cannot call nth with index {}, which isn't Int<0..>", t.0[1].with_info(i)).into());
+                        }
+                        o.add_all(&elem);
+                    }
+                    Ok(maybe_type(o))
+                },
+                |a, i| {
+                    let [iter, n] = tuple_args(&a);
+                    let n = n.get().as_any().downcast_ref::<Int>().unwrap().0 as usize;
+                    let nth = iter.get().iterable(&i.global).unwrap().nth(n).transpose()?;
+                    Ok(nth.map_or_else(Data::empty_tuple, Data::one_tuple))
+                },
+            ),
+        )
+        .add_var(
+            "position",
+            Function::new_generic(
+                |a, i| {
+                    for (elem, t) in iter_args(a, 2, "position", i)? {
+                        check_predicate(&t.0[1], &elem, "position", i)?;
+                    }
+                    Ok(maybe_type(Type::new(IntT(0, INT_MAX))))
+                },
+                |a, i| {
+                    let [iter, f] = tuple_args(&a);
+                    for (index, v) in iter.get().iterable(&i.global).unwrap().enumerate() {
+                        if predicate(&f, v?, &i.global, i.global.call_site.as_ref())? {
+                            return Ok(Data::one_tuple(Data::new(Int(index as _))));
+                        }
+                    }
+                    Ok(Data::empty_tuple())
+                },
+            ),
+        )
+        .add_var(
+            "find",
+            Function::new_generic(
+                |a, i| {
+                    let mut o = Type::empty();
+                    for (elem, t) in iter_args(a, 2, "find", i)? {
+                        check_predicate(&t.0[1], &elem, "find", i)?;
+                        o.add_all(&elem);
+                    }
+                    Ok(maybe_type(o))
+                },
+                |a, i| {
+                    let [iter, f] = tuple_args(&a);
+                    for v in iter.get().iterable(&i.global).unwrap() {
+                        let v = v?;
+                        if predicate(&f, v.clone(), &i.global, i.global.call_site.as_ref())? {
+                            return Ok(Data::one_tuple(v));
+                        }
+                    }
+                    Ok(Data::empty_tuple())
+                },
+            ),
+        )
+        .add_var(
+            "collect",
+            Function::new_generic(
+                |a, i| Ok(Type::new(ListT(iter_elem_type(a, "collect", i)?))),
+                |a, i| {
+                    Ok(Data::new(List(
+                        a.get().iterable(&i.global).unwrap().collect::<Result<_, _>>()?,
+                    )))
+                },
+            ),
+        )
+        .add_var(
+            "collect_string",
+            Function::new_generic(
+                |a, i| {
+                    let elem = iter_elem_type(a, "collect_string", i)?;
+                    if elem.is_included_in_single(&data::string::StringT) {
+                        Ok(Type::new(data::string::StringT))
                     } else {
-                        return Err(format!("cannot call chain on non-iterable type {}.", a.with_info(i)).into());
-                    };
-                    Ok(Type::new(IterT::new(ItersT::Chained, data, i)?))
-                })),
-                run: Arc::new(|a, _i| Ok(Data::new(Iter(Iters::Chained, a.clone())))),
-                inner_statements: None,
-            },
+                        Err(format!("cannot call collect_string on an iterator over {}, which isn't String. Use `concat` to convert other values to strings.", elem.with_info(i)).into())
+                    }
+                },
+                |a, i| {
+                    let mut o = String::new();
+                    for v in a.get().iterable(&i.global).unwrap() {
+                        o.push_str(&v?.get().as_any().downcast_ref::<data::string::String>().unwrap().0);
+                    }
+                    Ok(Data::new(data::string::String(o)))
+                },
+            ),
         )
     }
 }
 
+fn genfunc_iter(name: &'static str, ft: ItersT, fd: Iters) -> data::function::Function {
+    Function::new_generic(
+        move |a, i| {
+            let data = if let Some(a) = a.iterable() {
+                a
+            } else {
+                return Err(format!(
+                    "cannot call {name} on non-iterable type {}.",
+                    a.with_info(i)
+                )
+                .into());
+            };
+            Ok(Type::new(IterT::new(ft.clone(), data, i)?))
+        },
+        move |a, i| {
+            Ok(Data::new(Iter(
+                fd.clone(),
+                a.clone(),
+                i.global.call_site.clone(),
+            )))
+        },
+    )
+}
+fn genfunc_sum_or_product(
+    name: &'static str,
+    identity: isize,
+    int_op: fn(isize, isize) -> Option<isize>,
+    float_op: fn(f64, f64) -> f64,
+) -> data::function::Function {
+    Function::new_generic(
+        move |a, i| {
+            let (mut int, mut float) = (false, false);
+            for t in iter_elem_type(a, name, i)?.types.iter() {
+                if t.as_any().is::<IntT>() {
+                    int = true;
+                } else if t.as_any().is::<data::float::FloatT>() {
+                    float = true;
+                } else {
+                    return Err(format!("cannot call {name} on an iterator over elements of type {}, which is not `Int/Float`", t.with_info(i)).into());
+                }
+            }
+            let mut o = Type::empty_tuple();
+            if int {
+                o.add(Arc::new(IntT(INT_MIN, INT_MAX)));
+            }
+            if float {
+                o.add(Arc::new(data::float::FloatT));
+            }
+            Ok(o)
+        },
+        move |a, i| {
+            // `int_result` is `None` once it overflowed, `float_result` is used if any `Float` was found
+            let mut int_result = Some(identity);
+            let mut float_result = identity as f64;
+            let (mut empty, mut found_float) = (true, false);
+            for v in a.get().iterable(&i.global).unwrap() {
+                let v = v?;
+                let v = v.get();
+                if let Some(v) = v.as_any().downcast_ref::<Int>() {
+                    int_result = int_result.and_then(|r| int_op(r, v.0));
+                    float_result = float_op(float_result, v.0 as f64);
+                } else {
+                    let v = v.as_any().downcast_ref::<data::float::Float>().unwrap();
+                    float_result = float_op(float_result, v.0);
+                    found_float = true;
+                }
+                empty = false;
+            }
+            Ok(if empty {
+                Data::empty_tuple()
+            } else if found_float {
+                Data::new(data::float::Float(float_result))
+            } else if let Some(r) = int_result {
+                Data::new(Int(r))
+            } else {
+                Data::empty_tuple()
+            })
+        },
+    )
+}
+/// `replace` is called with the ordering of a new key compared to the best key so far,
+/// and returns true if the new element should replace the best one.
+fn genfunc_by_key(name: &'static str, replace: fn(Ordering) -> bool) -> data::function::Function {
+    Function::new_generic(
+        move |a, i| {
+            let mut o = Type::empty();
+            for (elem, t) in iter_args(a, 2, name, i)? {
+                sort_kind(&func_out(&t.0[1], &elem, name, i)?, name, i)?;
+                o.add_all(&elem);
+            }
+            Ok(maybe_type(o))
+        },
+        move |a, i| {
+            let [iter, f] = tuple_args(&a);
+            let f = f.get();
+            let mut best: Option<(Data, Data)> = None;
+            for v in iter.get().iterable(&i.global).unwrap() {
+                let v = v?;
+                let key = run_callback(&**f, v.clone(), &i.global, i.global.call_site.as_ref())
+                    .unwrap()?;
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_key, _)| replace(compare(&key, best_key)))
+                {
+                    best = Some((key, v));
+                }
+            }
+            Ok(best.map_or_else(Data::empty_tuple, |(_, v)| Data::one_tuple(v)))
+        },
+    )
+}
+
+/// the type of the elements of the iterable `a`
+fn iter_elem_type(a: &Type, name: &str, i: &CheckInfo) -> Result<Type, CheckError> {
+    a.iterable().ok_or_else(|| {
+        format!(
+            "cannot call {name} on non-iterable type {}.",
+            a.with_info(i)
+        )
+        .into()
+    })
+}
+/// checks that `a` only contains tuples of length `len` whose first element is iterable,
+/// and returns them together with the type of that iterable's elements
+fn iter_args<'a>(
+    a: &'a Type,
+    len: usize,
+    name: &str,
+    i: &CheckInfo,
+) -> Result<Vec<(Type, &'a data::tuple::TupleT)>, CheckError> {
+    a.types
+        .iter()
+        .map(|t| {
+            if let Some(t) = t
+                .as_any()
+                .downcast_ref::<data::tuple::TupleT>()
+                .filter(|t| t.0.len() == len)
+            {
+                if let Some(elem) = t.0[0].iterable() {
+                    Ok((elem, t))
+                } else {
+                    Err(format!(
+                        "cannot call {name} on non-iterable type {}, which is part of {}.",
+                        t.0[0].with_info(i),
+                        a.with_info(i)
+                    )
+                    .into())
+                }
+            } else {
+                Err(format!(
+                    "cannot call {name} on {}, expected a tuple of length {len} starting with an iterable",
+                    a.with_info(i)
+                )
+                .into())
+            }
+        })
+        .collect()
+}
+/// the output type of the function(s) `f` when called with `arg`
+fn func_out(f: &Type, arg: &Type, name: &str, i: &CheckInfo) -> Result<Type, CheckError> {
+    let mut o = Type::empty();
+    for f in f.types.iter() {
+        if let Some(f) = f.executable() {
+            o.add_all(&f.o(arg)?);
+        } else {
+            return Err(format!(
+                "cannot call {name} with {} instead of a function",
+                f.with_info(i)
+            )
+            .into());
+        }
+    }
+    Ok(o)
+}
+fn check_predicate(f: &Type, arg: &Type, name: &str, i: &CheckInfo) -> Result<(), CheckError> {
+    let o = func_out(f, arg, name, i)?;
+    if o.is_included_in(&data::bool::bool_type()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{name}: function must return a Bool, but returns {}",
+            o.with_info(i)
+        )
+        .into())
+    }
+}
+/// calls `f` (see `run_callback`) and returns true if it returned true
+fn predicate(
+    f: &Data,
+    v: Data,
+    gi: &program::run::RunLocalGlobalInfo,
+    call_site: Option<&SourceRange>,
+) -> Result<bool, CheckError> {
+    Ok(run_callback(&**f.get(), v, gi, call_site)
+        .ok_or_else(|| CheckError::from("called predicate with non-function argument"))??
+        .get()
+        .as_any()
+        .downcast_ref::<data::bool::Bool>()
+        .is_some_and(|b| b.0))
+}
+/// the type of the accumulator of `fold`/`reduce`, which is the initial type plus everything `f` can return.
+/// because `f`'s output depends on the accumulator, this repeats until the type stops growing.
+fn fold_type(
+    init: Type,
+    elem: &Type,
+    f: &Type,
+    name: &str,
+    i: &CheckInfo,
+) -> Result<Type, CheckError> {
+    let mut acc = init;
+    for widened in [false, true] {
+        if widened {
+            // types like `Int<0..1>`, `Int<0..2>`, ... could grow forever, so try again with the full `Int` range
+            let mut wide = Type::empty();
+            for t in acc.types.iter() {
+                if t.as_any().is::<IntT>() {
+                    wide.add(Arc::new(IntT(INT_MIN, INT_MAX)));
+                } else {
+                    wide.add(Arc::clone(t));
+                }
+            }
+            acc = wide;
+        }
+        for _ in 0..8 {
+            let out = func_out(
+                f,
+                &Type::new(data::tuple::TupleT(vec![acc.clone(), elem.clone()])),
+                name,
+                i,
+            )?;
+            if out.is_included_in(&acc) {
+                return Ok(acc);
+            }
+            acc.add_all(&out);
+        }
+    }
+    Err(format!(
+        "{name}: couldn't determine the type of the accumulator, the function keeps returning new types (last tried {})",
+        acc.with_info(i)
+    )
+    .into())
+}
 fn genfunc_iter_and_func(
     name: &'static str,
     ft: impl Fn(FunctionT) -> ItersT + Send + Sync + 'static,
@@ -312,10 +776,14 @@ fn genfunc_iter_and_func(
         fixed_type: None,
         fixed_type_out: Arc::new(Mutex::new(None)),
         out: Ok(Arc::new(move |a, i| iter_out_arg(a, i, name, |f| ft(f)))),
-        run: Arc::new(move |a, _i| {
+        run: Arc::new(move |a, i| {
             if let Some(tuple) = a.get().as_any().downcast_ref::<data::tuple::Tuple>() {
                 if let (Some(v), Some(f)) = (tuple.get(0), tuple.get(1)) {
-                    Ok(Data::new(Iter(fd(f.clone()), v.clone())))
+                    Ok(Data::new(Iter(
+                        fd(f.clone()),
+                        v.clone(),
+                        i.global.call_site.clone(),
+                    )))
                 } else {
                     return Err("{name} called on tuple with len < 2".into());
                 }
@@ -374,11 +842,15 @@ fn genfunc_iter_and_arg<T: MersType, D: MersData>(
         out: Ok(Arc::new(move |a, i| {
             iter_out_arg(a, i, name, |f: &T| ft(f), type_sample)
         })),
-        run: Arc::new(move |a, _i| {
+        run: Arc::new(move |a, i| {
             if let Some(tuple) = a.get().as_any().downcast_ref::<data::tuple::Tuple>() {
                 if let (Some(v), Some(f)) = (tuple.get(0), tuple.get(1)) {
                     if let Some(f) = f.get().as_any().downcast_ref::<D>() {
-                        Ok(Data::new(Iter(fd(f), v.clone())))
+                        Ok(Data::new(Iter(
+                            fd(f),
+                            v.clone(),
+                            i.global.call_site.clone(),
+                        )))
                     } else {
                         return Err("{name} called on tuple not containing function".into());
                     }
@@ -402,6 +874,15 @@ pub enum Iters {
     Take(usize),
     Enumerate,
     Chained,
+    Skip(usize),
+    SkipWhile(Data),
+    TakeWhile(Data),
+    StepBy(usize),
+    Zip(Data),
+    FlatMap(Data),
+    Windows(usize),
+    Chunks(usize),
+    GroupBy(Data),
 }
 #[derive(Clone, Debug)]
 pub enum ItersT {
@@ -412,9 +893,21 @@ pub enum ItersT {
     Take,
     Enumerate,
     Chained,
+    Skip,
+    SkipWhile(data::function::FunctionT),
+    TakeWhile(data::function::FunctionT),
+    StepBy,
+    /// the type of the other iterable's elements
+    Zip(Type),
+    FlatMap(data::function::FunctionT),
+    Windows,
+    Chunks,
+    GroupBy(data::function::FunctionT),
 }
+/// An iterator created by a function like `map`, which wraps the iterable `1`.
+/// `2` is where that function was called, which is added to the stack trace of errors from functions the iterator calls.
 #[derive(Clone, Debug)]
-pub struct Iter(pub Iters, pub Data);
+pub struct Iter(pub Iters, pub Data, pub Option<SourceRange>);
 #[derive(Clone, Debug)]
 pub struct IterT(pub ItersT, pub Type, pub Type);
 impl MersData for Iter {
@@ -429,12 +922,12 @@ impl MersData for Iter {
         gi: &crate::program::run::RunLocalGlobalInfo,
     ) -> Option<Box<dyn Iterator<Item = Result<Data, CheckError>>>> {
         let gi = gi.clone();
+        let call_site = self.2.clone();
         Some(match &self.0 {
             Iters::Map(f) => {
                 let f = Clone::clone(f);
                 Box::new(self.1.get().iterable(&gi)?.map(move |v| {
-                    f.get()
-                        .execute(v?, &gi)
+                    run_callback(&**f.get(), v?, &gi, call_site.as_ref())
                         .ok_or_else(|| CheckError::from("called map with non-function argument"))?
                 }))
             }
@@ -442,7 +935,8 @@ impl MersData for Iter {
                 let f = Clone::clone(f);
                 Box::new(self.1.get().iterable(&gi)?.filter_map(move |v| {
                     match v {
-                        Ok(v) => match f.get().execute(v.clone(), &gi) {
+                        Ok(v) => match run_callback(&**f.get(), v.clone(), &gi, call_site.as_ref())
+                        {
                             Some(Ok(f)) => {
                                 if f.get()
                                     .as_any()
@@ -466,7 +960,7 @@ impl MersData for Iter {
             Iters::FilterMap(f) => {
                 let f = Clone::clone(f);
                 Box::new(self.1.get().iterable(&gi)?.filter_map(move |v| match v {
-                    Ok(v) => match f.get().execute(v, &gi) {
+                    Ok(v) => match run_callback(&**f.get(), v, &gi, call_site.as_ref()) {
                         Some(Ok(r)) => Some(Ok(r.one_tuple_content()?)),
                         Some(Err(e)) => Some(Err(e)),
                         None => Some(Err(CheckError::from(
@@ -479,7 +973,7 @@ impl MersData for Iter {
             Iters::MapWhile(f) => {
                 let f = Clone::clone(f);
                 Box::new(self.1.get().iterable(&gi)?.map_while(move |v| match v {
-                    Ok(v) => match f.get().execute(v, &gi) {
+                    Ok(v) => match run_callback(&**f.get(), v, &gi, call_site.as_ref()) {
                         Some(Ok(r)) => Some(Ok(r.one_tuple_content()?)),
                         Some(Err(e)) => Some(Err(e)),
                         None => Some(Err(CheckError::from(
@@ -518,20 +1012,164 @@ impl MersData for Iter {
                     Err(e) => Box::new([Err(e)].into_iter()),
                 }
             }
+            Iters::Skip(n) => Box::new(self.1.get().iterable(&gi)?.skip(*n)),
+            Iters::SkipWhile(f) => {
+                let f = Clone::clone(f);
+                let mut iter = self.1.get().iterable(&gi)?;
+                let mut skipping = true;
+                Box::new(std::iter::from_fn(move || loop {
+                    let v = match iter.next()? {
+                        Ok(v) => v,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    if skipping {
+                        match predicate(&f, v.clone(), &gi, call_site.as_ref()) {
+                            Ok(true) => continue,
+                            Ok(false) => skipping = false,
+                            Err(e) => return Some(Err(e)),
+                        }
+                    }
+                    return Some(Ok(v));
+                }))
+            }
+            Iters::TakeWhile(f) => {
+                let f = Clone::clone(f);
+                let mut iter = self.1.get().iterable(&gi)?;
+                let mut done = false;
+                Box::new(std::iter::from_fn(move || {
+                    if done {
+                        return None;
+                    }
+                    let v = match iter.next()? {
+                        Ok(v) => v,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    match predicate(&f, v.clone(), &gi, call_site.as_ref()) {
+                        Ok(true) => Some(Ok(v)),
+                        Ok(false) => {
+                            done = true;
+                            None
+                        }
+                        Err(e) => Some(Err(e)),
+                    }
+                }))
+            }
+            Iters::StepBy(n) => Box::new(self.1.get().iterable(&gi)?.step_by(*n)),
+            Iters::Zip(other) => Box::new(
+                self.1
+                    .get()
+                    .iterable(&gi)?
+                    .zip(other.get().iterable(&gi)?)
+                    .map(|(a, b)| Ok(Data::new(data::tuple::Tuple::from([a?, b?])))),
+            ),
+            Iters::FlatMap(f) => {
+                let f = Clone::clone(f);
+                Box::new(
+                    self.1
+                        .get()
+                        .iterable(&gi)?
+                        .map(move |v| {
+                            run_callback(&**f.get(), v?, &gi, call_site.as_ref())
+                                .ok_or_else(|| {
+                                    CheckError::from("called flat_map with non-function argument")
+                                })??
+                                .get()
+                                .iterable(&gi)
+                                .ok_or_else(|| {
+                                    CheckError::from("flat_map function returned a non-iterable")
+                                })
+                        })
+                        .flat_map(|v| match v {
+                            Ok(iter) => iter,
+                            Err(e) => Box::new(std::iter::once(Err(e))),
+                        }),
+                )
+            }
+            Iters::Windows(n) => {
+                let n = *n;
+                let mut iter = self.1.get().iterable(&gi)?;
+                let mut window = VecDeque::new();
+                Box::new(std::iter::from_fn(move || {
+                    if window.len() == n {
+                        window.pop_front();
+                    }
+                    while window.len() < n {
+                        match iter.next()? {
+                            Ok(v) => window.push_back(v),
+                            Err(e) => return Some(Err(e)),
+                        }
+                    }
+                    Some(Ok(Data::new(List(window.iter().cloned().collect()))))
+                }))
+            }
+            Iters::Chunks(n) => {
+                let n = *n;
+                let mut iter = self.1.get().iterable(&gi)?;
+                Box::new(std::iter::from_fn(move || {
+                    let mut chunk = Vec::new();
+                    while chunk.len() < n {
+                        match iter.next() {
+                            Some(Ok(v)) => chunk.push(v),
+                            Some(Err(e)) => return Some(Err(e)),
+                            None => break,
+                        }
+                    }
+                    if chunk.is_empty() {
+                        None
+                    } else {
+                        Some(Ok(Data::new(List(chunk))))
+                    }
+                }))
+            }
+            Iters::GroupBy(f) => {
+                let f = Clone::clone(f);
+                let mut iter = self.1.get().iterable(&gi)?;
+                // the first element of the next group and its key
+                let mut next: Option<(Data, Data)> = None;
+                let key = move |v: &Data, gi: &program::run::RunLocalGlobalInfo| {
+                    run_callback(&**f.get(), v.clone(), gi, call_site.as_ref()).ok_or_else(
+                        || CheckError::from("called group_by with non-function argument"),
+                    )?
+                };
+                Box::new(std::iter::from_fn(move || {
+                    let (group_key, first) = match next.take() {
+                        Some(v) => v,
+                        None => match iter.next()?.and_then(|v| Ok((key(&v, &gi)?, v))) {
+                            Ok(v) => v,
+                            Err(e) => return Some(Err(e)),
+                        },
+                    };
+                    let mut group = vec![first];
+                    for v in iter.by_ref() {
+                        match v.and_then(|v| Ok((key(&v, &gi)?, v))) {
+                            Ok((k, v)) if k == group_key => group.push(v),
+                            Ok(v) => {
+                                next = Some(v);
+                                break;
+                            }
+                            Err(e) => return Some(Err(e)),
+                        }
+                    }
+                    Some(Ok(Data::new(data::tuple::Tuple::from([
+                        group_key,
+                        Data::new(List(group)),
+                    ]))))
+                }))
+            }
         })
     }
     fn clone(&self) -> Box<dyn MersData> {
         Box::new(Clone::clone(self))
     }
     fn as_type(&self) -> data::Type {
-        Type::new(
-            IterT::new(
-                self.0.as_type(),
-                self.1.get().as_type(),
-                &crate::info::Info::neverused(),
-            )
-            .unwrap(),
-        )
+        // `IterT` stores the type of the elements of the inner iterable, not the type of the iterable itself
+        let inner = self
+            .1
+            .get()
+            .as_type()
+            .iterable()
+            .unwrap_or_else(Type::empty);
+        Type::new(IterT::new(self.0.as_type(), inner, &crate::info::Info::neverused()).unwrap())
     }
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -593,6 +1231,36 @@ impl IterT {
                     .into());
                 }
             }
+            ItersT::Skip | ItersT::StepBy => data.clone(),
+            ItersT::SkipWhile(f) | ItersT::TakeWhile(f) => {
+                if f.o(&data)?.is_included_in(&data::bool::bool_type()) {
+                    data.clone()
+                } else {
+                    return Err(format!(
+                        "Iter:SkipWhile/TakeWhile, but function doesn't return bool for argument {}.",
+                        data.with_info(i)
+                    )
+                    .into());
+                }
+            }
+            ItersT::Zip(other) => Type::new(data::tuple::TupleT(vec![data.clone(), other.clone()])),
+            ItersT::FlatMap(f) => {
+                let o = f.o(&data)?;
+                if let Some(out) = o.iterable() {
+                    out
+                } else {
+                    return Err(format!(
+                        "Iter:FlatMap, but function returns the non-iterable type {}.",
+                        o.with_info(i)
+                    )
+                    .into());
+                }
+            }
+            ItersT::Windows | ItersT::Chunks => Type::new(ListT(data.clone())),
+            ItersT::GroupBy(f) => Type::new(data::tuple::TupleT(vec![
+                f.o(&data)?,
+                Type::new(ListT(data.clone())),
+            ])),
         };
         Ok(Self(iter, data, t))
     }
@@ -654,6 +1322,17 @@ impl Iters {
             Self::Take(_) => ItersT::Take,
             Self::Enumerate => ItersT::Enumerate,
             Self::Chained => ItersT::Chained,
+            Self::Skip(_) => ItersT::Skip,
+            Self::SkipWhile(f) => ItersT::SkipWhile(f.get().executable().unwrap()),
+            Self::TakeWhile(f) => ItersT::TakeWhile(f.get().executable().unwrap()),
+            Self::StepBy(_) => ItersT::StepBy,
+            Self::Zip(other) => {
+                ItersT::Zip(other.get().as_type().iterable().unwrap_or_else(Type::empty))
+            }
+            Self::FlatMap(f) => ItersT::FlatMap(f.get().executable().unwrap()),
+            Self::Windows(_) => ItersT::Windows,
+            Self::Chunks(_) => ItersT::Chunks,
+            Self::GroupBy(f) => ItersT::GroupBy(f.get().executable().unwrap()),
         }
     }
 }
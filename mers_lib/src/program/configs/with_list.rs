@@ -1,18 +1,27 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    cmp::Ordering,
+    sync::{Arc, Mutex},
+};
 
 use crate::{
     data::{
         self,
-        int::{IntT, INT_MAX},
+        int::{IntT, INT_MAX, INT_MIN},
         Data, MersData, MersType, MersTypeWInfo, Type,
     },
     errors::CheckError,
     info::DisplayInfo,
     parsing::{statements::to_string_literal, Source},
-    program::{self, run::CheckInfo},
+    program::{
+        self,
+        run::{chain::run_callback, CheckInfo},
+    },
 };
 
-use super::Config;
+use super::{
+    util::{self, tuple_arg_types, tuple_args},
+    Config,
+};
 
 impl Config {
     /// Adds a simple list type
@@ -23,6 +32,17 @@ impl Config {
     /// `insert: fn` changes the element at the given index to a new value, then returns true (index <= len) or false (index > len)
     /// `replace: fn` replaces and returns the element at the given index with some new value (index < len) or returns `()` (index >= len)
     /// `remove: fn` removes and returns the element at the given index (index < len) or returns `()` (index >= len)
+    /// `sort: fn` sorts a list of numbers (Int/Float), strings or bytes in place: &list.sort
+    /// `sort_by: fn` sorts a list in place using a comparator, which returns a negative Int if a should be before b, a positive one if it should be after b, and 0 if they are equal: (&list, (a, b) -> ...).sort_by
+    /// `sort_by_key: fn` sorts a list in place by the numbers, strings or bytes a key function returns for each element: (&list, v -> v.len).sort_by_key
+    /// `reverse: fn` reverses a list in place
+    /// `dedup: fn` removes consecutive equal elements from a list (on a sorted list, this removes all duplicates)
+    /// `binary_search: fn` searches a sorted list for a value. returns (index) if it was found and () otherwise: (list, value).binary_search
+    /// `contains: fn` returns true if the list contains an element which is equal to the value: (list, value).contains
+    /// `slice: fn` returns part of a list: (list, start).slice or (list, start, end).slice. like in `substring`, negative indices count from the end of the list.
+    /// `extend: fn` adds all elements of an iterable to the end of a list: (&list, (1, 2)).extend
+    /// `swap: fn` swaps the elements at two indices, then returns true (both indices < len) or false (otherwise): (&list, 0, 2).swap
+    /// `truncate: fn` shortens the list to the given length, does nothing if it is already shorter: (&list, 3).truncate
     pub fn with_list(self) -> Self {
         // TODO: Type with generics
         self
@@ -393,9 +413,413 @@ impl Config {
                 inner_statements: None,
                 },
             )
+            .add_var(
+                "sort",
+                util::to_mers_func(
+                    |a, i| {
+                        sort_kind(&list_ref_elem_type(a, "sort", i)?, "sort", i)?;
+                        Ok(Type::empty_tuple())
+                    },
+                    |a, _i| {
+                        modify_list(&a, |l| *l = merge_sort_by(std::mem::take(l), &mut compare));
+                        Ok(Data::empty_tuple())
+                    },
+                ),
+            )
+            .add_var(
+                "sort_by",
+                util::to_mers_func(
+                    |a, i| {
+                        for t in tuple_arg_types(a, 2, "sort_by", i)? {
+                            let elem = list_ref_elem_type(&t.0[0], "sort_by", i)?;
+                            let pair = Type::new(data::tuple::TupleT(vec![elem.clone(), elem]));
+                            for f in t.0[1].types.iter() {
+                                let o = f
+                                    .executable()
+                                    .ok_or_else(|| format!("sort_by: comparator {} is not a function", f.with_info(i)))?
+                                    .o(&pair)?;
+                                if !o.is_included_in_single(&IntT(INT_MIN, INT_MAX)) {
+                                    return Err(format!("sort_by: comparator must return an Int, but returns {}", o.with_info(i)).into());
+                                }
+                            }
+                        }
+                        Ok(Type::empty_tuple())
+                    },
+                    |a, i| {
+                        let [list, f] = tuple_args(&a);
+                        let f = f.get();
+                        // the comparator may use the list, so it can't be locked while sorting
+                        let elems = modify_list(&list, std::mem::take);
+                        let mut err = None;
+                        let elems = merge_sort_by(elems, &mut |a, b| {
+                            if err.is_some() {
+                                return Ordering::Equal;
+                            }
+                            let pair = Data::new(data::tuple::Tuple::from([a.clone(), b.clone()]));
+                            match run_callback(&**f, pair, &i.global, i.global.call_site.as_ref()).unwrap() {
+                                Ok(v) => v.get().as_any().downcast_ref::<data::int::Int>().unwrap().0.cmp(&0),
+                                Err(e) => {
+                                    err = Some(e);
+                                    Ordering::Equal
+                                }
+                            }
+                        });
+                        modify_list(&list, |l| *l = elems);
+                        if let Some(e) = err {
+                            return Err(e);
+                        }
+                        Ok(Data::empty_tuple())
+                    },
+                ),
+            )
+            .add_var(
+                "sort_by_key",
+                util::to_mers_func(
+                    |a, i| {
+                        for t in tuple_arg_types(a, 2, "sort_by_key", i)? {
+                            let elem = list_ref_elem_type(&t.0[0], "sort_by_key", i)?;
+                            let mut keys = Type::empty();
+                            for f in t.0[1].types.iter() {
+                                keys.add_all(&f
+                                    .executable()
+                                    .ok_or_else(|| format!("sort_by_key: key function {} is not a function", f.with_info(i)))?
+                                    .o(&elem)?);
+                            }
+                            sort_kind(&keys, "sort_by_key", i)?;
+                        }
+                        Ok(Type::empty_tuple())
+                    },
+                    |a, i| {
+                        let [list, f] = tuple_args(&a);
+                        let f = f.get();
+                        // the key function may use the list, so it can't be locked while sorting
+                        let elems = modify_list(&list, std::mem::take);
+                        let mut keyed = Vec::with_capacity(elems.len());
+                        let mut err = None;
+                        for elem in elems {
+                            match run_callback(&**f, elem.clone(), &i.global, i.global.call_site.as_ref()).unwrap() {
+                                Ok(key) => keyed.push((key, elem)),
+                                Err(e) => {
+                                    err = Some(e);
+                                    keyed.push((Data::empty_tuple(), elem));
+                                }
+                            }
+                        }
+                        if err.is_none() {
+                            keyed = merge_sort_by(keyed, &mut |(a, _), (b, _)| compare(a, b));
+                        }
+                        modify_list(&list, |l| *l = keyed.into_iter().map(|(_, elem)| elem).collect());
+                        if let Some(e) = err {
+                            return Err(e);
+                        }
+                        Ok(Data::empty_tuple())
+                    },
+                ),
+            )
+            .add_var(
+                "reverse",
+                util::to_mers_func(
+                    |a, i| {
+                        list_ref_elem_type(a, "reverse", i)?;
+                        Ok(Type::empty_tuple())
+                    },
+                    |a, _i| {
+                        modify_list(&a, |l| l.reverse());
+                        Ok(Data::empty_tuple())
+                    },
+                ),
+            )
+            .add_var(
+                "dedup",
+                util::to_mers_func(
+                    |a, i| {
+                        list_ref_elem_type(a, "dedup", i)?;
+                        Ok(Type::empty_tuple())
+                    },
+                    |a, _i| {
+                        modify_list(&a, |l| l.dedup_by(|a, b| a == b));
+                        Ok(Data::empty_tuple())
+                    },
+                ),
+            )
+            .add_var(
+                "binary_search",
+                util::to_mers_func(
+                    |a, i| {
+                        for t in tuple_arg_types(a, 2, "binary_search", i)? {
+                            let mut elem = list_elem_type(&t.0[0], "binary_search", i)?;
+                            elem.add_all(&t.0[1]);
+                            sort_kind(&elem, "binary_search", i)?;
+                        }
+                        Ok(Type::newm(vec![
+                            Arc::new(data::tuple::TupleT(vec![Type::new(IntT(0, INT_MAX))])),
+                            Arc::new(data::tuple::TupleT(vec![])),
+                        ]))
+                    },
+                    |a, _i| {
+                        let [list, value] = tuple_args(&a);
+                        let list = list.get();
+                        let list = &list.as_any().downcast_ref::<List>().unwrap().0;
+                        Ok(match list.binary_search_by(|e| compare(e, &value)) {
+                            Ok(index) => Data::one_tuple(Data::new(data::int::Int(index as isize))),
+                            Err(_) => Data::empty_tuple(),
+                        })
+                    },
+                ),
+            )
+            .add_var(
+                "contains",
+                util::to_mers_func(
+                    |a, i| {
+                        for t in tuple_arg_types(a, 2, "contains", i)? {
+                            list_elem_type(&t.0[0], "contains", i)?;
+                        }
+                        Ok(data::bool::bool_type())
+                    },
+                    |a, _i| {
+                        let [list, value] = tuple_args(&a);
+                        let list = list.get();
+                        let list = &list.as_any().downcast_ref::<List>().unwrap().0;
+                        Ok(Data::new(data::bool::Bool(list.contains(&value))))
+                    },
+                ),
+            )
+            .add_var(
+                "slice",
+                util::to_mers_func(
+                    |a, i| {
+                        let mut o = Type::empty();
+                        for t in a.types.iter() {
+                            let t = t.as_any().downcast_ref::<data::tuple::TupleT>().filter(|t| {
+                                (t.0.len() == 2 || t.0.len() == 3)
+                                    && t.0[1..].iter().all(|t| t.is_included_in_single(&IntT(INT_MIN, INT_MAX)))
+                            }).ok_or_else(|| format!("slice: expected (List, Int) or (List, Int, Int), but got {}", a.with_info(i)))?;
+                            for t in t.0[0].types.iter() {
+                                if t.as_any().is::<ListT>() {
+                                    o.add(Arc::clone(t));
+                                } else {
+                                    return Err(format!("slice: expected a list, but found {}", t.with_info(i)).into());
+                                }
+                            }
+                        }
+                        Ok(o)
+                    },
+                    |a, _i| {
+                        let a = a.get();
+                        let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+                        let list = a.0[0].read();
+                        let list = list.get();
+                        let list = &list.as_any().downcast_ref::<List>().unwrap().0;
+                        let index = |i: usize| {
+                            a.get(i).map(|v| {
+                                let v = v.get().as_any().downcast_ref::<data::int::Int>().unwrap().0;
+                                if v < 0 {
+                                    list.len().saturating_sub(v.unsigned_abs())
+                                } else {
+                                    (v as usize).min(list.len())
+                                }
+                            })
+                        };
+                        let start = index(1).unwrap();
+                        let end = index(2).unwrap_or(list.len());
+                        Ok(Data::new(List(if start < end {
+                            list[start..end].to_vec()
+                        } else {
+                            vec![]
+                        })))
+                    },
+                ),
+            )
+            .add_var(
+                "extend",
+                util::to_mers_func(
+                    |a, i| {
+                        for t in tuple_arg_types(a, 2, "extend", i)? {
+                            let elem = list_ref_elem_type(&t.0[0], "extend", i)?;
+                            let new = t.0[1].iterable().ok_or_else(|| format!("extend: {} is not iterable", t.0[1].with_info(i)))?;
+                            if !new.is_included_in(&elem) {
+                                return Err(format!("extend: can't add elements of type {} to a list of {}", new.with_info(i), elem.with_info(i)).into());
+                            }
+                        }
+                        Ok(Type::empty_tuple())
+                    },
+                    |a, i| {
+                        let [list, new] = tuple_args(&a);
+                        // the iterable could be or use the list, so it can't be locked while iterating
+                        let new = new.get().iterable(&i.global).unwrap().collect::<Result<Vec<_>, _>>()?;
+                        modify_list(&list, |l| l.extend(new));
+                        Ok(Data::empty_tuple())
+                    },
+                ),
+            )
+            .add_var(
+                "swap",
+                util::to_mers_func(
+                    |a, i| {
+                        for t in tuple_arg_types(a, 3, "swap", i)? {
+                            list_ref_elem_type(&t.0[0], "swap", i)?;
+                            if !t.0[1..].iter().all(|t| t.is_included_in_single(&IntT(0, INT_MAX))) {
+                                return Err(format!("swap: indices should be Int<0..>, but got {}", a.with_info(i)).into());
+                            }
+                        }
+                        Ok(data::bool::bool_type())
+                    },
+                    |a, _i| {
+                        let [list, x, y] = tuple_args(&a);
+                        let [x, y] = [x, y].map(|n| n.get().as_any().downcast_ref::<data::int::Int>().unwrap().0 as usize);
+                        Ok(Data::new(data::bool::Bool(modify_list(&list, |l| {
+                            if x < l.len() && y < l.len() {
+                                l.swap(x, y);
+                                true
+                            } else {
+                                false
+                            }
+                        }))))
+                    },
+                ),
+            )
+            .add_var(
+                "truncate",
+                util::to_mers_func(
+                    |a, i| {
+                        for t in tuple_arg_types(a, 2, "truncate", i)? {
+                            list_ref_elem_type(&t.0[0], "truncate", i)?;
+                            if !t.0[1].is_included_in_single(&IntT(0, INT_MAX)) {
+                                return Err(format!("truncate: length should be an Int<0..>, but was {}", t.0[1].with_info(i)).into());
+                            }
+                        }
+                        Ok(Type::empty_tuple())
+                    },
+                    |a, _i| {
+                        let [list, len] = tuple_args(&a);
+                        let len = len.get().as_any().downcast_ref::<data::int::Int>().unwrap().0 as usize;
+                        modify_list(&list, |l| l.truncate(len));
+                        Ok(Data::empty_tuple())
+                    },
+                ),
+            )
     }
 }
 
+/// the types of the elements of the list(s) `t` refers to
+fn list_ref_elem_type(t: &Type, func: &str, i: &CheckInfo) -> Result<Type, CheckError> {
+    if let Some(t) = t.dereference() {
+        list_elem_type(&t, func, i)
+    } else {
+        Err(format!(
+            "{func}: expected a reference to a list, but got {}",
+            t.with_info(i)
+        )
+        .into())
+    }
+}
+/// the types of the elements of the list(s) in `t`
+fn list_elem_type(t: &Type, func: &str, i: &CheckInfo) -> Result<Type, CheckError> {
+    let mut o = Type::empty();
+    for t in t.types.iter() {
+        if let Some(t) = t.as_any().downcast_ref::<ListT>() {
+            o.add_all(&t.0);
+        } else {
+            return Err(format!("{func}: expected a list, but found {}", t.with_info(i)).into());
+        }
+    }
+    Ok(o)
+}
+/// returns an error unless all values of type `t` can be compared by `compare`
+pub(crate) fn sort_kind(t: &Type, func: &str, i: &CheckInfo) -> Result<(), CheckError> {
+    let kinds: [&dyn MersType; 4] = [
+        &IntT(INT_MIN, INT_MAX),
+        &data::float::FloatT,
+        &data::string::StringT,
+        &data::byte::ByteT,
+    ];
+    let mut found = [false; 4];
+    for t in t.types.iter() {
+        if let Some(k) = kinds.iter().position(|k| t.is_included_in(*k)) {
+            found[k] = true;
+        } else {
+            return Err(format!(
+                "{func}: can only compare Int, Float, String and Byte values, not {}",
+                t.with_info(i)
+            )
+            .into());
+        }
+    }
+    // Ints and Floats can be compared to each other, but not to Strings or Bytes
+    if (found[0] || found[1]) as u8 + found[2] as u8 + found[3] as u8 > 1 {
+        return Err(format!(
+            "{func}: can't compare values of different types in {}",
+            t.with_info(i)
+        )
+        .into());
+    }
+    Ok(())
+}
+/// A stable merge sort. Unlike `slice::sort_by`, this doesn't panic if `cmp` isn't a total order
+/// (which user-provided comparators don't have to be), the order of the elements is just unspecified then.
+fn merge_sort_by<T>(mut v: Vec<T>, cmp: &mut impl FnMut(&T, &T) -> Ordering) -> Vec<T> {
+    if v.len() <= 1 {
+        return v;
+    }
+    let right = v.split_off(v.len() / 2);
+    let left = merge_sort_by(v, cmp);
+    let right = merge_sort_by(right, cmp);
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        if cmp(r, l) == Ordering::Less {
+            out.extend(right.next());
+        } else {
+            out.extend(left.next());
+        }
+    }
+    out.extend(left);
+    out.extend(right);
+    out
+}
+/// compares numbers, strings or bytes. see `sort_kind`.
+pub(crate) fn compare(a: &Data, b: &Data) -> Ordering {
+    let (a, b) = (a.get(), b.get());
+    let (a, b) = (a.as_any(), b.as_any());
+    let num = |v: &dyn std::any::Any| {
+        v.downcast_ref::<data::int::Int>()
+            .map(|v| v.0 as f64)
+            .or_else(|| v.downcast_ref::<data::float::Float>().map(|v| v.0))
+    };
+    if let (Some(a), Some(b)) = (
+        a.downcast_ref::<data::int::Int>(),
+        b.downcast_ref::<data::int::Int>(),
+    ) {
+        a.0.cmp(&b.0)
+    } else if let (Some(a), Some(b)) = (num(a), num(b)) {
+        a.total_cmp(&b)
+    } else if let (Some(a), Some(b)) = (
+        a.downcast_ref::<data::string::String>(),
+        b.downcast_ref::<data::string::String>(),
+    ) {
+        a.0.cmp(&b.0)
+    } else if let (Some(a), Some(b)) = (
+        a.downcast_ref::<data::byte::Byte>(),
+        b.downcast_ref::<data::byte::Byte>(),
+    ) {
+        a.0.cmp(&b.0)
+    } else {
+        Ordering::Equal
+    }
+}
+/// calls `f` on the list `list_ref` refers to
+fn modify_list<R>(list_ref: &Data, f: impl FnOnce(&mut Vec<Data>) -> R) -> R {
+    let list_ref = list_ref.get();
+    let mut list = list_ref
+        .as_any()
+        .downcast_ref::<data::reference::Reference>()
+        .unwrap()
+        .write();
+    let mut list = list.get_mut();
+    f(&mut list.mut_any().downcast_mut::<List>().unwrap().0)
+}
+
 #[derive(Debug, Clone)]
 pub struct List(pub Vec<Data>);
 #[derive(Debug)]
@@ -0,0 +1,209 @@
+use std::{
+    fmt::Display,
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use line_span::LineSpanExt;
+
+use crate::{
+    data::{self, Data, MersDataWInfo, Type},
+    errors::SourceRange,
+    parsing::SourceFrom,
+    program::{self, run::CheckInfo},
+};
+
+use super::{gen::function::func, util, Config};
+
+/// Receives every log record which isn't filtered out by the minimum level
+pub type LogSink = Box<dyn FnMut(LogRecord) + Send + Sync>;
+
+impl Config {
+    /// logging with levels. records which are less severe than the minimum level (by default `info`) are ignored,
+    /// all others are printed to stderr like `2024-01-31 12:00:00.000 WARN  [script.mers:3] message`,
+    /// or passed to the `LogSink` set using `Config::set_log_sink` (or `RunLocalGlobalInfo::log_sink`).
+    /// `log_debug: fn`, `log_info: fn`, `log_warn: fn`, `log_error: fn` log any value (Strings are logged as they are, other values are formatted like `print` would)
+    /// `log_set_level: fn` sets the minimum level: "debug", "info", "warn", "error" or "off". returns () or, for any other String, `{log_error: String}`
+    /// `log_level: fn` returns the current minimum level as a String
+    pub fn with_log(self) -> Self {
+        self.add_var("log_debug", log_func(LogLevel::Debug))
+            .add_var("log_info", log_func(LogLevel::Info))
+            .add_var("log_warn", log_func(LogLevel::Warn))
+            .add_var("log_error", log_func(LogLevel::Error))
+            .add_var(
+                "log_set_level",
+                util::to_mers_func_with_in_type(
+                    Type::new(data::string::StringT),
+                    |_a, i| Ok(util::or_error(Type::empty_tuple(), "log_error", i)),
+                    |a, i| {
+                        let level = a
+                            .get()
+                            .as_any()
+                            .downcast_ref::<data::string::String>()
+                            .unwrap()
+                            .0
+                            .parse();
+                        Ok(match level {
+                            Ok(level) => {
+                                *i.global.log_level.lock().unwrap() = level;
+                                Data::empty_tuple()
+                            }
+                            Err(e) => util::error_object("log_error", e, i),
+                        })
+                    },
+                ),
+            )
+            .add_var(
+                "log_level",
+                func(|_: (), i| Ok(i.global.log_level.lock().unwrap().to_string())),
+            )
+    }
+
+    /// Sets the minimum level a log record must have to be emitted.
+    /// Programs can change this using `log_set_level`.
+    pub fn set_log_level(self, level: LogLevel) -> Self {
+        *self.info_run.global.log_level.lock().unwrap() = level;
+        self
+    }
+    /// Passes log records to `sink` instead of printing them to stderr.
+    pub fn set_log_sink(self, sink: impl FnMut(LogRecord) + Send + Sync + 'static) -> Self {
+        *self.info_run.global.log_sink.lock().unwrap() = Some(Box::new(sink));
+        self
+    }
+}
+
+fn log_func(level: LogLevel) -> data::function::Function {
+    data::function::Function {
+        info: program::run::Info::neverused(),
+        info_check: Arc::new(Mutex::new(CheckInfo::neverused())),
+        fixed_type: None,
+        fixed_type_out: Arc::new(Mutex::new(None)),
+        out: Ok(Arc::new(|_a, _i| Ok(Type::empty_tuple()))),
+        run: Arc::new(move |a, i| {
+            if level < *i.global.log_level.lock().unwrap() {
+                return Ok(Data::empty_tuple());
+            }
+            let message = if let Some(s) = a.get().as_any().downcast_ref::<data::string::String>() {
+                s.0.clone()
+            } else {
+                a.get().with_info(i).to_string()
+            };
+            let record = LogRecord {
+                level,
+                message,
+                time: chrono::Local::now(),
+                source: i.global.call_site.clone(),
+            };
+            if let Some(sink) = &mut *i.global.log_sink.lock().unwrap() {
+                sink(record);
+            } else if let Some((_, stderr)) = &mut *i.global.stdout.lock().unwrap() {
+                let _ = writeln!(stderr, "{record}");
+                let _ = stderr.flush();
+            } else {
+                eprintln!("{record}");
+            }
+            Ok(Data::empty_tuple())
+        }),
+        inner_statements: None,
+    }
+}
+
+/// The severity of a log record. `Off` is only used as a minimum level, to disable logging.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+    Off,
+}
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+            Self::Off => "off",
+        }
+    }
+}
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+impl std::str::FromStr for LogLevel {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "debug" => Self::Debug,
+            "info" => Self::Info,
+            "warn" => Self::Warn,
+            "error" => Self::Error,
+            "off" => Self::Off,
+            _ => {
+                return Err(format!(
+                    "unknown log level {s:?}, expected \"debug\", \"info\", \"warn\", \"error\" or \"off\""
+                ))
+            }
+        })
+    }
+}
+
+/// A message logged by a mers program
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub message: String,
+    pub time: chrono::DateTime<chrono::Local>,
+    /// the statement which called the log function
+    pub source: Option<SourceRange>,
+}
+impl LogRecord {
+    /// the file containing the statement which logged this record, if the source code was loaded from a file
+    pub fn file(&self) -> Option<&Path> {
+        match self.source.as_ref()?.in_file().src_from() {
+            SourceFrom::File(path) => Some(path),
+            SourceFrom::Unspecified => None,
+        }
+    }
+    /// the (1-based) line and column of the statement which logged this record
+    pub fn line_and_column(&self) -> Option<(usize, usize)> {
+        let source = self.source.as_ref()?;
+        let src = source.in_file();
+        let pos = src.pos_in_og(source.start().pos(), true);
+        let mut line_start = 0;
+        let line = src
+            .src_og()
+            .line_spans()
+            .take_while(|l| {
+                if l.start() <= pos {
+                    line_start = l.start();
+                    true
+                } else {
+                    false
+                }
+            })
+            .count();
+        Some((line, pos - line_start + 1))
+    }
+}
+impl Display for LogRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:<5} ",
+            self.time.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.level.as_str().to_uppercase()
+        )?;
+        match (self.file(), self.line_and_column()) {
+            (Some(file), Some((line, _))) => write!(f, "[{}:{line}] ", file.to_string_lossy())?,
+            (None, Some((line, _))) => write!(f, "[line {line}] ")?,
+            _ => {}
+        }
+        write!(f, "{}", self.message)
+    }
+}
@@ -12,7 +12,7 @@ use super::{
         function::{fun, func, Funcs, StaticMersFunc},
         IntR, OneOf, OneOrNone,
     },
-    Config,
+    util, Config,
 };
 
 impl Config {
@@ -46,6 +46,20 @@ impl Config {
     /// `floor_to_int: fn` round all numbers towards -infty, return the result as an Int (saturates at the Int boundaries, hence the to_int instead of as_int)
     /// `truncate_to_int: fn` round all numbers towards 0, return the result as an Int (saturates at the Int boundaries, hence the to_int instead of as_int)
     /// `round_ties_even_to_int: fn` round ties (x.5) to the nearest even number, return the result as an Int (saturates at the Int boundaries, hence the to_int instead of as_int)
+    /// `pi: Float` and `e: Float` are the mathematical constants
+    /// `sqrt`, `cbrt`, `exp`, `ln`, `log2`, `log10: fn` return the (square/cube) root, e^x, or the natural/base-2/base-10 logarithm as a Float
+    /// `log: fn` takes `(x, base)` and returns the logarithm of x with respect to base
+    /// `sin`, `cos`, `tan`, `asin`, `acos`, `atan: fn` trigonometric functions (in radians)
+    /// `sinh`, `cosh`, `tanh`, `asinh`, `acosh`, `atanh: fn` hyperbolic functions
+    /// `atan2: fn` takes `(y, x)` and returns the angle of the point (x, y) in radians, from -pi to pi
+    /// `hypot: fn` takes `(x, y)` and returns sqrt(x^2 + y^2)
+    /// `is_nan`, `is_finite`, `is_infinite: fn` check a number. Ints are always finite.
+    /// `gcd: fn` returns the greatest common divisor of two Ints (always >= 0), or () if the result doesn't fit in an Int
+    /// `lcm: fn` returns the least common multiple of two Ints (always >= 0), or () if the result doesn't fit in an Int
+    /// `bit_and`, `bit_or`, `bit_xor: fn` bitwise operations on `(Int, Int)`
+    /// `bit_not: fn` inverts all bits of an Int
+    /// `shift_left: fn` takes `(a, n)` and returns a shifted left by n bits, or () if n < 0 or bits would be lost
+    /// `shift_right: fn` takes `(a, n)` and returns a shifted right by n bits (keeping the sign), or () if n < 0
     pub fn with_math(self) -> Self {
         self.add_var(
             "parse_float",
@@ -653,7 +667,387 @@ impl Config {
                 Ok(isize_from(v.round_ties_even()))
             }),
         )
+        .add_var("pi", data::float::Float(std::f64::consts::PI))
+        .add_var("e", data::float::Float(std::f64::consts::E))
+        .add_var("sqrt", func_float(f64::sqrt))
+        .add_var("cbrt", func_float(f64::cbrt))
+        .add_var("exp", func_float(f64::exp))
+        .add_var("ln", func_float(f64::ln))
+        .add_var("log2", func_float(f64::log2))
+        .add_var("log10", func_float(f64::log10))
+        .add_var("log", func_float2(f64::log))
+        .add_var("sin", func_float(f64::sin))
+        .add_var("cos", func_float(f64::cos))
+        .add_var("tan", func_float(f64::tan))
+        .add_var("asin", func_float(f64::asin))
+        .add_var("acos", func_float(f64::acos))
+        .add_var("atan", func_float(f64::atan))
+        .add_var("atan2", func_float2(f64::atan2))
+        .add_var("sinh", func_float(f64::sinh))
+        .add_var("cosh", func_float(f64::cosh))
+        .add_var("tanh", func_float(f64::tanh))
+        .add_var("asinh", func_float(f64::asinh))
+        .add_var("acosh", func_float(f64::acosh))
+        .add_var("atanh", func_float(f64::atanh))
+        .add_var("hypot", func_float2(f64::hypot))
+        .add_var(
+            "is_nan",
+            func(|n: OneOf<IntR<INT_MIN, INT_MAX>, f64>, _| {
+                Ok(matches!(n, OneOf::B(n) if n.is_nan()))
+            }),
+        )
+        .add_var(
+            "is_finite",
+            func(|n: OneOf<IntR<INT_MIN, INT_MAX>, f64>, _| {
+                Ok(match n {
+                    OneOf::A(_) => true,
+                    OneOf::B(n) => n.is_finite(),
+                })
+            }),
+        )
+        .add_var(
+            "is_infinite",
+            func(|n: OneOf<IntR<INT_MIN, INT_MAX>, f64>, _| {
+                Ok(matches!(n, OneOf::B(n) if n.is_infinite()))
+            }),
+        )
+        .add_var(
+            "gcd",
+            func_int_op(
+                "gcd",
+                |a, b| {
+                    let zero = |r: IntRange| r.0 <= 0 && 0 <= r.1;
+                    let min = |r: IntRange| r.0 == INT_MIN;
+                    let may_fail = (min(a) && (zero(b) || min(b))) || (min(b) && zero(a));
+                    let max = max_abs(a).max(max_abs(b)).min(INT_MAX as u128) as isize;
+                    let zero = zero(a) && zero(b);
+                    (Some((if zero { 0 } else { 1 }, max)), may_fail)
+                },
+                |a, b| gcd(a.unsigned_abs(), b.unsigned_abs()).try_into().ok(),
+            ),
+        )
+        .add_var(
+            "lcm",
+            func_int_op(
+                "lcm",
+                |a, b| {
+                    let zero = |r: IntRange| r.0 <= 0 && 0 <= r.1;
+                    let max = max_abs(a) * max_abs(b);
+                    (
+                        Some((
+                            if zero(a) || zero(b) { 0 } else { 1 },
+                            max.min(INT_MAX as u128) as isize,
+                        )),
+                        max > INT_MAX as u128,
+                    )
+                },
+                |a, b| {
+                    let (a, b) = (a.unsigned_abs(), b.unsigned_abs());
+                    if a == 0 || b == 0 {
+                        Some(0)
+                    } else {
+                        (a / gcd(a, b)).checked_mul(b)?.try_into().ok()
+                    }
+                },
+            ),
+        )
+        .add_var(
+            "bit_and",
+            func_int_op(
+                "bit_and",
+                |a, b| {
+                    let min = if a.0 >= 0 || b.0 >= 0 {
+                        0
+                    } else {
+                        !bit_fill(!a.0.min(b.0))
+                    };
+                    let max = match (a.0 >= 0, b.0 >= 0) {
+                        (true, true) => a.1.min(b.1),
+                        (true, false) => a.1,
+                        (false, true) => b.1,
+                        (false, false) if a.1 < 0 && b.1 < 0 => a.1.min(b.1),
+                        (false, false) => a.1.max(b.1),
+                    };
+                    (Some((min, max)), false)
+                },
+                |a, b| Some(a & b),
+            ),
+        )
+        .add_var(
+            "bit_or",
+            func_int_op(
+                "bit_or",
+                |a, b| {
+                    let min = if a.0 >= 0 && b.0 >= 0 {
+                        a.0.max(b.0)
+                    } else {
+                        a.0.min(b.0)
+                    };
+                    let max = if a.1 < 0 || b.1 < 0 {
+                        -1
+                    } else {
+                        bit_fill(a.1.max(b.1))
+                    };
+                    (Some((min, max)), false)
+                },
+                |a, b| Some(a | b),
+            ),
+        )
+        .add_var(
+            "bit_xor",
+            func_int_op(
+                "bit_xor",
+                |a, b| {
+                    let range = if a.0 >= 0 && b.0 >= 0 {
+                        (0, bit_fill(a.1.max(b.1)))
+                    } else if a.1 < 0 && b.1 < 0 {
+                        (0, bit_fill((!a.0).max(!b.0)))
+                    } else if a.0 >= 0 && b.1 < 0 {
+                        (!bit_fill(a.1.max(!b.0)), -1)
+                    } else if a.1 < 0 && b.0 >= 0 {
+                        (!bit_fill((!a.0).max(b.1)), -1)
+                    } else {
+                        let fill = bit_fill(a.1.max(b.1).max(!a.0).max(!b.0));
+                        (!fill, fill)
+                    };
+                    (Some(range), false)
+                },
+                |a, b| Some(a ^ b),
+            ),
+        )
+        .add_var(
+            "bit_not",
+            Function::new_generic(
+                |a, i| {
+                    let mut o = Type::empty();
+                    for t in &a.types {
+                        if let Some(t) = t.as_any().downcast_ref::<data::int::IntT>() {
+                            o.add(Arc::new(data::int::IntT(!t.1, !t.0)));
+                        } else {
+                            return Err(format!(
+                                "called `bit_not` on `{}`, but it takes an `Int`",
+                                a.with_info(i)
+                            )
+                            .into());
+                        }
+                    }
+                    Ok(o)
+                },
+                |a, _| {
+                    let a = a.get();
+                    let a = a
+                        .as_any()
+                        .downcast_ref::<data::int::Int>()
+                        .expect("called `bit_not` on non-Int");
+                    Ok(Data::new(data::int::Int(!a.0)))
+                },
+            ),
+        )
+        .add_var(
+            "shift_left",
+            func_int_op(
+                "shift_left",
+                |a, b| {
+                    if b.1 < 0 {
+                        return (None, true);
+                    }
+                    let (bmin, bmax) = (b.0.clamp(0, 63) as u32, b.1.min(63) as u32);
+                    let shl = |v: isize, s: u32| (v as i128) << s;
+                    let min = if a.0 >= 0 { shl(a.0, bmin) } else { shl(a.0, bmax) };
+                    let max = if a.1 >= 0 { shl(a.1, bmax) } else { shl(a.1, bmin) };
+                    let may_fail = b.0 < 0
+                        || min < INT_MIN as i128
+                        || max > INT_MAX as i128
+                        || (b.1 > 63 && (a.0 != 0 || a.1 != 0));
+                    (
+                        Some((
+                            min.max(INT_MIN as i128) as isize,
+                            max.min(INT_MAX as i128) as isize,
+                        )),
+                        may_fail,
+                    )
+                },
+                |a, b| {
+                    if b < 0 {
+                        None
+                    } else if a == 0 {
+                        Some(0)
+                    } else if b > 63 {
+                        None
+                    } else {
+                        ((a as i128) << b).try_into().ok()
+                    }
+                },
+            ),
+        )
+        .add_var(
+            "shift_right",
+            func_int_op(
+                "shift_right",
+                |a, b| {
+                    if b.1 < 0 {
+                        return (None, true);
+                    }
+                    let (bmin, bmax) = (b.0.clamp(0, 63), b.1.min(63));
+                    let min = if a.0 >= 0 { a.0 >> bmax } else { a.0 >> bmin };
+                    let max = if a.1 >= 0 { a.1 >> bmin } else { a.1 >> bmax };
+                    (Some((min, max)), b.0 < 0)
+                },
+                |a, b| if b < 0 { None } else { Some(a >> b.min(63)) },
+            ),
+        )
+    }
+}
+
+fn int_or_float_type() -> Type {
+    Type::newm(vec![
+        Arc::new(data::int::IntT(INT_MIN, INT_MAX)),
+        Arc::new(data::float::FloatT),
+    ])
+}
+fn as_f64(v: &Data) -> f64 {
+    let v = v.get();
+    if let Some(v) = v.as_any().downcast_ref::<data::int::Int>() {
+        v.0 as f64
+    } else {
+        v.as_any()
+            .downcast_ref::<data::float::Float>()
+            .expect("float function called on non-Int/Float")
+            .0
+    }
+}
+
+/// a function which takes an `Int/Float`, converts it to a float, and returns `f(x)` as a `Float`.
+fn func_float(f: fn(f64) -> f64) -> Function {
+    util::to_mers_func_with_in_out_types(
+        int_or_float_type(),
+        Type::new(data::float::FloatT),
+        move |a, _| Ok(Data::new(data::float::Float(f(as_f64(&a))))),
+    )
+}
+/// like `func_float`, but for functions which take a 2-tuple `(Int/Float, Int/Float)`.
+fn func_float2(f: fn(f64, f64) -> f64) -> Function {
+    util::to_mers_func_with_in_out_types(
+        Type::new(data::tuple::TupleT(vec![
+            int_or_float_type(),
+            int_or_float_type(),
+        ])),
+        Type::new(data::float::FloatT),
+        move |a, _| {
+            let t = a.get();
+            let a = &t
+                .as_any()
+                .downcast_ref::<data::tuple::Tuple>()
+                .expect("float function called on non-tuple")
+                .0;
+            let v = f(as_f64(&a[0].read()), as_f64(&a[1].read()));
+            Ok(Data::new(data::float::Float(v)))
+        },
+    )
+}
+
+/// `(min, max)` of an `IntT`
+type IntRange = (isize, isize);
+/// for operations on two `Int`s, `(Int, Int)`, where the range of the result can be derived from the ranges of the inputs.
+///
+/// `op_ranges` gets the ranges of the two inputs and returns the range of the result
+/// (or `None` if the operation can never succeed) and whether the operation may fail.
+/// `op` returns `None` if it failed, in which case the function returns `()`.
+/// If both inputs are known exactly, `op` is used to get the exact result type.
+fn func_int_op(
+    name: &'static str,
+    op_ranges: fn(IntRange, IntRange) -> (Option<IntRange>, bool),
+    op: fn(isize, isize) -> Option<isize>,
+) -> Function {
+    Function::new_generic(
+        move |a, i| {
+            let mut o = Type::empty();
+            let mut may_fail = false;
+            for t in &a.types {
+                let Some(t) = t
+                    .as_any()
+                    .downcast_ref::<data::tuple::TupleT>()
+                    .filter(|t| t.0.len() == 2)
+                else {
+                    return Err(format!(
+                        "called `{name}` on `{}`, but it takes a tuple `(Int, Int)`",
+                        a.with_info(i)
+                    )
+                    .into());
+                };
+                for l in &t.0[0].types {
+                    for r in &t.0[1].types {
+                        let (Some(l), Some(r)) = (
+                            l.as_any().downcast_ref::<data::int::IntT>(),
+                            r.as_any().downcast_ref::<data::int::IntT>(),
+                        ) else {
+                            return Err(format!(
+                                "called `{name}` on `{}`, but it takes a tuple `(Int, Int)`",
+                                a.with_info(i)
+                            )
+                            .into());
+                        };
+                        let (range, fails) = if l.0 == l.1 && r.0 == r.1 {
+                            match op(l.0, r.0) {
+                                Some(v) => (Some((v, v)), false),
+                                None => (None, true),
+                            }
+                        } else {
+                            op_ranges((l.0, l.1), (r.0, r.1))
+                        };
+                        if let Some((min, max)) = range {
+                            o.add(Arc::new(data::int::IntT(min, max)));
+                        }
+                        may_fail |= fails;
+                    }
+                }
+            }
+            if may_fail {
+                o.add(Arc::new(data::tuple::TupleT(vec![])));
+            }
+            Ok(o)
+        },
+        move |a, _| {
+            let t = a.get();
+            let a = &t
+                .as_any()
+                .downcast_ref::<data::tuple::Tuple>()
+                .expect("int op called on non-tuple")
+                .0;
+            let (l, r) = (a[0].read(), a[1].read());
+            let (l, r) = (l.get(), r.get());
+            let (l, r) = (
+                l.as_any()
+                    .downcast_ref::<data::int::Int>()
+                    .expect("int op called on non-Int"),
+                r.as_any()
+                    .downcast_ref::<data::int::Int>()
+                    .expect("int op called on non-Int"),
+            );
+            Ok(match op(l.0, r.0) {
+                Some(v) => Data::new(data::int::Int(v)),
+                None => Data::empty_tuple(),
+            })
+        },
+    )
+}
+
+/// the smallest `2^n - 1` which is `>= v` (`0` for `v <= 0`)
+fn bit_fill(v: isize) -> isize {
+    if v <= 0 {
+        0
+    } else {
+        INT_MAX >> (v.leading_zeros() - 1)
+    }
+}
+fn max_abs(r: IntRange) -> u128 {
+    r.0.unsigned_abs().max(r.1.unsigned_abs()) as u128
+}
+fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        (a, b) = (b, a % b);
     }
+    a
 }
 
 const ISIZE_MAX_F: f64 = isize::MAX as _;
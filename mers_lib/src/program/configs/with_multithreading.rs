@@ -1,23 +1,49 @@
 use std::{
+    collections::VecDeque,
     fmt::{Debug, Display},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{self, AtomicBool, AtomicUsize},
+        Arc, Condvar, Mutex,
+    },
     thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    data::{self, Data, MersData, MersType, MersTypeWInfo, Type},
+    data::{
+        self,
+        int::{IntT, INT_MAX},
+        Data, MersData, MersType, MersTypeWInfo, Type,
+    },
     errors::CheckError,
     info::DisplayInfo,
     parsing::{statements::to_string_literal, Source},
-    program::{self, run::CheckInfo},
+    program::{
+        self,
+        run::{chain::run_callback, CheckInfo},
+    },
 };
 
-use super::Config;
+use super::{
+    util::{self, maybe},
+    with_list::{List, ListT},
+    Config,
+};
 
 impl Config {
     /// `thread: fn` turns `func /* () -> t */` into a `Thread`, which will run the function.
     /// `thread_finished: fn` returns `false` while the thread is running and `true` otherwise.
     /// `thread_await: fn` returns `t`, the value produced by the thread's function.
+    /// `channel: fn` creates a `Channel<T>` from a `List<T>`, whose elements will be the first values in the channel. To create an empty channel, use `q := [List<T>] ().as_list, q.channel`.
+    ///   `(list, capacity).channel` creates a channel which can hold at most `capacity` values, `send` then waits until there is space in the channel.
+    /// `send: fn` adds a value to the channel: (channel, value).send. returns false (without sending) if the channel was closed, true otherwise.
+    /// `recv: fn` waits until a value is available and returns (value), or () once the channel is closed and empty.
+    /// `try_recv: fn` returns (value) if one is available and () otherwise, without waiting.
+    /// `recv_timeout: fn` like recv, but waits for at most the given number of seconds: (channel, 0.5).recv_timeout
+    /// `close: fn` closes the channel. values which were sent before can still be received, but nothing can be sent anymore.
+    /// `par_map: fn` like `map`, but calls the function on multiple threads at once and returns a List of the results, in the original order: (iter, v -> ...).par_map
+    /// `par_for_each: fn` like `for_each`, but calls the function on multiple threads at once
+    ///   (the number of threads can be limited using `Config::set_parallelism`. if a function returns an error, no more elements are processed and the error of the first element which caused one is returned.)
     pub fn with_multithreading(self) -> Self {
         self.add_type(
             "Thread".to_string(),
@@ -51,9 +77,14 @@ impl Config {
                 })),
                 run: Arc::new(|a, i| {
                     let gi = i.global.clone();
-                    Ok(Data::new(Thread(Arc::new(Mutex::new(Ok(std::thread::spawn(
-                        move || a.get().execute(Data::empty_tuple(), &gi).unwrap(),
-                    )))))))
+                    let func_type = a.get().executable();
+                    Ok(Data::new(Thread(
+                        Arc::new(Mutex::new(Ok(std::thread::spawn(move || {
+                            // errors show where the thread was started
+                            run_callback(&**a.get(), Data::empty_tuple(), &gi, gi.call_site.as_ref()).unwrap()
+                        })))),
+                        func_type,
+                    )))
                 }),
                 inner_statements: None,
             },
@@ -109,12 +140,172 @@ impl Config {
                 }),
                 inner_statements: None,
             })
+            .add_type(
+                "Channel".to_string(),
+                Err(Arc::new(|s, i| {
+                    let mut src = Source::new_from_string_raw(s.to_owned());
+                    let srca = Arc::new(src.clone());
+                    let t = crate::parsing::types::parse_type(&mut src, &srca)?;
+                    Ok(Arc::new(Type::new(ChannelT(crate::parsing::types::type_from_parsed(&t, i)?))))
+                })),
+            )
+            .add_var(
+                "channel",
+                util::to_mers_func(
+                    |a, i| {
+                        let mut out = Type::empty();
+                        for t in a.types.iter() {
+                            let (list, capacity) = if let Some(t) = t.as_any().downcast_ref::<data::tuple::TupleT>().filter(|t| t.0.len() == 2) {
+                                (&t.0[0], Some(&t.0[1]))
+                            } else {
+                                (a, None)
+                            };
+                            if capacity.is_some_and(|c| !c.is_included_in_single(&IntT(1, INT_MAX))) {
+                                return Err(format!("channel: capacity must be an Int<1..>, but the argument was {}", a.with_info(i)).into());
+                            }
+                            for t in list.types.iter() {
+                                if let Some(t) = t.as_any().downcast_ref::<ListT>() {
+                                    out.add(Arc::new(ChannelT(t.0.clone())));
+                                } else {
+                                    return Err(format!("channel: expected List or (List, Int), but got {}", a.with_info(i)).into());
+                                }
+                            }
+                        }
+                        Ok(out)
+                    },
+                    |a, _i| {
+                        let tuple = a.get().as_any().downcast_ref::<data::tuple::Tuple>().cloned();
+                        let (list, capacity) = if let Some(t) = tuple {
+                            let capacity = t.0[1].read().get().as_any().downcast_ref::<data::int::Int>().unwrap().0;
+                            (t.0[0].read().clone(), Some(capacity as usize))
+                        } else {
+                            (a, None)
+                        };
+                        let list = list.get().as_any().downcast_ref::<List>().unwrap().0.clone();
+                        Ok(Data::new(Channel::new(list.into(), capacity)))
+                    },
+                ),
+            )
+            .add_var(
+                "send",
+                util::to_mers_func(
+                    |a, i| {
+                        for t in a.types.iter() {
+                            let t = t.as_any().downcast_ref::<data::tuple::TupleT>().filter(|t| t.0.len() == 2).ok_or_else(|| format!("send: expected (Channel, value), but got {}", a.with_info(i)))?;
+                            for elem in channel_elem_types(&t.0[0], "send", i)? {
+                                if !t.0[1].is_included_in(&elem) {
+                                    return Err(format!("send: can't send a value of type {} to a channel of {}", t.0[1].with_info(i), elem.with_info(i)).into());
+                                }
+                            }
+                        }
+                        Ok(data::bool::bool_type())
+                    },
+                    |a, i| {
+                        let a = a.get();
+                        let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+                        let channel = a.0[0].read().get().as_any().downcast_ref::<Channel>().cloned().unwrap();
+                        let v = a.0[1].read().clone();
+                        Ok(Data::new(data::bool::Bool(channel.send(v, i.global.limit_runtime))))
+                    },
+                ),
+            )
+            .add_var(
+                "recv",
+                util::to_mers_func(
+                    |a, i| recv_type(a, "recv", i),
+                    |a, i| {
+                        let a = a.get();
+                        let channel = a.as_any().downcast_ref::<Channel>().unwrap();
+                        Ok(maybe(channel.recv(i.global.limit_runtime)))
+                    },
+                ),
+            )
+            .add_var(
+                "try_recv",
+                util::to_mers_func(
+                    |a, i| recv_type(a, "try_recv", i),
+                    |a, _i| {
+                        let a = a.get();
+                        let channel = a.as_any().downcast_ref::<Channel>().unwrap();
+                        Ok(maybe(channel.recv(Some(Instant::now()))))
+                    },
+                ),
+            )
+            .add_var(
+                "recv_timeout",
+                util::to_mers_func(
+                    |a, i| {
+                        let mut out = Type::empty();
+                        for t in a.types.iter() {
+                            let t = t.as_any().downcast_ref::<data::tuple::TupleT>().filter(|t| {
+                                t.0.len() == 2 && t.0[1].is_included_in(&Type::newm(vec![Arc::new(IntT(0, INT_MAX)), Arc::new(data::float::FloatT)]))
+                            }).ok_or_else(|| format!("recv_timeout: expected (Channel, Int/Float), but got {}", a.with_info(i)))?;
+                            out.add_all(&recv_type(&t.0[0], "recv_timeout", i)?);
+                        }
+                        Ok(out)
+                    },
+                    |a, i| {
+                        let a = a.get();
+                        let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+                        let channel = a.0[0].read().get().as_any().downcast_ref::<Channel>().cloned().unwrap();
+                        let timeout = a.0[1].read();
+                        let timeout = timeout.get();
+                        let timeout = if let Some(v) = timeout.as_any().downcast_ref::<data::int::Int>() {
+                            Duration::from_secs(v.0.max(0) as u64)
+                        } else {
+                            // `max` turns NaN into 0
+                            Duration::try_from_secs_f64(timeout.as_any().downcast_ref::<data::float::Float>().unwrap().0.max(0.0)).unwrap_or(Duration::MAX)
+                        };
+                        let deadline = Instant::now().checked_add(timeout);
+                        let deadline = match (deadline, i.global.limit_runtime) {
+                            (Some(d), Some(limit)) => Some(d.min(limit)),
+                            (d, limit) => d.or(limit),
+                        };
+                        Ok(maybe(channel.recv(deadline)))
+                    },
+                ),
+            )
+            .add_var(
+                "par_map",
+                util::to_mers_func(
+                    |a, i| Ok(Type::new(ListT(par_func_out(a, "par_map", i)?))),
+                    |a, i| Ok(Data::new(List(par_run(a, &i.global)?))),
+                ),
+            )
+            .add_var(
+                "par_for_each",
+                util::to_mers_func(
+                    |a, i| {
+                        par_func_out(a, "par_for_each", i)?;
+                        Ok(Type::empty_tuple())
+                    },
+                    |a, i| {
+                        par_run(a, &i.global)?;
+                        Ok(Data::empty_tuple())
+                    },
+                ),
+            )
+            .add_var(
+                "close",
+                util::to_mers_func(
+                    |a, i| {
+                        channel_elem_types(a, "close", i)?;
+                        Ok(Type::empty_tuple())
+                    },
+                    |a, _i| {
+                        a.get().as_any().downcast_ref::<Channel>().unwrap().close();
+                        Ok(Data::empty_tuple())
+                    },
+                ),
+            )
     }
 }
 
 #[derive(Clone)]
 pub struct Thread(
     pub Arc<Mutex<Result<JoinHandle<Result<Data, CheckError>>, Result<Data, CheckError>>>>,
+    /// the type of the function the thread is running, used by `as_type`
+    pub Option<data::function::FunctionT>,
 );
 #[derive(Debug, Clone)]
 pub struct ThreadT(pub Type);
@@ -130,7 +321,12 @@ impl MersData for Thread {
         Box::new(Clone::clone(self))
     }
     fn as_type(&self) -> Type {
-        unreachable!("can't get type from Thread value! (can't construct Thread with syntax, so this should be fine?)")
+        Type::new(ThreadT(
+            self.1
+                .as_ref()
+                .and_then(|f| f.o(&Type::empty_tuple()).ok())
+                .unwrap_or_else(Type::empty),
+        ))
     }
     fn as_any(&self) -> &dyn std::any::Any {
         self
@@ -196,3 +392,278 @@ impl Display for Thread {
         write!(f, "<Thread>")
     }
 }
+
+/// the output type of the function(s) when called with the iterable's elements, for `(iterable, function)` tuples
+fn par_func_out(a: &Type, func: &str, i: &CheckInfo) -> Result<Type, CheckError> {
+    let mut out = Type::empty();
+    for t in a.types.iter() {
+        let t = t
+            .as_any()
+            .downcast_ref::<data::tuple::TupleT>()
+            .filter(|t| t.0.len() == 2)
+            .ok_or_else(|| {
+                format!(
+                    "{func}: expected (iterable, function), but got {}",
+                    a.with_info(i)
+                )
+            })?;
+        let elem = t.0[0]
+            .iterable()
+            .ok_or_else(|| format!("{func}: {} is not iterable", t.0[0].with_info(i)))?;
+        for f in t.0[1].types.iter() {
+            let f = f
+                .executable()
+                .ok_or_else(|| format!("{func}: {} is not a function", f.with_info(i)))?;
+            out.add_all(&f.o(&elem)?);
+        }
+    }
+    Ok(out)
+}
+/// calls the function on each element of the iterable, using a pool of up to `gi.parallelism` threads.
+fn par_run(a: Data, gi: &program::run::RunLocalGlobalInfo) -> Result<Vec<Data>, CheckError> {
+    let a = a.get();
+    let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+    let (iter, f) = (a.get(0).unwrap(), a.get(1).unwrap());
+    let elems = iter
+        .get()
+        .iterable(gi)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()?;
+    let threads = gi
+        .parallelism
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, |n| n.get())
+        .min(elems.len());
+    // each thread takes the next element until all are done or an error occurred.
+    // because elements are taken in order, all elements before one which caused an error are always processed.
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results = Mutex::new((0..elems.len()).map(|_| None).collect::<Vec<_>>());
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                let f = f.get();
+                while !failed.load(atomic::Ordering::Relaxed) {
+                    let index = next.fetch_add(1, atomic::Ordering::Relaxed);
+                    let Some(v) = elems.get(index) else {
+                        break;
+                    };
+                    let r = run_callback(&**f, v.clone(), gi, gi.call_site.as_ref()).unwrap();
+                    if r.is_err() {
+                        failed.store(true, atomic::Ordering::Relaxed);
+                    }
+                    results.lock().unwrap()[index] = Some(r);
+                }
+            });
+        }
+    });
+    // unprocessed elements come after the first error, so they are never reached here
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect()
+}
+/// the element types of all channels in `t`
+fn channel_elem_types(t: &Type, func: &str, i: &CheckInfo) -> Result<Vec<Type>, CheckError> {
+    t.types
+        .iter()
+        .map(|t| {
+            t.as_any()
+                .downcast_ref::<ChannelT>()
+                .map(|t| t.0.clone())
+                .ok_or_else(|| {
+                    format!("{func}: expected a Channel, but found {}", t.with_info(i)).into()
+                })
+        })
+        .collect()
+}
+/// `(T)/()`, where `T` are the element types of the channels in `t`
+fn recv_type(t: &Type, func: &str, i: &CheckInfo) -> Result<Type, CheckError> {
+    let mut out = Type::empty();
+    for t in channel_elem_types(t, func, i)? {
+        out.add_all(&t);
+    }
+    Ok(Type::newm(vec![
+        Arc::new(data::tuple::TupleT(vec![out])),
+        Arc::new(data::tuple::TupleT(vec![])),
+    ]))
+}
+
+/// A queue which threads can use to send values to each other.
+/// Clones refer to the same channel.
+#[derive(Clone)]
+pub struct Channel(pub Arc<ChannelInner>);
+pub struct ChannelInner {
+    state: Mutex<ChannelState>,
+    /// the types of all values which were ever in the channel, used by `as_type`
+    elem_type: Mutex<Type>,
+    /// notified whenever a value is added or removed, or when the channel is closed
+    changed: Condvar,
+    capacity: Option<usize>,
+}
+struct ChannelState {
+    queue: VecDeque<Data>,
+    closed: bool,
+}
+/// `Channel<T>`. At runtime, `T` is the type of all values which were ever in the channel (like the inner type of a reference).
+#[derive(Debug, Clone)]
+pub struct ChannelT(pub Type);
+
+impl Channel {
+    pub fn new(queue: VecDeque<Data>, capacity: Option<usize>) -> Self {
+        let mut elem_type = Type::empty();
+        for v in queue.iter() {
+            elem_type.add_all(&v.get().as_type());
+        }
+        Self(Arc::new(ChannelInner {
+            elem_type: Mutex::new(elem_type),
+            state: Mutex::new(ChannelState {
+                queue,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+            capacity,
+        }))
+    }
+    /// waits until there is space in the channel (if it has a capacity) or until the `deadline`,
+    /// then adds `v`. returns false if the channel was closed or the deadline passed.
+    pub fn send(&self, v: Data, deadline: Option<Instant>) -> bool {
+        let mut state = self.0.state.lock().unwrap();
+        while self
+            .0
+            .capacity
+            .is_some_and(|capacity| state.queue.len() >= capacity)
+            && !state.closed
+        {
+            state = match self.wait(state, deadline) {
+                Some(state) => state,
+                None => return false,
+            };
+        }
+        if state.closed {
+            return false;
+        }
+        self.0.elem_type.lock().unwrap().add_all(&v.get().as_type());
+        state.queue.push_back(v);
+        self.0.changed.notify_all();
+        true
+    }
+    /// waits until a value is available or until the `deadline`, then removes and returns it.
+    /// returns `None` if the channel was closed and is empty, or if the deadline passed.
+    pub fn recv(&self, deadline: Option<Instant>) -> Option<Data> {
+        let mut state = self.0.state.lock().unwrap();
+        loop {
+            if let Some(v) = state.queue.pop_front() {
+                self.0.changed.notify_all();
+                return Some(v);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.wait(state, deadline)?;
+        }
+    }
+    /// the types of all values which were ever in the channel
+    pub fn elem_type(&self) -> Type {
+        self.0.elem_type.lock().unwrap().clone()
+    }
+    pub fn close(&self) {
+        self.0.state.lock().unwrap().closed = true;
+        self.0.changed.notify_all();
+    }
+    fn wait<'a>(
+        &self,
+        state: std::sync::MutexGuard<'a, ChannelState>,
+        deadline: Option<Instant>,
+    ) -> Option<std::sync::MutexGuard<'a, ChannelState>> {
+        if let Some(deadline) = deadline {
+            let timeout = deadline.checked_duration_since(Instant::now())?;
+            let (state, result) = self.0.changed.wait_timeout(state, timeout).unwrap();
+            if result.timed_out() {
+                None
+            } else {
+                Some(state)
+            }
+        } else {
+            Some(self.0.changed.wait(state).unwrap())
+        }
+    }
+}
+
+impl MersData for Channel {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| Arc::ptr_eq(&self.0, &other.0))
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(ChannelT(self.elem_type()))
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl MersType for ChannelT {
+    fn display(
+        &self,
+        info: &crate::info::DisplayInfo<'_>,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "Channel<{}>",
+            to_string_literal(&self.0.with_display(info).to_string(), '>')
+        )
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| self.0.is_same_type_as(&other.0))
+    }
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        // values can be sent and received, so, like references, `Channel<Int>` isn't included in `Channel<Int/Float>`
+        self.is_same_type_as(target)
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl Debug for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Channel>")
+    }
+}
+impl Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Channel>")
+    }
+}
@@ -0,0 +1,557 @@
+use std::{
+    fmt::Display,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    data::{self, function::Function, int::INT_MAX, Data, MersData, MersType, Type},
+    info::DisplayInfo,
+    program::run::Info,
+};
+
+use super::{
+    util::{self, bytes_arg, check_bytes_arg, error_object, or_error, string_arg},
+    with_list::{List, ListT},
+    Config,
+};
+
+impl Config {
+    /// adds TCP and UDP networking. functions which can fail return `{net_error: String}` on failure.
+    /// `tcp_bind: fn` listens for TCP connections on an address: "127.0.0.1:8080".tcp_bind returns a `TcpListener`
+    /// `tcp_accept: fn` waits for a connection to a `TcpListener` and returns a `TcpStream`
+    /// `tcp_connect: fn` connects to an address and returns a `TcpStream`: "127.0.0.1:8080".tcp_connect, or, with a timeout in seconds, ("127.0.0.1:8080", 0.5).tcp_connect
+    /// `tcp_read_line: fn` reads a line (including the newline) from a `TcpStream`. returns (line), or () if the connection was closed by the other side.
+    /// `tcp_read_bytes: fn` reads at most n bytes: (stream, n).tcp_read_bytes returns a `List<Byte>`, which is empty if the connection was closed by the other side.
+    /// `tcp_write_string: fn` (stream, string).tcp_write_string sends the string's bytes
+    /// `tcp_write_bytes: fn` (stream, bytes).tcp_write_bytes sends bytes from any iterable over Bytes
+    /// `udp_bind: fn` creates a `UdpSocket` bound to an address: "127.0.0.1:0".udp_bind
+    /// `udp_connect: fn` (socket, address).udp_connect sets the address `udp_send` sends to, and ignores packets from other addresses
+    /// `udp_send: fn` (socket, bytes).udp_send sends a packet to the connected address
+    /// `udp_send_to: fn` (socket, address, bytes).udp_send_to sends a packet to the address
+    /// `udp_recv: fn` waits for a packet and returns (bytes, source_address)
+    /// `net_local_addr: fn` returns the local address of a `TcpListener`, `TcpStream` or `UdpSocket` (useful after binding to port 0)
+    /// `net_peer_addr: fn` returns the address of the other side of a `TcpStream`
+    /// `net_set_timeout: fn` (socket, seconds).net_set_timeout makes reads and writes on a `TcpStream` or `UdpSocket` fail if they take longer than that. use () to wait forever.
+    /// `net_close: fn` closes a `TcpListener`, `TcpStream` or `UdpSocket`. using it afterwards causes a net_error.
+    pub fn with_net(self) -> Self {
+        self.add_type(
+            "TcpListener".to_owned(),
+            Ok(Arc::new(Type::new(TcpListenerT))),
+        )
+        .add_type("TcpStream".to_owned(), Ok(Arc::new(Type::new(TcpStreamT))))
+        .add_type("UdpSocket".to_owned(), Ok(Arc::new(Type::new(UdpSocketT))))
+        .add_var(
+            "tcp_bind",
+            net_func(
+                Type::new(data::string::StringT),
+                Type::new(TcpListenerT),
+                |a, _| {
+                    let listener = std::net::TcpListener::bind(string_arg(a))?;
+                    Ok(Data::new(TcpListener::new(listener)))
+                },
+            ),
+        )
+        .add_var(
+            "tcp_accept",
+            net_func(Type::new(TcpListenerT), Type::new(TcpStreamT), |a, _| {
+                let listener = a.get().as_any().downcast_ref::<TcpListener>().cloned().unwrap();
+                // don't hold the lock while waiting, so `net_close` doesn't have to wait for a connection
+                let listener = listener.with(|l| l.try_clone())?;
+                let (stream, _) = listener.accept()?;
+                Ok(Data::new(TcpStream::new(Connection::new(stream)?)))
+            }),
+        )
+        .add_var(
+            "tcp_connect",
+            net_func(
+                Type::newm(vec![
+                    Arc::new(data::string::StringT),
+                    Arc::new(data::tuple::TupleT(vec![
+                        Type::new(data::string::StringT),
+                        timeout_type(),
+                    ])),
+                ]),
+                Type::new(TcpStreamT),
+                |a, _| {
+                    let a = a.get();
+                    let stream = if let Some(addr) = a.as_any().downcast_ref::<data::string::String>() {
+                        std::net::TcpStream::connect(&addr.0)?
+                    } else {
+                        let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                        let addr = string_arg(&a[0].read());
+                        if let Some(timeout) = timeout_arg(&a[1].read()) {
+                            let mut err = None;
+                            let mut stream = None;
+                            for addr in addr.to_socket_addrs()? {
+                                match std::net::TcpStream::connect_timeout(&addr, timeout) {
+                                    Ok(s) => {
+                                        stream = Some(s);
+                                        break;
+                                    }
+                                    Err(e) => err = Some(e),
+                                }
+                            }
+                            match (stream, err) {
+                                (Some(s), _) => s,
+                                (None, Some(e)) => return Err(e),
+                                (None, None) => {
+                                    return Err(io::Error::new(
+                                        io::ErrorKind::InvalidInput,
+                                        "address did not resolve to any socket addresses",
+                                    ))
+                                }
+                            }
+                        } else {
+                            std::net::TcpStream::connect(addr)?
+                        }
+                    };
+                    Ok(Data::new(TcpStream::new(Connection::new(stream)?)))
+                },
+            ),
+        )
+        .add_var(
+            "tcp_read_line",
+            net_func(
+                Type::new(TcpStreamT),
+                Type::newm(vec![
+                    Arc::new(data::tuple::TupleT(vec![Type::new(data::string::StringT)])),
+                    Arc::new(data::tuple::TupleT(vec![])),
+                ]),
+                |a, _| {
+                    let stream = a.get().as_any().downcast_ref::<TcpStream>().cloned().unwrap();
+                    let reader = stream.with(|c| Ok(Arc::clone(&c.reader)))?;
+                    let mut line = String::new();
+                    Ok(if reader.lock().unwrap().read_line(&mut line)? == 0 {
+                        Data::empty_tuple()
+                    } else {
+                        Data::one_tuple(Data::new(data::string::String(line)))
+                    })
+                },
+            ),
+        )
+        .add_var(
+            "tcp_read_bytes",
+            net_func(
+                Type::new(data::tuple::TupleT(vec![
+                    Type::new(TcpStreamT),
+                    Type::new(data::int::IntT(1, INT_MAX)),
+                ])),
+                Type::new(ListT(Type::new(data::byte::ByteT))),
+                |a, _| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                    let stream = a[0].read().get().as_any().downcast_ref::<TcpStream>().cloned().unwrap();
+                    let n = a[1].read().get().as_any().downcast_ref::<data::int::Int>().unwrap().0;
+                    let mut buf = vec![0; (n as usize).min(1 << 20)];
+                    let reader = stream.with(|c| Ok(Arc::clone(&c.reader)))?;
+                    let len = reader.lock().unwrap().read(&mut buf)?;
+                    Ok(bytes_list(&buf[..len]))
+                },
+            ),
+        )
+        .add_var(
+            "tcp_write_string",
+            net_func(
+                Type::new(data::tuple::TupleT(vec![
+                    Type::new(TcpStreamT),
+                    Type::new(data::string::StringT),
+                ])),
+                Type::empty_tuple(),
+                |a, _| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                    let stream = a[0].read().get().as_any().downcast_ref::<TcpStream>().cloned().unwrap();
+                    let string = string_arg(&a[1].read());
+                    stream.with(|c| c.stream.try_clone())?.write_all(string.as_bytes())?;
+                    Ok(Data::empty_tuple())
+                },
+            ),
+        )
+        .add_var(
+            "tcp_write_bytes",
+            util::to_mers_func(
+                |a, i| {
+                    for t in a.types.iter() {
+                        let t = t
+                            .as_any()
+                            .downcast_ref::<data::tuple::TupleT>()
+                            .filter(|t| t.0.len() == 2 && t.0[0].is_included_in_single(&TcpStreamT))
+                            .ok_or_else(|| format!("tcp_write_bytes: expected (TcpStream, Iter<Byte>), but got {}", a.with_info(i)))?;
+                        check_bytes_arg(&t.0[1], "tcp_write_bytes", i)?;
+                    }
+                    Ok(or_error(Type::empty_tuple(), "net_error", i))
+                },
+                |a, i| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                    let stream = a[0].read().get().as_any().downcast_ref::<TcpStream>().cloned().unwrap();
+                    let bytes = bytes_arg(&a[1].read(), i)?;
+                    Ok(net_result(stream.with(|c| c.stream.try_clone()).and_then(|mut s| s.write_all(&bytes)).map(|()| Data::empty_tuple()), i))
+                },
+            ),
+        )
+        .add_var(
+            "udp_bind",
+            net_func(
+                Type::new(data::string::StringT),
+                Type::new(UdpSocketT),
+                |a, _| {
+                    let socket = std::net::UdpSocket::bind(string_arg(a))?;
+                    Ok(Data::new(UdpSocket::new(socket)))
+                },
+            ),
+        )
+        .add_var(
+            "udp_connect",
+            net_func(
+                Type::new(data::tuple::TupleT(vec![
+                    Type::new(UdpSocketT),
+                    Type::new(data::string::StringT),
+                ])),
+                Type::empty_tuple(),
+                |a, _| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                    let socket = a[0].read().get().as_any().downcast_ref::<UdpSocket>().cloned().unwrap();
+                    let addr = string_arg(&a[1].read());
+                    socket.with(|s| s.connect(addr))?;
+                    Ok(Data::empty_tuple())
+                },
+            ),
+        )
+        .add_var(
+            "udp_send",
+            util::to_mers_func(
+                |a, i| {
+                    for t in a.types.iter() {
+                        let t = t
+                            .as_any()
+                            .downcast_ref::<data::tuple::TupleT>()
+                            .filter(|t| t.0.len() == 2 && t.0[0].is_included_in_single(&UdpSocketT))
+                            .ok_or_else(|| format!("udp_send: expected (UdpSocket, Iter<Byte>), but got {}", a.with_info(i)))?;
+                        check_bytes_arg(&t.0[1], "udp_send", i)?;
+                    }
+                    Ok(or_error(Type::empty_tuple(), "net_error", i))
+                },
+                |a, i| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                    let socket = a[0].read().get().as_any().downcast_ref::<UdpSocket>().cloned().unwrap();
+                    let bytes = bytes_arg(&a[1].read(), i)?;
+                    Ok(net_result(socket.with(|s| s.send(&bytes)).map(|_| Data::empty_tuple()), i))
+                },
+            ),
+        )
+        .add_var(
+            "udp_send_to",
+            util::to_mers_func(
+                |a, i| {
+                    for t in a.types.iter() {
+                        let t = t
+                            .as_any()
+                            .downcast_ref::<data::tuple::TupleT>()
+                            .filter(|t| {
+                                t.0.len() == 3
+                                    && t.0[0].is_included_in_single(&UdpSocketT)
+                                    && t.0[1].is_included_in_single(&data::string::StringT)
+                            })
+                            .ok_or_else(|| format!("udp_send_to: expected (UdpSocket, String, Iter<Byte>), but got {}", a.with_info(i)))?;
+                        check_bytes_arg(&t.0[2], "udp_send_to", i)?;
+                    }
+                    Ok(or_error(Type::empty_tuple(), "net_error", i))
+                },
+                |a, i| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                    let socket = a[0].read().get().as_any().downcast_ref::<UdpSocket>().cloned().unwrap();
+                    let addr = string_arg(&a[1].read());
+                    let bytes = bytes_arg(&a[2].read(), i)?;
+                    Ok(net_result(socket.with(|s| s.send_to(&bytes, addr)).map(|_| Data::empty_tuple()), i))
+                },
+            ),
+        )
+        .add_var(
+            "udp_recv",
+            net_func(
+                Type::new(UdpSocketT),
+                Type::new(data::tuple::TupleT(vec![
+                    Type::new(ListT(Type::new(data::byte::ByteT))),
+                    Type::new(data::string::StringT),
+                ])),
+                |a, _| {
+                    let socket = a.get().as_any().downcast_ref::<UdpSocket>().cloned().unwrap();
+                    let mut buf = vec![0; 65536];
+                    // don't hold the lock while waiting, so other threads can send and `net_close` doesn't have to wait
+                    let (len, addr) = socket.with(|s| s.try_clone())?.recv_from(&mut buf)?;
+                    Ok(Data::new(data::tuple::Tuple::from([
+                        bytes_list(&buf[..len]),
+                        Data::new(data::string::String(addr.to_string())),
+                    ])))
+                },
+            ),
+        )
+        .add_var(
+            "net_local_addr",
+            net_func(
+                Type::newm(vec![
+                    Arc::new(TcpListenerT),
+                    Arc::new(TcpStreamT),
+                    Arc::new(UdpSocketT),
+                ]),
+                Type::new(data::string::StringT),
+                |a, _| {
+                    let a = a.get();
+                    let a = a.as_any();
+                    let addr = if let Some(l) = a.downcast_ref::<TcpListener>() {
+                        l.with(|l| l.local_addr())?
+                    } else if let Some(s) = a.downcast_ref::<TcpStream>() {
+                        s.with(|c| c.stream.local_addr())?
+                    } else {
+                        a.downcast_ref::<UdpSocket>().unwrap().with(|s| s.local_addr())?
+                    };
+                    Ok(Data::new(data::string::String(addr.to_string())))
+                },
+            ),
+        )
+        .add_var(
+            "net_peer_addr",
+            net_func(
+                Type::new(TcpStreamT),
+                Type::new(data::string::StringT),
+                |a, _| {
+                    let stream = a.get().as_any().downcast_ref::<TcpStream>().cloned().unwrap();
+                    let addr = stream.with(|c| c.stream.peer_addr())?;
+                    Ok(Data::new(data::string::String(addr.to_string())))
+                },
+            ),
+        )
+        .add_var(
+            "net_set_timeout",
+            net_func(
+                Type::new(data::tuple::TupleT(vec![
+                    Type::newm(vec![Arc::new(TcpStreamT), Arc::new(UdpSocketT)]),
+                    timeout_type(),
+                ])),
+                Type::empty_tuple(),
+                |a, _| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap().0;
+                    let timeout = timeout_arg(&a[1].read());
+                    let socket = a[0].read();
+                    let socket = socket.get();
+                    if let Some(s) = socket.as_any().downcast_ref::<TcpStream>() {
+                        s.with(|c| {
+                            c.stream.set_read_timeout(timeout)?;
+                            c.stream.set_write_timeout(timeout)
+                        })?;
+                    } else {
+                        socket.as_any().downcast_ref::<UdpSocket>().unwrap().with(|s| {
+                            s.set_read_timeout(timeout)?;
+                            s.set_write_timeout(timeout)
+                        })?;
+                    }
+                    Ok(Data::empty_tuple())
+                },
+            ),
+        )
+        .add_var(
+            "net_close",
+            util::to_mers_func_with_in_out_types(
+                Type::newm(vec![
+                    Arc::new(TcpListenerT),
+                    Arc::new(TcpStreamT),
+                    Arc::new(UdpSocketT),
+                ]),
+                Type::empty_tuple(),
+                |a, _| {
+                    let a = a.get();
+                    let a = a.as_any();
+                    if let Some(l) = a.downcast_ref::<TcpListener>() {
+                        l.0.lock().unwrap().take();
+                    } else if let Some(s) = a.downcast_ref::<TcpStream>() {
+                        if let Some(c) = s.0.lock().unwrap().take() {
+                            // wakes up threads which are waiting in `tcp_read_line` or `tcp_read_bytes`
+                            _ = c.stream.shutdown(Shutdown::Both);
+                        }
+                    } else if let Some(s) = a.downcast_ref::<UdpSocket>() {
+                        s.0.lock().unwrap().take();
+                    }
+                    Ok(Data::empty_tuple())
+                },
+            ),
+        )
+    }
+}
+
+/// a function which takes `in_type` and returns `ok_type/{net_error: String}`
+fn net_func(
+    in_type: Type,
+    ok_type: Type,
+    run: impl Fn(&Data, &Info) -> io::Result<Data> + Send + Sync + 'static,
+) -> Function {
+    util::to_mers_func_with_in_type(
+        in_type,
+        move |_a, i| Ok(or_error(ok_type.clone(), "net_error", i)),
+        move |a, i| Ok(net_result(run(&a, i), i)),
+    )
+}
+fn net_result(r: io::Result<Data>, i: &Info) -> Data {
+    r.unwrap_or_else(|e| error_object("net_error", e, i))
+}
+fn bytes_list(bytes: &[u8]) -> Data {
+    Data::new(List(
+        bytes
+            .iter()
+            .map(|b| Data::new(data::byte::Byte(*b)))
+            .collect(),
+    ))
+}
+/// `Int<0..>/Float/()`, a number of seconds or `()` for no timeout
+fn timeout_type() -> Type {
+    Type::newm(vec![
+        Arc::new(data::int::IntT(0, INT_MAX)),
+        Arc::new(data::float::FloatT),
+        Arc::new(data::tuple::TupleT(vec![])),
+    ])
+}
+fn timeout_arg(a: &Data) -> Option<Duration> {
+    let a = a.get();
+    if let Some(v) = a.as_any().downcast_ref::<data::int::Int>() {
+        Some(Duration::from_secs(v.0.max(0) as u64))
+    } else {
+        a.as_any()
+            .downcast_ref::<data::float::Float>()
+            .and_then(|v| Duration::try_from_secs_f64(v.0.max(0.0)).ok())
+    }
+}
+
+macro_rules! net_type {
+    ($(#[$doc:meta])* $name:ident, $type:ident, $inner:ty) => {
+        $(#[$doc])*
+        #[derive(Clone, Debug)]
+        pub struct $name(Arc<Mutex<Option<$inner>>>);
+        #[derive(Clone, Debug)]
+        pub struct $type;
+        impl $name {
+            pub fn new(v: $inner) -> Self {
+                Self(Arc::new(Mutex::new(Some(v))))
+            }
+            /// runs `f` on the socket, or returns an error if it was closed
+            pub(crate) fn with<R>(&self, f: impl FnOnce(&mut $inner) -> io::Result<R>) -> io::Result<R> {
+                match self.0.lock().unwrap().as_mut() {
+                    Some(v) => f(v),
+                    None => Err(io::Error::new(
+                        io::ErrorKind::NotConnected,
+                        concat!(stringify!($name), " was closed"),
+                    )),
+                }
+            }
+        }
+        impl MersData for $name {
+            fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{self}")
+            }
+            fn is_eq(&self, other: &dyn MersData) -> bool {
+                other
+                    .as_any()
+                    .downcast_ref::<Self>()
+                    .is_some_and(|other| Arc::ptr_eq(&self.0, &other.0))
+            }
+            fn clone(&self) -> Box<dyn MersData> {
+                Box::new(Clone::clone(self))
+            }
+            fn as_type(&self) -> Type {
+                Type::new($type)
+            }
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+            fn mut_any(&mut self) -> &mut dyn std::any::Any {
+                self
+            }
+            fn to_any(self) -> Box<dyn std::any::Any> {
+                Box::new(self)
+            }
+        }
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, concat!("<", stringify!($name), ">"))
+            }
+        }
+        impl MersType for $type {
+            fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{self}")
+            }
+            fn iterable(&self) -> Option<Type> {
+                None
+            }
+            fn get(&self) -> Option<Type> {
+                None
+            }
+            fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+                other.as_any().is::<Self>()
+            }
+            fn is_included_in(&self, target: &dyn MersType) -> bool {
+                target.as_any().is::<Self>()
+            }
+            fn without(&self, remove: &dyn MersType) -> Option<Type> {
+                if self.is_included_in(remove) {
+                    Some(Type::empty())
+                } else {
+                    None
+                }
+            }
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+            fn mut_any(&mut self) -> &mut dyn std::any::Any {
+                self
+            }
+            fn to_any(self) -> Box<dyn std::any::Any> {
+                Box::new(self)
+            }
+        }
+        impl Display for $type {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, stringify!($name))
+            }
+        }
+    };
+}
+
+net_type!(
+    /// A socket listening for TCP connections, created by `tcp_bind`
+    TcpListener,
+    TcpListenerT,
+    std::net::TcpListener
+);
+net_type!(
+    /// A TCP connection, created by `tcp_accept` or `tcp_connect`
+    TcpStream,
+    TcpStreamT,
+    Connection
+);
+/// The handles of a `TcpStream`. Reads lock only `reader`, and writes use a clone of `stream`,
+/// so one thread can write while another one is waiting for data.
+#[derive(Debug)]
+pub struct Connection {
+    pub stream: std::net::TcpStream,
+    pub reader: Arc<Mutex<BufReader<std::net::TcpStream>>>,
+}
+impl Connection {
+    pub fn new(stream: std::net::TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: Arc::new(Mutex::new(BufReader::new(stream.try_clone()?))),
+            stream,
+        })
+    }
+}
+net_type!(
+    /// A UDP socket, created by `udp_bind`
+    UdpSocket,
+    UdpSocketT,
+    std::net::UdpSocket
+);
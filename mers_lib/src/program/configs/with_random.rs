@@ -0,0 +1,370 @@
+use std::{
+    fmt::Display,
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    data::{
+        self,
+        int::{IntT, INT_MAX, INT_MIN},
+        tuple::TupleT,
+        Data, MersData, MersType, Type,
+    },
+    errors::CheckError,
+    info::DisplayInfo,
+    program::run::CheckInfo,
+};
+
+use super::{
+    gen::{
+        function::{fun, Funcs, StaticMersFunc},
+        FromMersData, IntR, ToMersData,
+    },
+    util,
+    with_list::{List, ListT},
+    Config,
+};
+
+impl Config {
+    /// adds the `Rng` type, a pseudo-random number generator. The same seed always produces the same numbers, on every platform.
+    /// `rng_new: fn` creates a new `Rng` from an `Int` seed, or, if called with `()`, from OS-provided entropy
+    /// `rng_int: fn` returns a random integer in the range min..=max: (rng, min, max).rng_int. If min > max, this causes an error.
+    /// `rng_float: fn` returns a random float in the range [0, 1)
+    /// `rng_shuffle: fn` returns a shuffled copy of the list: (rng, list).rng_shuffle
+    /// `rng_choose: fn` returns a random element of the list, or () if it is empty: (rng, list).rng_choose
+    pub fn with_random(self) -> Self {
+        self.add_type("Rng".to_owned(), Ok(Arc::new(Type::new(RngT))))
+            .add_var(
+                "rng_new",
+                Funcs(
+                    fun(|_: (), _| Ok(Rng::from_seed(entropy()))),
+                    fun(|seed: IntR<INT_MIN, INT_MAX>, _| Ok(Rng::from_seed(seed.0 as u64))),
+                )
+                .mers_func(),
+            )
+            .add_var(
+                "rng_float",
+                fun(|rng: Rng, _| Ok(rng.next_f64())).mers_func(),
+            )
+            .add_var(
+                "rng_int",
+                util::to_mers_func(
+                    |a, i| {
+                        let (mut min, mut max) = (INT_MAX, INT_MIN);
+                        for t in rng_args(a, 3, "rng_int", i)? {
+                            for (bound, is_max) in [(&t.0[1], false), (&t.0[2], true)] {
+                                for t in bound.types.iter() {
+                                    if let Some(t) = t.as_any().downcast_ref::<IntT>() {
+                                        if is_max {
+                                            max = max.max(t.1);
+                                        } else {
+                                            min = min.min(t.0);
+                                        }
+                                    } else {
+                                        return Err(format!(
+                                            "rng_int: expected (Rng, Int, Int), but got {}",
+                                            a.with_info(i)
+                                        )
+                                        .into());
+                                    }
+                                }
+                            }
+                        }
+                        if min > max {
+                            return Err(format!(
+                                "rng_int: min is always greater than max in {}",
+                                a.with_info(i)
+                            )
+                            .into());
+                        }
+                        Ok(Type::new(IntT(min, max)))
+                    },
+                    |a, _i| {
+                        let a = a.get();
+                        let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+                        let rng = a.0[0].read();
+                        let rng = rng.get();
+                        let rng = rng.as_any().downcast_ref::<Rng>().unwrap();
+                        let [min, max] = [1, 2].map(|i| {
+                            a.0[i]
+                                .read()
+                                .get()
+                                .as_any()
+                                .downcast_ref::<data::int::Int>()
+                                .unwrap()
+                                .0
+                        });
+                        if min > max {
+                            return Err(format!(
+                                "rng_int: min ({min}) is greater than max ({max})"
+                            )
+                            .into());
+                        }
+                        Ok(Data::new(data::int::Int(rng.next_in_range(min, max))))
+                    },
+                ),
+            )
+            .add_var(
+                "rng_shuffle",
+                util::to_mers_func(
+                    |a, i| {
+                        let mut out = Type::empty();
+                        for t in rng_args(a, 2, "rng_shuffle", i)? {
+                            for t in t.0[1].types.iter() {
+                                if t.as_any().is::<ListT>() {
+                                    out.add(Arc::clone(t));
+                                } else {
+                                    return Err(format!(
+                                        "rng_shuffle: expected (Rng, List), but got {}",
+                                        a.with_info(i)
+                                    )
+                                    .into());
+                                }
+                            }
+                        }
+                        Ok(out)
+                    },
+                    |a, _i| {
+                        let a = a.get();
+                        let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+                        let rng = a.0[0].read();
+                        let rng = rng.get();
+                        let rng = rng.as_any().downcast_ref::<Rng>().unwrap();
+                        let list = a.0[1].read();
+                        let mut list = list
+                            .get()
+                            .as_any()
+                            .downcast_ref::<List>()
+                            .unwrap()
+                            .0
+                            .clone();
+                        // Fisher-Yates
+                        for i in (1..list.len()).rev() {
+                            list.swap(i, rng.next_in_range(0, i as isize) as usize);
+                        }
+                        Ok(Data::new(List(list)))
+                    },
+                ),
+            )
+            .add_var(
+                "rng_choose",
+                util::to_mers_func(
+                    |a, i| {
+                        let mut out = Type::empty();
+                        for t in rng_args(a, 2, "rng_choose", i)? {
+                            for t in t.0[1].types.iter() {
+                                if let Some(t) = t.as_any().downcast_ref::<ListT>() {
+                                    out.add_all(&t.0);
+                                } else {
+                                    return Err(format!(
+                                        "rng_choose: expected (Rng, List), but got {}",
+                                        a.with_info(i)
+                                    )
+                                    .into());
+                                }
+                            }
+                        }
+                        Ok(Type::newm(vec![
+                            Arc::new(TupleT(vec![out])),
+                            Arc::new(TupleT(vec![])),
+                        ]))
+                    },
+                    |a, _i| {
+                        let a = a.get();
+                        let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+                        let rng = a.0[0].read();
+                        let rng = rng.get();
+                        let rng = rng.as_any().downcast_ref::<Rng>().unwrap();
+                        let list = a.0[1].read();
+                        let list = list.get();
+                        let list = &list.as_any().downcast_ref::<List>().unwrap().0;
+                        Ok(if list.is_empty() {
+                            Data::empty_tuple()
+                        } else {
+                            Data::one_tuple(
+                                list[rng.next_in_range(0, list.len() as isize - 1) as usize]
+                                    .clone(),
+                            )
+                        })
+                    },
+                ),
+            )
+    }
+}
+
+/// checks that `a` only contains tuples of length `len` whose first element is an `Rng`
+fn rng_args<'a>(
+    a: &'a Type,
+    len: usize,
+    func: &str,
+    i: &CheckInfo,
+) -> Result<Vec<&'a TupleT>, CheckError> {
+    a.types
+        .iter()
+        .map(|t| {
+            t.as_any()
+                .downcast_ref::<TupleT>()
+                .filter(|t| t.0.len() == len && t.0[0].is_included_in_single(&RngT))
+                .ok_or_else(|| {
+                    format!(
+                        "{func}: expected a tuple of length {len} starting with an Rng, but got {}",
+                        a.with_info(i)
+                    )
+                    .into()
+                })
+        })
+        .collect()
+}
+
+/// a seed from the OS's randomness (which the standard library uses for `HashMap`s) and the current time
+fn entropy() -> u64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0),
+    );
+    hasher.finish()
+}
+
+/// A xoshiro256** pseudo-random number generator.
+/// Clones share the generator's state, so using a clone advances the original, too.
+#[derive(Clone, Debug)]
+pub struct Rng(pub Arc<Mutex<[u64; 4]>>);
+#[derive(Clone, Debug)]
+pub struct RngT;
+impl Rng {
+    pub fn from_seed(seed: u64) -> Self {
+        // use splitmix64 to turn the seed into the initial state, as recommended by the authors of xoshiro
+        let mut seed = seed;
+        let mut next = || {
+            seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        Self(Arc::new(Mutex::new([next(), next(), next(), next()])))
+    }
+    pub fn next_u64(&self) -> u64 {
+        let mut s = self.0.lock().unwrap();
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+    /// a float in the range [0, 1)
+    pub fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// an integer in the range `min..=max`. requires `min <= max`.
+    pub fn next_in_range(&self, min: isize, max: isize) -> isize {
+        let span = (max as i128 - min as i128 + 1) as u128;
+        if span > u64::MAX as u128 {
+            return self.next_u64() as isize;
+        }
+        let span = span as u64;
+        // reject values which would make lower results more likely than higher ones
+        let limit = u64::MAX - u64::MAX % span;
+        loop {
+            let v = self.next_u64();
+            if v < limit {
+                return (min as i128 + (v % span) as i128) as isize;
+            }
+        }
+    }
+}
+
+impl MersData for Rng {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| Arc::ptr_eq(&self.0, &other.0))
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(RngT)
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl Display for Rng {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Rng>")
+    }
+}
+impl MersType for RngT {
+    fn display(
+        &self,
+        _info: &crate::info::DisplayInfo<'_>,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other.as_any().is::<Self>()
+    }
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        target.as_any().is::<Self>()
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl Display for RngT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rng")
+    }
+}
+impl FromMersData for Rng {
+    fn as_type_from() -> Type {
+        Self::as_type_to()
+    }
+    fn can_represent(t: &Type) -> bool {
+        t.is_included_in_single(&RngT)
+    }
+    fn try_represent<O, F: FnOnce(Option<Self>) -> O>(d: &(impl MersData + ?Sized), f: F) -> O {
+        f(d.as_any().downcast_ref::<Self>().cloned())
+    }
+}
+impl ToMersData for Rng {
+    fn as_type_to() -> Type {
+        Type::new(RngT)
+    }
+    fn represent(self) -> Data {
+        Data::new(self)
+    }
+}
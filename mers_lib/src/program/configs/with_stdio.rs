@@ -17,6 +17,8 @@ use super::{
         function::{func, func_err},
         IntR, OneOrNone,
     },
+    util,
+    with_fs::{Chunks, ChunksT, Lines, LinesT, ReadSource},
     Config,
 };
 
@@ -27,6 +29,12 @@ impl Config {
     /// `eprint: fn` prints to stderr
     /// `debug: fn` debug-prints any value
     /// `read_line: fn` reads a line from stdin and returns it
+    /// `lines: fn` returns an iterable over the lines of stdin, which are only read while iterating: ().lines.filter(...).take(10)
+    /// `stdin_chunks: fn` returns an iterable over the bytes of stdin, n bytes (a `List<Byte>`) at a time: 4096.stdin_chunks
+    /// `read_all_stdin: fn` reads stdin until its end and returns it as a String
+    ///
+    /// `lines` and `stdin_chunks` return the `Lines` and `Chunks` types, which `with_fs()` makes available by name.
+    /// If reading fails, iterating over them stops with an error.
     /// `exit: fn` exits the program with the given exit code. returns `<unreachable>`, just like `panic`
     pub fn with_stdio(self) -> Self {
         self
@@ -86,6 +94,41 @@ impl Config {
                     })
                 }),
             )
+            .add_var(
+                "lines",
+                util::to_mers_func_with_in_out_types(
+                    Type::empty_tuple(),
+                    Type::new(LinesT),
+                    |_, i| {
+                        Ok(Data::new(Lines(ReadSource::Stdin(Arc::clone(
+                            &i.global.stdin,
+                        )))))
+                    },
+                ),
+            )
+            .add_var(
+                "stdin_chunks",
+                util::to_mers_func_with_in_out_types(
+                    Type::new(data::int::IntT(1, INT_MAX)),
+                    Type::new(ChunksT),
+                    |a, i| {
+                        let n = a.get().as_any().downcast_ref::<data::int::Int>().unwrap().0;
+                        Ok(Data::new(Chunks(
+                            ReadSource::Stdin(Arc::clone(&i.global.stdin)),
+                            n as usize,
+                        )))
+                    },
+                ),
+            )
+            .add_var(
+                "read_all_stdin",
+                func(|_: (), i| {
+                    let all = ReadSource::Stdin(Arc::clone(&i.global.stdin))
+                        .read_all()
+                        .map_err(|e| format!("Error while reading stdin: {e}"))?;
+                    Ok(String::from_utf8_lossy(&all).into_owned())
+                }),
+            )
             .add_var(
                 "debug",
                 data::function::Function {
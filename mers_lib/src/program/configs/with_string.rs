@@ -1,17 +1,44 @@
-use crate::data::{
-    self,
-    int::{INT_MAX, INT_MIN},
-    Data, MersDataWInfo, Type,
+use std::{fmt::Display, sync::Arc};
+
+use crate::{
+    data::{
+        self,
+        function::{Function, FunctionT},
+        int::{INT_MAX, INT_MIN},
+        object::{ObjectFieldsMap, ObjectT},
+        tuple::TupleT,
+        Data, MersData, MersDataWInfo, MersType, MersTypeWInfo, Type,
+    },
+    errors::{CheckError, EColor},
+    info::DisplayInfo,
+    program::run::{CheckInfo, MersStatement},
 };
 
 use super::{
-    gen::{function::func, AnyOrNone, IntR, IterToList, OneOf, OneOrNone},
-    util, Config,
+    gen::{
+        function::func, AnyOrNone, FromMersData, IntR, IterToList, OneOf, OneOrNone, ToMersData,
+    },
+    util::{self, bytes_arg, check_bytes_arg, error_object, or_error},
+    Config,
 };
 
 impl Config {
     /// `trim: fn` removes leading and trailing whitespace from a string
     /// `substring: fn` extracts part of a string. usage: (str, start).substring or (str, start, end).substring. start and end may be negative, in which case they become str.len - n: (str, 0, -1) shortens the string by 1.
+    /// Indices count bytes (see `char_substring`), an index in the middle of a character refers to the start of that character.
+    /// `chars: fn` returns a list of the characters in a string, each as a string
+    /// `char_len: fn` returns the number of characters (unicode codepoints) in a string. `len` returns the number of bytes.
+    /// `char_substring: fn` like substring, but indices count characters instead of bytes
+    /// `to_upper: fn` converts a string to uppercase
+    /// `to_lower: fn` converts a string to lowercase
+    /// `repeat: fn` repeats a string n times: (str, n).repeat
+    /// `pad_left: fn` adds spaces (or a given fill string) to the start of a string until it is (at least) n characters long: (str, n).pad_left or (str, n, fill).pad_left
+    /// `pad_right: fn` like pad_left, but adds the fill to the end of the string
+    /// `str_reverse: fn` reverses the characters in a string
+    /// `is_numeric: fn` returns true if the string isn't empty and all characters are numeric
+    /// `is_alphabetic: fn` returns true if the string isn't empty and all characters are alphabetic
+    /// `to_codepoints: fn` returns the unicode codepoints of a string's characters as a list of Ints
+    /// `from_codepoints: fn` turns an iterable over codepoints into a string: (String), or () if one of them isn't a valid codepoint
     /// `index_of: fn` finds the index of a pattern in a string
     /// `index_of_rev: fn` finds the last index of a pattern in a string
     /// `starts_with: fn` checks if the string starts with the pattern
@@ -21,8 +48,294 @@ impl Config {
     /// `str_split: fn` splits the string at the given pattern, removing that pattern from the string.
     /// `to_string: fn` turns any argument into a (more or less useful) string representation
     /// `concat: fn` concatenates all arguments given to it. arg must be an enumerable
+    /// `format: fn` formats values using a template. usage: (template, (a, b)).format or (template, {name: a}).format.
+    /// Placeholders are `{}` (the next value), `{0}` (the first value) or `{name}` (an object's field). `{{` and `}}` are literal braces.
+    /// After a `:`, a placeholder can specify fill and alignment (`<`, `^`, `>`), `0`-padding, a width and a precision (both at most 65535): `{:>8}`, `{total:*^12.2}`.
+    /// The precision is the number of decimal places of a `Float` or the maximum number of characters of a `String`, it is ignored for other values like `Int`s.
+    /// If the template is a string literal, placeholders are compared to the values' type when checking the program.
+    /// `regex: fn` compiles a pattern into a `Regex`, or returns `{regex_error: String}` if the pattern is invalid
+    /// `regex_match: fn` checks if the regex matches somewhere in the string. usage: (regex, str).regex_match
+    /// `regex_find_all: fn` returns a list with one entry per match. each entry is a list of capture groups, where the first one is the entire match and groups which didn't participate in the match are `()`.
+    /// `regex_replace: fn` replaces all matches of the regex. usage: (regex, str, replacement).regex_replace, where the replacement may refer to capture groups using `$1` or `${name}`.
+    /// `regex_split: fn` splits the string at every match of the regex
+    /// `string_to_bytes: fn` returns the UTF-8 bytes of a string as a `List<Byte>`
+    /// `bytes_to_string: fn` decodes UTF-8 bytes, returns `String` or `{bytes_to_string_error: String}` if the bytes aren't valid UTF-8
+    /// `bytes_to_string_lossy: fn` decodes UTF-8 bytes, replacing invalid sequences with `�`
+    /// `hex_encode: fn` turns bytes into a string of lowercase hex digits, two per byte
+    /// `hex_decode: fn` turns a string of hex digits into a `List<Byte>`, or returns `{hex_decode_error: String}`
+    /// `base64_encode: fn` turns bytes into a (standard, padded) base64 string
+    /// `base64_decode: fn` turns a base64 string (padding is optional) into a `List<Byte>`, or returns `{base64_decode_error: String}`
     pub fn with_string(self) -> Self {
-        self.add_var("trim", func(|v: &str, _| Ok(v.trim().to_owned())))
+        self.add_type("Regex".to_owned(), Ok(Arc::new(Type::new(RegexT))))
+            .add_var(
+                "regex",
+                util::to_mers_func_with_in_type(
+                    Type::new(data::string::StringT),
+                    |_a, i| {
+                        Ok(Type::newm(vec![
+                            Arc::new(RegexT),
+                            Arc::new(data::object::ObjectT::new(vec![(
+                                i.global.object_fields.get_or_add_field("regex_error"),
+                                Type::new(data::string::StringT),
+                            )])),
+                        ]))
+                    },
+                    |a, i| {
+                        let a = a.get();
+                        let pattern = &a
+                            .as_any()
+                            .downcast_ref::<data::string::String>()
+                            .expect("got non-string argument to regex")
+                            .0;
+                        Ok(match regex::Regex::new(pattern) {
+                            Ok(regex) => Data::new(Regex(Arc::new(regex))),
+                            Err(e) => Data::new(data::object::Object::new(vec![(
+                                i.global.object_fields.get_or_add_field("regex_error"),
+                                Data::new(data::string::String(e.to_string())),
+                            )])),
+                        })
+                    },
+                ),
+            )
+            .add_var(
+                "regex_match",
+                func(|(r, v): (Regex, &str), _| Ok(r.0.is_match(v))),
+            )
+            .add_var(
+                "regex_find_all",
+                func(|(r, v): (Regex, &str), _| {
+                    Ok(IterToList(
+                        r.0.captures_iter(v)
+                            .map(|c| {
+                                IterToList(
+                                    c.iter()
+                                        .map(|m| AnyOrNone(m.map(|m| m.as_str().to_owned())))
+                                        .collect::<Vec<_>>()
+                                        .into_iter(),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .into_iter(),
+                    ))
+                }),
+            )
+            .add_var(
+                "regex_replace",
+                func(|(r, v, w): (Regex, &str, &str), _| Ok(r.0.replace_all(v, w).into_owned())),
+            )
+            .add_var(
+                "regex_split",
+                func(|(r, v): (Regex, &str), _| {
+                    Ok(IterToList(
+                        r.0.split(v)
+                            .map(|v| v.to_owned())
+                            .collect::<Vec<_>>()
+                            .into_iter(),
+                    ))
+                }),
+            )
+            .add_var("format", Format::new())
+            .add_var(
+                "chars",
+                func(|s: &str, _| {
+                    Ok(IterToList(
+                        s.chars().map(String::from).collect::<Vec<_>>().into_iter(),
+                    ))
+                }),
+            )
+            .add_var(
+                "char_len",
+                func(|s: &str, _| Ok(IntR::<0, INT_MAX>(s.chars().count() as isize))),
+            )
+            .add_var(
+                "char_substring",
+                func(
+                    |v: OneOf<
+                        (&str, IntR<INT_MIN, INT_MAX>),
+                        (&str, IntR<INT_MIN, INT_MAX>, IntR<INT_MIN, INT_MAX>),
+                    >,
+                     _| {
+                        let (s, start, end) = match v {
+                            OneOf::A((t, s)) => (t, s.0, None),
+                            OneOf::B((t, s, e)) => (t, s.0, Some(e.0)),
+                        };
+                        let (start, end) = substring_range(s.chars().count(), start, end);
+                        Ok(s.chars()
+                            .skip(start)
+                            .take(end.saturating_sub(start))
+                            .collect::<String>())
+                    },
+                ),
+            )
+            .add_var("to_upper", func(|s: &str, _| Ok(s.to_uppercase())))
+            .add_var("to_lower", func(|s: &str, _| Ok(s.to_lowercase())))
+            .add_var(
+                "repeat",
+                func(|(s, n): (&str, IntR<0, INT_MAX>), _| {
+                    let n = n.0 as usize;
+                    // fails instead of aborting if the result is too large
+                    alloc_string(s.len().checked_mul(n), "repeat")?;
+                    Ok(s.repeat(n))
+                }),
+            )
+            .add_var("pad_left", func(|v, _| pad(v, true)))
+            .add_var("pad_right", func(|v, _| pad(v, false)))
+            .add_var(
+                "str_reverse",
+                func(|s: &str, _| Ok(s.chars().rev().collect::<String>())),
+            )
+            .add_var(
+                "is_numeric",
+                func(|s: &str, _| Ok(!s.is_empty() && s.chars().all(char::is_numeric))),
+            )
+            .add_var(
+                "is_alphabetic",
+                func(|s: &str, _| Ok(!s.is_empty() && s.chars().all(char::is_alphabetic))),
+            )
+            .add_var(
+                "to_codepoints",
+                func(|s: &str, _| {
+                    Ok(IterToList(
+                        s.chars()
+                            .map(|c| IntR::<0, 0x10FFFF>(c as isize))
+                            .collect::<Vec<_>>()
+                            .into_iter(),
+                    ))
+                }),
+            )
+            .add_var(
+                "from_codepoints",
+                util::to_mers_func(
+                    |a, i| {
+                        if a.iterable().is_some_and(|t| {
+                            t.is_included_in_single(&data::int::IntT(INT_MIN, INT_MAX))
+                        }) {
+                            Ok(Type::newm(vec![
+                                Arc::new(TupleT(vec![Type::new(data::string::StringT)])),
+                                Arc::new(TupleT(vec![])),
+                            ]))
+                        } else {
+                            Err(format!(
+                                "from_codepoints: expected an iterable over Ints, but got {}",
+                                a.with_info(i)
+                            )
+                            .into())
+                        }
+                    },
+                    |a, i| {
+                        let mut out = String::new();
+                        for c in a.get().iterable(&i.global).unwrap() {
+                            let c = c?
+                                .get()
+                                .as_any()
+                                .downcast_ref::<data::int::Int>()
+                                .unwrap()
+                                .0;
+                            match u32::try_from(c).ok().and_then(char::from_u32) {
+                                Some(c) => out.push(c),
+                                None => return Ok(Data::empty_tuple()),
+                            }
+                        }
+                        Ok(Data::one_tuple(Data::new(data::string::String(out))))
+                    },
+                ),
+            )
+            .add_var(
+                "string_to_bytes",
+                func(|s: &str, _| Ok(IterToList(s.as_bytes().to_vec().into_iter()))),
+            )
+            .add_var(
+                "bytes_to_string",
+                util::to_mers_func(
+                    |a, i| {
+                        check_bytes_arg(a, "bytes_to_string", i)?;
+                        Ok(or_error(
+                            Type::new(data::string::StringT),
+                            "bytes_to_string_error",
+                            i,
+                        ))
+                    },
+                    |a, i| {
+                        Ok(match String::from_utf8(bytes_arg(&a, i)?) {
+                            Ok(s) => Data::new(data::string::String(s)),
+                            Err(e) => error_object("bytes_to_string_error", e, i),
+                        })
+                    },
+                ),
+            )
+            .add_var(
+                "bytes_to_string_lossy",
+                util::to_mers_func(
+                    |a, i| {
+                        check_bytes_arg(a, "bytes_to_string_lossy", i)?;
+                        Ok(Type::new(data::string::StringT))
+                    },
+                    |a, i| {
+                        Ok(Data::new(data::string::String(
+                            String::from_utf8_lossy(&bytes_arg(&a, i)?).into_owned(),
+                        )))
+                    },
+                ),
+            )
+            .add_var(
+                "hex_encode",
+                util::to_mers_func(
+                    |a, i| {
+                        check_bytes_arg(a, "hex_encode", i)?;
+                        Ok(Type::new(data::string::StringT))
+                    },
+                    |a, i| {
+                        let mut out = String::new();
+                        for b in bytes_arg(&a, i)? {
+                            out.push_str(&format!("{b:02x}"));
+                        }
+                        Ok(Data::new(data::string::String(out)))
+                    },
+                ),
+            )
+            .add_var(
+                "hex_decode",
+                bytes_decode_func("hex_decode_error", |s| {
+                    if s.len() % 2 != 0 {
+                        return Err("odd number of hex digits".to_owned());
+                    }
+                    s.as_bytes()
+                        .chunks(2)
+                        .map(|pair| {
+                            // `from_str_radix` would also accept a leading `+`
+                            std::str::from_utf8(pair)
+                                .ok()
+                                .filter(|pair| pair.chars().all(|c| c.is_ascii_hexdigit()))
+                                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                                .ok_or_else(|| {
+                                    format!(
+                                        "invalid hex digits {:?}",
+                                        String::from_utf8_lossy(pair)
+                                    )
+                                })
+                        })
+                        .collect()
+                }),
+            )
+            .add_var(
+                "base64_encode",
+                util::to_mers_func(
+                    |a, i| {
+                        check_bytes_arg(a, "base64_encode", i)?;
+                        Ok(Type::new(data::string::StringT))
+                    },
+                    |a, i| {
+                        Ok(Data::new(data::string::String(base64_encode(&bytes_arg(
+                            &a, i,
+                        )?))))
+                    },
+                ),
+            )
+            .add_var(
+                "base64_decode",
+                bytes_decode_func("base64_decode_error", base64_decode),
+            )
+            .add_var("trim", func(|v: &str, _| Ok(v.trim().to_owned())))
             .add_var(
                 "index_of",
                 func(|(v, p): (&str, &str), _| {
@@ -112,27 +425,685 @@ impl Config {
                             OneOf::A((t, s)) => (t, s.0, None),
                             OneOf::B((t, s, e)) => (t, s.0, Some(e.0)),
                         };
-                        let start = if start < 0 {
-                            s.len().saturating_sub(start.abs() as usize)
-                        } else {
-                            start as usize
+                        let (start, end) = substring_range(s.len(), start, end);
+                        // don't cut characters in half: move the indices to the start of the character they are in
+                        let floor = |mut i: usize| {
+                            while !s.is_char_boundary(i) {
+                                i -= 1;
+                            }
+                            i
                         };
-                        let end = end
-                            .map(|i| {
-                                if i < 0 {
-                                    s.len().saturating_sub(i.abs() as usize)
-                                } else {
-                                    i as usize
-                                }
-                            })
-                            .unwrap_or(usize::MAX);
-                        let end = end.min(s.len());
-                        if end < start {
-                            return Ok(String::new());
-                        }
-                        Ok(s[start..end].to_owned())
+                        Ok(s[floor(start)..floor(end)].to_owned())
                     },
                 ),
             )
     }
 }
+
+/// the `start..end` range for `substring`-like functions, where negative indices count from the end.
+/// the range is limited to `0..len`, and `end` is never less than `start`.
+fn substring_range(len: usize, start: isize, end: Option<isize>) -> (usize, usize) {
+    let index = |i: isize| {
+        if i < 0 {
+            len.saturating_sub(i.unsigned_abs())
+        } else {
+            (i as usize).min(len)
+        }
+    };
+    let start = index(start);
+    let end = end.map_or(len, index);
+    (start, end.max(start))
+}
+fn pad(
+    v: OneOf<(&str, IntR<0, INT_MAX>), (&str, IntR<0, INT_MAX>, &str)>,
+    left: bool,
+) -> Result<String, CheckError> {
+    let (s, width, fill) = match v {
+        OneOf::A((s, w)) => (s, w.0 as usize, " "),
+        OneOf::B((s, w, f)) => (s, w.0 as usize, f),
+    };
+    let fill_len = fill.chars().count();
+    let missing = width.saturating_sub(s.chars().count());
+    if fill_len == 0 || missing == 0 {
+        return Ok(s.to_owned());
+    }
+    // the fill is repeated, then cut so that exactly `missing` characters are added
+    let padding_len = (missing / fill_len).checked_mul(fill.len()).map(|len| {
+        len + fill
+            .chars()
+            .take(missing % fill_len)
+            .map(char::len_utf8)
+            .sum::<usize>()
+    });
+    let mut out = alloc_string(
+        padding_len.and_then(|len| len.checked_add(s.len())),
+        if left { "pad_left" } else { "pad_right" },
+    )?;
+    if !left {
+        out.push_str(s);
+    }
+    out.extend(fill.chars().cycle().take(missing));
+    if left {
+        out.push_str(s);
+    }
+    Ok(out)
+}
+/// an empty string with space for `len` bytes, or an error if `len` is `None` (overflowed) or too large
+fn alloc_string(len: Option<usize>, func: &str) -> Result<String, CheckError> {
+    let mut out = String::new();
+    match len.map(|len| out.try_reserve_exact(len)) {
+        Some(Ok(())) => Ok(out),
+        _ => Err(format!("{func}: the resulting string would be too large").into()),
+    }
+}
+
+/// A compiled regular expression, created by the `regex` function
+#[derive(Clone, Debug)]
+pub struct Regex(pub Arc<regex::Regex>);
+#[derive(Clone, Debug)]
+pub struct RegexT;
+impl MersData for Regex {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| self.0.as_str() == other.0.as_str())
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(RegexT)
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl Display for Regex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Regex {}>", self.0.as_str())
+    }
+}
+impl MersType for RegexT {
+    fn display(
+        &self,
+        _info: &crate::info::DisplayInfo<'_>,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other.as_any().is::<Self>()
+    }
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        target.as_any().is::<Self>()
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl Display for RegexT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Regex")
+    }
+}
+impl FromMersData for Regex {
+    fn as_type_from() -> Type {
+        Self::as_type_to()
+    }
+    fn can_represent(t: &Type) -> bool {
+        t.is_included_in_single(&RegexT)
+    }
+    fn try_represent<O, F: FnOnce(Option<Self>) -> O>(d: &(impl MersData + ?Sized), f: F) -> O {
+        f(d.as_any().downcast_ref::<Self>().cloned())
+    }
+}
+impl ToMersData for Regex {
+    fn as_type_to() -> Type {
+        Type::new(RegexT)
+    }
+    fn represent(self) -> Data {
+        Data::new(self)
+    }
+}
+
+/// The `format` function. It has its own type so that it can check literal templates (see `FormatT::check_call`).
+#[derive(Clone)]
+pub struct Format(Function);
+#[derive(Clone)]
+pub struct FormatT(FunctionT);
+impl Format {
+    pub fn new() -> Self {
+        Self(util::to_mers_func(
+            |a, i| {
+                for t in a.types.iter() {
+                    if !t.as_any().downcast_ref::<TupleT>().is_some_and(|t| {
+                        t.0.len() == 2
+                            && t.0[0].is_included_in_single(&data::string::StringT)
+                            && t.0[1]
+                                .types
+                                .iter()
+                                .all(|t| t.as_any().is::<TupleT>() || t.as_any().is::<ObjectT>())
+                    }) {
+                        return Err(format!(
+                            "format: argument must be (String, tuple or object), but was {}",
+                            a.with_info(i)
+                        )
+                        .into());
+                    }
+                }
+                Ok(Type::new(data::string::StringT))
+            },
+            |a, i| {
+                let a = a.get();
+                let a = a.as_any().downcast_ref::<data::tuple::Tuple>().unwrap();
+                let (template, values) = (a.0[0].read(), a.0[1].read());
+                let (template, values) = (template.get(), values.get());
+                let template = &template
+                    .as_any()
+                    .downcast_ref::<data::string::String>()
+                    .unwrap()
+                    .0;
+                let parts =
+                    parse_format(template).map_err(|e| CheckError::from(format!("format: {e}")))?;
+                let mut out = String::new();
+                let mut next = 0;
+                for part in parts {
+                    match part {
+                        FormatPart::Text(text) => out.push_str(&text),
+                        FormatPart::Placeholder(key, spec) => {
+                            let value = match (
+                                &key,
+                                values.as_any().downcast_ref::<data::tuple::Tuple>(),
+                                values.as_any().downcast_ref::<data::object::Object>(),
+                            ) {
+                                (FormatKey::Next, Some(t), _) => {
+                                    next += 1;
+                                    t.get(next - 1)
+                                }
+                                (FormatKey::Index(n), Some(t), _) => t.get(*n),
+                                (FormatKey::Name(name), _, Some(o)) => {
+                                    o.get(i.global.object_fields.get_or_add_field(name))
+                                }
+                                _ => None,
+                            }
+                            .ok_or_else(|| {
+                                CheckError::from(format!(
+                                    "format: placeholder {{{}}} doesn't refer to any of the values {}",
+                                    part_key_to_string(&key),
+                                    values.with_info(i)
+                                ))
+                            })?;
+                            spec.write(&mut out, value.get().as_ref(), i);
+                        }
+                    }
+                }
+                Ok(Data::new(data::string::String(out)))
+            },
+        ))
+    }
+}
+impl Default for Format {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum FormatPart {
+    Text(String),
+    Placeholder(FormatKey, FormatSpec),
+}
+enum FormatKey {
+    Next,
+    Index(usize),
+    Name(String),
+}
+fn part_key_to_string(key: &FormatKey) -> String {
+    match key {
+        FormatKey::Next => String::new(),
+        FormatKey::Index(n) => n.to_string(),
+        FormatKey::Name(name) => name.clone(),
+    }
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FormatAlign {
+    Left,
+    Center,
+    Right,
+}
+struct FormatSpec {
+    fill: char,
+    align: Option<FormatAlign>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+impl FormatSpec {
+    fn write(&self, out: &mut String, value: &dyn MersData, info: &crate::program::run::Info) {
+        let numeric =
+            value.as_any().is::<data::int::Int>() || value.as_any().is::<data::float::Float>();
+        let mut s = match (
+            self.precision,
+            value.as_any().downcast_ref::<data::float::Float>(),
+            value.as_any().downcast_ref::<data::string::String>(),
+        ) {
+            (Some(p), Some(f), _) => format!("{:.p$}", f.0),
+            (Some(p), _, Some(s)) => s.0.chars().take(p).collect(),
+            _ => value.with_info(info).to_string(),
+        };
+        let len = s.chars().count();
+        if len >= self.width {
+            out.push_str(&s);
+            return;
+        }
+        let pad = self.width - len;
+        if self.zero && numeric {
+            let sign = if s.starts_with('-') {
+                s.remove(0).to_string()
+            } else {
+                String::new()
+            };
+            out.push_str(&sign);
+            out.extend(std::iter::repeat_n('0', pad));
+            out.push_str(&s);
+            return;
+        }
+        let (before, after) = match self.align.unwrap_or(if numeric {
+            FormatAlign::Right
+        } else {
+            FormatAlign::Left
+        }) {
+            FormatAlign::Left => (0, pad),
+            FormatAlign::Center => (pad / 2, pad - pad / 2),
+            FormatAlign::Right => (pad, 0),
+        };
+        out.extend(std::iter::repeat_n(self.fill, before));
+        out.push_str(&s);
+        out.extend(std::iter::repeat_n(self.fill, after));
+    }
+}
+
+fn parse_format(template: &str) -> Result<Vec<FormatPart>, String> {
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => return Err("unmatched `}` (use `}}` for a literal `}`)".to_owned()),
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(ch) => placeholder.push(ch),
+                        None => return Err("unclosed `{` (use `{{` for a literal `{`)".to_owned()),
+                    }
+                }
+                if !text.is_empty() {
+                    parts.push(FormatPart::Text(std::mem::take(&mut text)));
+                }
+                let (key, spec) = placeholder
+                    .split_once(':')
+                    .unwrap_or((placeholder.as_str(), ""));
+                let key = if key.is_empty() {
+                    FormatKey::Next
+                } else if let Ok(n) = key.parse() {
+                    FormatKey::Index(n)
+                } else {
+                    FormatKey::Name(key.to_owned())
+                };
+                parts.push(FormatPart::Placeholder(key, parse_format_spec(spec)?));
+            }
+            ch => text.push(ch),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(FormatPart::Text(text));
+    }
+    Ok(parts)
+}
+/// the largest width or precision a placeholder may have (the same limit as in rust's `format!`)
+const MAX_FORMAT_WIDTH: usize = u16::MAX as usize;
+fn parse_format_spec(spec: &str) -> Result<FormatSpec, String> {
+    let align_of = |ch| match ch {
+        '<' => Some(FormatAlign::Left),
+        '^' => Some(FormatAlign::Center),
+        '>' => Some(FormatAlign::Right),
+        _ => None,
+    };
+    let mut chars = spec.chars().collect::<Vec<_>>().into_iter().peekable();
+    let mut out = FormatSpec {
+        fill: ' ',
+        align: None,
+        zero: false,
+        width: 0,
+        precision: None,
+    };
+    // fill and alignment: `<`, `*<`
+    let mut first_two = spec.chars();
+    match (first_two.next(), first_two.next().and_then(align_of)) {
+        (Some(fill), Some(align)) => {
+            out.fill = fill;
+            out.align = Some(align);
+            chars.nth(1);
+        }
+        (Some(ch), None) if align_of(ch).is_some() => {
+            out.align = align_of(ch);
+            chars.next();
+        }
+        _ => {}
+    }
+    if chars.peek() == Some(&'0') {
+        out.zero = true;
+        chars.next();
+    }
+    let rest = chars.collect::<String>();
+    let (width, precision) = match rest.split_once('.') {
+        Some((w, p)) => (w, Some(p)),
+        None => (rest.as_str(), None),
+    };
+    if !width.is_empty() {
+        out.width = width
+            .parse()
+            .ok()
+            .filter(|w| *w <= MAX_FORMAT_WIDTH)
+            .ok_or_else(|| {
+                format!(
+                    "invalid width `{width}` in `{{:{spec}}}` (must be at most {MAX_FORMAT_WIDTH})"
+                )
+            })?;
+    }
+    if let Some(precision) = precision {
+        out.precision = Some(
+            precision
+                .parse()
+                .ok()
+                .filter(|p| *p <= MAX_FORMAT_WIDTH)
+                .ok_or_else(|| format!("invalid precision `{precision}` in `{{:{spec}}}` (must be at most {MAX_FORMAT_WIDTH})"))?,
+        );
+    }
+    Ok(out)
+}
+
+impl MersData for Format {
+    fn display(&self, info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.display(info, f)
+    }
+    fn executable(&self) -> Option<FunctionT> {
+        self.0.executable()
+    }
+    fn execute(
+        &self,
+        arg: Data,
+        gi: &crate::program::run::RunLocalGlobalInfo,
+    ) -> Option<Result<Data, CheckError>> {
+        self.0.execute(arg, gi)
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other.as_any().is::<Self>()
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(FormatT(self.0.get_as_type()))
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl std::fmt::Debug for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Format")
+    }
+}
+impl MersType for FormatT {
+    fn display(
+        &self,
+        info: &crate::info::DisplayInfo<'_>,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        self.0.display(info, f)
+    }
+    fn executable(&self) -> Option<FunctionT> {
+        Some(self.0.clone())
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other.as_any().is::<Self>()
+    }
+    /// If `arg` is a tuple with a string literal as its first element,
+    /// checks that the template's placeholders match the values in `arg_type`.
+    fn check_call(
+        &self,
+        arg: &dyn MersStatement,
+        arg_type: &Type,
+        info: &CheckInfo,
+    ) -> Result<(), CheckError> {
+        let Some(template) = arg
+            .as_any()
+            .downcast_ref::<crate::program::run::tuple::Tuple>()
+            .and_then(|t| t.elems.first())
+            .and_then(|t| {
+                t.as_any()
+                    .downcast_ref::<crate::program::run::value::Value>()
+            })
+        else {
+            return Ok(());
+        };
+        let template_src = template.source_range();
+        let template = template.val.get();
+        let Some(template) = template.as_any().downcast_ref::<data::string::String>() else {
+            return Ok(());
+        };
+        let err = |msg: String| {
+            CheckError::new()
+                .src(vec![(template_src.clone(), Some(EColor::FormatTemplate))])
+                .msg_str(msg)
+        };
+        let parts =
+            parse_format(&template.0).map_err(|e| err(format!("invalid format template: {e}")))?;
+        let keys = parts
+            .into_iter()
+            .filter_map(|p| match p {
+                FormatPart::Placeholder(key, _) => Some(key),
+                FormatPart::Text(_) => None,
+            })
+            .collect::<Vec<_>>();
+        for t in arg_type.types.iter() {
+            let Some(values) = t.as_any().downcast_ref::<TupleT>().and_then(|t| t.0.get(1)) else {
+                continue;
+            };
+            for values in values.types.iter() {
+                if let Some(tuple) = values.as_any().downcast_ref::<TupleT>() {
+                    let mut used = vec![false; tuple.0.len()];
+                    let mut next = 0;
+                    for key in &keys {
+                        let index = match key {
+                            FormatKey::Next => {
+                                next += 1;
+                                next - 1
+                            }
+                            FormatKey::Index(n) => *n,
+                            FormatKey::Name(name) => {
+                                return Err(err(format!(
+                                    "format template uses the named placeholder {{{name}}}, but the values are a tuple {}",
+                                    values.with_info(info)
+                                )))
+                            }
+                        };
+                        if let Some(used) = used.get_mut(index) {
+                            *used = true;
+                        } else {
+                            return Err(err(format!(
+                                "format template refers to value #{index}, but there are only {} values in {}",
+                                tuple.0.len(),
+                                values.with_info(info)
+                            )));
+                        }
+                    }
+                    if let Some(unused) = used.iter().position(|u| !u) {
+                        return Err(err(format!(
+                            "format template has no placeholder for value #{unused} in {}",
+                            values.with_info(info)
+                        )));
+                    }
+                } else if let Some(object) = values.as_any().downcast_ref::<ObjectT>() {
+                    for key in &keys {
+                        match key {
+                            FormatKey::Name(name) => {
+                                if object
+                                    .get(info.global.object_fields.get_or_add_field(name))
+                                    .is_none()
+                                {
+                                    return Err(err(format!(
+                                        "format template uses the placeholder {{{name}}}, but the object {} has no such field",
+                                        values.with_info(info)
+                                    )));
+                                }
+                            }
+                            FormatKey::Next | FormatKey::Index(_) => {
+                                return Err(err(format!(
+                                    "format template uses positional placeholders, but the values are an object {}",
+                                    values.with_info(info)
+                                )))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        target.as_any().is::<Self>() || self.0.is_included_in(target)
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl std::fmt::Debug for FormatT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FormatT")
+    }
+}
+
+/// a function which turns a `String` into a `List<Byte>` or `{error_field: String}`
+fn bytes_decode_func(
+    error_field: &'static str,
+    decode: fn(&str) -> Result<Vec<u8>, String>,
+) -> Function {
+    util::to_mers_func_with_in_type(
+        Type::new(data::string::StringT),
+        move |_a, i| {
+            Ok(or_error(
+                Type::new(super::with_list::ListT(Type::new(data::byte::ByteT))),
+                error_field,
+                i,
+            ))
+        },
+        move |a, i| {
+            let a = a.get();
+            let s = &a.as_any().downcast_ref::<data::string::String>().unwrap().0;
+            Ok(match decode(s) {
+                Ok(bytes) => Data::new(super::with_list::List(
+                    bytes
+                        .into_iter()
+                        .map(|b| Data::new(data::byte::Byte(b)))
+                        .collect(),
+                )),
+                Err(e) => error_object(error_field, e, i),
+            })
+        },
+    )
+}
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_CHARS[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+fn base64_decode(s: &str) -> Result<Vec<u8>, String> {
+    let s = s.trim_end_matches('=');
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut n, mut bits) = (0u32, 0);
+    for c in s.chars() {
+        let v = BASE64_CHARS
+            .iter()
+            .position(|b| *b as char == c)
+            .ok_or_else(|| format!("invalid base64 character {c:?}"))?;
+        n = n << 6 | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+    // a single leftover character can't encode a full byte
+    if s.len() % 4 == 1 {
+        return Err("invalid base64 length".to_owned());
+    }
+    Ok(out)
+}
@@ -0,0 +1,432 @@
+use std::{
+    fmt::Display,
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+
+use crate::{
+    data::{
+        self,
+        int::{INT_MAX, INT_MIN},
+        object::ObjectFieldsMap,
+        Data, MersData, MersType, Type,
+    },
+    errors::CheckError,
+    info::DisplayInfo,
+};
+
+use super::{
+    gen::{
+        function::{fun, func, Funcs, StaticMersFunc},
+        FromMersData, IntR, OneOf, OneOrNone, ToMersData,
+    },
+    util, Config,
+};
+
+/// used by `time_monotonic`
+static START: OnceLock<Instant> = OnceLock::new();
+
+impl Config {
+    /// adds the `Timestamp` and `Duration` types and functions to work with them.
+    /// A `Timestamp` is a point in time with a UTC offset, a `Duration` is a (possibly negative) amount of time.
+    /// `time_now: fn` returns the current system time in UTC
+    /// `time_now_local: fn` returns the current system time in the local timezone
+    /// `time_monotonic: fn` returns the time since the program started, measured using a monotonic clock (use this to measure how long something takes)
+    /// `time_parse: fn` parses an RFC 3339 / ISO 8601 string like `2024-05-01T12:30:00+02:00` into a `Timestamp`, or returns `{time_parse_error: String}`. Without an offset, UTC is assumed. `2024-05-01` is midnight (UTC) of that day.
+    /// `time_to_string: fn` formats a `Timestamp` as an RFC 3339 string
+    /// `time_to_utc: fn` changes a `Timestamp`'s offset to UTC, without changing the point in time it represents
+    /// `time_with_offset: fn` changes a `Timestamp`'s offset to the given number of seconds east of UTC: (timestamp, 3600).time_with_offset
+    /// `time_offset: fn` returns a `Timestamp`'s offset in seconds east of UTC
+    /// `time_unix: fn` returns the number of seconds since 1970-01-01T00:00:00Z
+    /// `time_from_unix: fn` returns the `Timestamp` the given number of seconds after 1970-01-01T00:00:00Z, or () if it would be out of range
+    /// `time_add: fn` adds a `Duration` to a `Timestamp` or `Duration`: (timestamp, duration).time_add
+    /// `time_sub: fn` subtracts a `Duration` from a `Timestamp` or `Duration`, or returns the `Duration` between two `Timestamp`s: (later, earlier).time_sub
+    /// `time_lt: fn` returns true if the first `Timestamp` is before the second one, or the first `Duration` is shorter than the second one
+    /// `time_gt: fn` returns true if the first `Timestamp` is after the second one, or the first `Duration` is longer than the second one
+    /// `duration_from_seconds: fn` turns a number of seconds (Int or Float) into a `Duration`
+    /// `duration_from_millis: fn` turns a number of milliseconds into a `Duration`
+    /// `duration_seconds: fn` returns the length of a `Duration` in seconds, as a Float
+    /// `duration_millis: fn` returns the length of a `Duration` in whole milliseconds
+    pub fn with_time(self) -> Self {
+        START.get_or_init(Instant::now);
+        self.add_type("Timestamp".to_owned(), Ok(Arc::new(Type::new(TimestampT))))
+            .add_type("Duration".to_owned(), Ok(Arc::new(Type::new(DurationT))))
+            .add_var(
+                "time_now",
+                func(|_: (), _| Ok(Timestamp(Utc::now().fixed_offset()))),
+            )
+            .add_var(
+                "time_now_local",
+                func(|_: (), _| Ok(Timestamp(chrono::Local::now().fixed_offset()))),
+            )
+            .add_var(
+                "time_monotonic",
+                func(|_: (), _| {
+                    Ok(Duration(
+                        TimeDelta::from_std(START.get_or_init(Instant::now).elapsed())
+                            .unwrap_or(TimeDelta::MAX),
+                    ))
+                }),
+            )
+            .add_var(
+                "time_parse",
+                util::to_mers_func_with_in_type(
+                    Type::new(data::string::StringT),
+                    |_a, i| {
+                        Ok(Type::newm(vec![
+                            Arc::new(TimestampT),
+                            Arc::new(data::object::ObjectT::new(vec![(
+                                i.global.object_fields.get_or_add_field("time_parse_error"),
+                                Type::new(data::string::StringT),
+                            )])),
+                        ]))
+                    },
+                    |a, i| {
+                        let a = a.get();
+                        let s = a
+                            .as_any()
+                            .downcast_ref::<data::string::String>()
+                            .expect("got non-string argument to time_parse")
+                            .0
+                            .trim();
+                        Ok(match parse_timestamp(s) {
+                            Ok(t) => Data::new(Timestamp(t)),
+                            Err(e) => Data::new(data::object::Object::new(vec![(
+                                i.global.object_fields.get_or_add_field("time_parse_error"),
+                                Data::new(data::string::String(e)),
+                            )])),
+                        })
+                    },
+                ),
+            )
+            .add_var("time_to_string", func(|t: Timestamp, _| Ok(t.to_string())))
+            .add_var(
+                "time_to_utc",
+                func(|t: Timestamp, _| Ok(Timestamp(t.0.to_utc().fixed_offset()))),
+            )
+            .add_var(
+                "time_with_offset",
+                func(|(t, o): (Timestamp, IntR<-86399, 86399>), _| {
+                    Ok(Timestamp(
+                        t.0.with_timezone(
+                            &FixedOffset::east_opt(o.0 as i32)
+                                .expect("offset is in range because of its type"),
+                        ),
+                    ))
+                }),
+            )
+            .add_var(
+                "time_offset",
+                func(|t: Timestamp, _| {
+                    Ok(IntR::<-86399, 86399>(
+                        t.0.offset().local_minus_utc() as isize
+                    ))
+                }),
+            )
+            .add_var(
+                "time_unix",
+                func(|t: Timestamp, _| Ok(IntR::<INT_MIN, INT_MAX>(t.0.timestamp() as isize))),
+            )
+            .add_var(
+                "time_from_unix",
+                func(|s: IntR<INT_MIN, INT_MAX>, _| {
+                    Ok(OneOrNone(
+                        DateTime::from_timestamp(s.0 as i64, 0)
+                            .map(|t| Timestamp(t.fixed_offset())),
+                    ))
+                }),
+            )
+            .add_var(
+                "time_add",
+                Funcs(
+                    fun(|(t, d): (Timestamp, Duration), _| {
+                        t.0.checked_add_signed(d.0)
+                            .map(Timestamp)
+                            .ok_or_else(|| out_of_range("time_add"))
+                    }),
+                    fun(|(a, b): (Duration, Duration), _| {
+                        a.0.checked_add(&b.0)
+                            .map(Duration)
+                            .ok_or_else(|| out_of_range("time_add"))
+                    }),
+                )
+                .mers_func(),
+            )
+            .add_var(
+                "time_sub",
+                Funcs(
+                    fun(|(a, b): (Timestamp, Timestamp), _| {
+                        Ok(Duration(a.0.signed_duration_since(b.0)))
+                    }),
+                    Funcs(
+                        fun(|(t, d): (Timestamp, Duration), _| {
+                            t.0.checked_sub_signed(d.0)
+                                .map(Timestamp)
+                                .ok_or_else(|| out_of_range("time_sub"))
+                        }),
+                        fun(|(a, b): (Duration, Duration), _| {
+                            a.0.checked_sub(&b.0)
+                                .map(Duration)
+                                .ok_or_else(|| out_of_range("time_sub"))
+                        }),
+                    ),
+                )
+                .mers_func(),
+            )
+            .add_var(
+                "time_lt",
+                Funcs(
+                    fun(|(a, b): (Timestamp, Timestamp), _| Ok(a.0 < b.0)),
+                    fun(|(a, b): (Duration, Duration), _| Ok(a.0 < b.0)),
+                )
+                .mers_func(),
+            )
+            .add_var(
+                "time_gt",
+                Funcs(
+                    fun(|(a, b): (Timestamp, Timestamp), _| Ok(a.0 > b.0)),
+                    fun(|(a, b): (Duration, Duration), _| Ok(a.0 > b.0)),
+                )
+                .mers_func(),
+            )
+            .add_var(
+                "duration_from_seconds",
+                func(|s: OneOf<IntR<INT_MIN, INT_MAX>, f64>, _| {
+                    match s {
+                        OneOf::A(s) => TimeDelta::try_seconds(s.0 as i64),
+                        OneOf::B(s) => {
+                            let nanos = s * 1e9;
+                            (nanos.is_finite() && nanos.abs() < i64::MAX as f64)
+                                .then(|| TimeDelta::nanoseconds(nanos as i64))
+                        }
+                    }
+                    .map(Duration)
+                    .ok_or_else(|| out_of_range("duration_from_seconds"))
+                }),
+            )
+            .add_var(
+                "duration_from_millis",
+                func(|s: IntR<INT_MIN, INT_MAX>, _| {
+                    TimeDelta::try_milliseconds(s.0 as i64)
+                        .map(Duration)
+                        .ok_or_else(|| out_of_range("duration_from_millis"))
+                }),
+            )
+            .add_var(
+                "duration_seconds",
+                func(|d: Duration, _| {
+                    Ok(d.0.num_seconds() as f64 + d.0.subsec_nanos() as f64 / 1e9)
+                }),
+            )
+            .add_var(
+                "duration_millis",
+                func(|d: Duration, _| {
+                    Ok(IntR::<INT_MIN, INT_MAX>(d.0.num_milliseconds() as isize))
+                }),
+            )
+    }
+}
+
+fn out_of_range(func: &str) -> CheckError {
+    format!("{func}: result is out of range").into()
+}
+
+fn parse_timestamp(s: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(s).or_else(|e| {
+        // ISO 8601 allows omitting the offset, we assume UTC in that case
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f"))
+            .or_else(|_| {
+                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .map(|d| d.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+            })
+            .map(|t| t.and_utc().fixed_offset())
+            .map_err(|_| format!("can't parse {s:?} as a timestamp: {e}"))
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct Timestamp(pub DateTime<FixedOffset>);
+#[derive(Clone, Debug)]
+pub struct TimestampT;
+#[derive(Clone, Debug)]
+pub struct Duration(pub TimeDelta);
+#[derive(Clone, Debug)]
+pub struct DurationT;
+
+impl MersData for Timestamp {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| self.0 == other.0)
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(TimestampT)
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.to_rfc3339())
+    }
+}
+impl MersType for TimestampT {
+    fn display(
+        &self,
+        _info: &crate::info::DisplayInfo<'_>,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other.as_any().is::<Self>()
+    }
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        target.as_any().is::<Self>()
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl Display for TimestampT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Timestamp")
+    }
+}
+impl FromMersData for Timestamp {
+    fn as_type_from() -> Type {
+        Self::as_type_to()
+    }
+    fn can_represent(t: &Type) -> bool {
+        t.is_included_in_single(&TimestampT)
+    }
+    fn try_represent<O, F: FnOnce(Option<Self>) -> O>(d: &(impl MersData + ?Sized), f: F) -> O {
+        f(d.as_any().downcast_ref::<Self>().cloned())
+    }
+}
+impl ToMersData for Timestamp {
+    fn as_type_to() -> Type {
+        Type::new(TimestampT)
+    }
+    fn represent(self) -> Data {
+        Data::new(self)
+    }
+}
+
+impl MersData for Duration {
+    fn display(&self, _info: &DisplayInfo<'_>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_eq(&self, other: &dyn MersData) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| self.0 == other.0)
+    }
+    fn clone(&self) -> Box<dyn MersData> {
+        Box::new(Clone::clone(self))
+    }
+    fn as_type(&self) -> Type {
+        Type::new(DurationT)
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // ISO 8601 duration, like `PT1.5S`
+        write!(f, "{}", self.0)
+    }
+}
+impl MersType for DurationT {
+    fn display(
+        &self,
+        _info: &crate::info::DisplayInfo<'_>,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+    fn is_same_type_as(&self, other: &dyn MersType) -> bool {
+        other.as_any().is::<Self>()
+    }
+    fn is_included_in(&self, target: &dyn MersType) -> bool {
+        target.as_any().is::<Self>()
+    }
+    fn without(&self, remove: &dyn MersType) -> Option<Type> {
+        if self.is_included_in(remove) {
+            Some(Type::empty())
+        } else {
+            None
+        }
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn to_any(self) -> Box<dyn std::any::Any> {
+        Box::new(self)
+    }
+}
+impl Display for DurationT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Duration")
+    }
+}
+impl FromMersData for Duration {
+    fn as_type_from() -> Type {
+        Self::as_type_to()
+    }
+    fn can_represent(t: &Type) -> bool {
+        t.is_included_in_single(&DurationT)
+    }
+    fn try_represent<O, F: FnOnce(Option<Self>) -> O>(d: &(impl MersData + ?Sized), f: F) -> O {
+        f(d.as_any().downcast_ref::<Self>().cloned())
+    }
+}
+impl ToMersData for Duration {
+    fn as_type_to() -> Type {
+        Type::new(DurationT)
+    }
+    fn represent(self) -> Data {
+        Data::new(self)
+    }
+}
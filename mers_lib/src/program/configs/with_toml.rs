@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+
+use crate::{
+    data::{
+        self,
+        bool::{Bool, FalseT, TrueT},
+        float::{Float, FloatT},
+        function::{Function, FunctionT},
+        int::{Int, IntT},
+        object::{Object, ObjectT},
+        string::StringT,
+        tuple::{Tuple, TupleT},
+        Data, MersType, MersTypeWInfo, Type,
+    },
+    errors::CheckError,
+    program::run::{chain::run_callback, CheckInfo, Info},
+};
+
+use super::{
+    util::{self, error_object, or_error},
+    with_list::{List, ListT},
+    Config,
+};
+
+impl Config {
+    /// converting between TOML documents and mers values.
+    /// tables become objects, arrays become lists (or tuples), and strings, integers, floats and booleans become `String`, `Int`, `Float` and `Bool`.
+    /// `toml_parse: fn` (text, handler).toml_parse parses a TOML document and calls the handler with it.
+    ///   the handler must be a function with a type annotation, like `cfg [{host: String, port: Int<0..65535>}] -> cfg`,
+    ///   which describes the shape the document must have. the document is checked against this type at runtime,
+    ///   and only converted and passed to the handler if it matches, so the handler (and its return value) is fully typed.
+    ///   toml_parse returns what the handler returns, or `{toml_error: String}` if the text isn't valid TOML or doesn't match the type.
+    ///   keys which aren't in the type are ignored. fields of type `()` (like `{port: Int/()}`) may be missing, dates and times can be read as `String`s.
+    /// `toml_encode: fn` turns an object into a TOML document. fields containing `()` are omitted, `()` anywhere else (like in `{a: ((), 1)}`) is a type error.
+    pub fn with_toml(self) -> Self {
+        self.add_var(
+            "toml_parse",
+            util::to_mers_func(
+                |a, i| {
+                    let mut out = Type::empty();
+                    for t in a.types.iter() {
+                        let t = t
+                            .as_any()
+                            .downcast_ref::<TupleT>()
+                            .filter(|t| {
+                                t.0.len() == 2 && t.0[0].is_included_in_single(&StringT)
+                            })
+                            .ok_or_else(|| {
+                                format!(
+                                    "toml_parse: expected (String, handler), but got {}",
+                                    a.with_info(i)
+                                )
+                            })?;
+                        for f in t.0[1].types.iter() {
+                            let f = f.as_any().downcast_ref::<FunctionT>().ok_or_else(|| {
+                                format!(
+                                    "toml_parse: handler {} is not a function",
+                                    f.with_info(i)
+                                )
+                            })?;
+                            let shape = util::fixed_arg_type(f).ok_or_else(|| {
+                                format!(
+                                    "toml_parse: the handler needs a type annotation which describes the document, like `cfg [{{port: Int}}] -> cfg`, but its type is {}",
+                                    f.with_info(i)
+                                )
+                            })?;
+                            for t in shape.types.iter() {
+                                if !t.as_any().is::<ObjectT>() {
+                                    return Err(format!(
+                                        "toml_parse: a TOML document is a table, so the handler's argument must be an object, but it can be {}",
+                                        t.with_info(i)
+                                    )
+                                    .into());
+                                }
+                            }
+                            check_shape(&shape, i)?;
+                            out.add_all(&f.o(&shape)?);
+                        }
+                    }
+                    Ok(or_error(out, "toml_error", i))
+                },
+                |a, i| {
+                    let a = a.get();
+                    let a = &a.as_any().downcast_ref::<Tuple>().unwrap().0;
+                    let text = a[0].read();
+                    let text = text.get();
+                    let text = &text
+                        .as_any()
+                        .downcast_ref::<data::string::String>()
+                        .unwrap()
+                        .0;
+                    let handler = a[1].read();
+                    let shape = util::fixed_arg_type(
+                        &handler
+                            .get()
+                            .as_any()
+                            .downcast_ref::<Function>()
+                            .unwrap()
+                            .get_as_type(),
+                    )
+                    .unwrap();
+                    let doc = match text.parse::<toml::Table>() {
+                        Ok(doc) => toml::Value::Table(doc),
+                        Err(e) => return Ok(error_object("toml_error", e.to_string().trim_end(), i)),
+                    };
+                    let names = field_names(i);
+                    let doc = match from_toml(&doc, &shape, &mut vec![], &names, i) {
+                        Ok(doc) => doc,
+                        Err(e) => return Ok(error_object("toml_error", e, i)),
+                    };
+                    let out = run_callback(&**handler.get(), doc, &i.global, i.global.call_site.as_ref()).unwrap();
+                    out
+                },
+            ),
+        )
+        .add_var(
+            "toml_encode",
+            util::to_mers_func(
+                |a, i| {
+                    for t in a.types.iter() {
+                        if !t.as_any().is::<ObjectT>() {
+                            return Err(format!(
+                                "toml_encode: a TOML document is a table, so the argument must be an object, but it can be {}",
+                                t.with_info(i)
+                            )
+                            .into());
+                        }
+                    }
+                    check_encodable(a, false, i)?;
+                    Ok(Type::new(StringT))
+                },
+                |a, i| {
+                    let names = field_names(i);
+                    let Some(toml::Value::Table(doc)) = to_toml(&a, &names) else {
+                        unreachable!("toml_encode argument was not an object")
+                    };
+                    Ok(Data::new(data::string::String(
+                        toml::to_string(&doc).map_err(|e| format!("toml_encode: {e}"))?,
+                    )))
+                },
+            ),
+        )
+    }
+}
+
+/// field id -> field name
+fn field_names(i: &Info) -> HashMap<usize, String> {
+    i.global
+        .object_fields
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, id)| (*id, name.clone()))
+        .collect()
+}
+
+/// checks that every part of the type can be read from TOML
+fn check_shape(t: &Type, i: &CheckInfo) -> Result<(), CheckError> {
+    for t in t.types.iter() {
+        if let Some(l) = t.as_any().downcast_ref::<ListT>() {
+            check_shape(&l.0, i)?;
+        } else if let Some(tuple) = t.as_any().downcast_ref::<TupleT>() {
+            for t in tuple.0.iter() {
+                check_shape(t, i)?;
+            }
+        } else if let Some(o) = t.as_any().downcast_ref::<ObjectT>() {
+            for (_, t) in o.iter() {
+                check_shape(t, i)?;
+            }
+        } else if !is_scalar(t.as_ref()) {
+            return Err(format!(
+                "toml_parse: TOML can't contain values of type {}",
+                t.with_info(i)
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// checks that every part of the type can be written to TOML.
+/// `()` is only allowed as the value of an object's field.
+fn check_encodable(t: &Type, is_field: bool, i: &CheckInfo) -> Result<(), CheckError> {
+    for t in t.types.iter() {
+        if let Some(l) = t.as_any().downcast_ref::<ListT>() {
+            check_encodable(&l.0, false, i)?;
+        } else if let Some(tuple) = t.as_any().downcast_ref::<TupleT>() {
+            if tuple.0.is_empty() && !is_field {
+                return Err(
+                    "toml_encode: () can only be the value of an object field (which is then omitted), not an element of a list or tuple"
+                        .to_owned()
+                        .into(),
+                );
+            }
+            for t in tuple.0.iter() {
+                check_encodable(t, false, i)?;
+            }
+        } else if let Some(o) = t.as_any().downcast_ref::<ObjectT>() {
+            for (_, t) in o.iter() {
+                check_encodable(t, true, i)?;
+            }
+        } else if !is_scalar(t.as_ref()) {
+            return Err(format!(
+                "toml_encode: values of type {} can't be written to TOML",
+                t.with_info(i)
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// `String`, `Int`, `Float` or `Bool`
+fn is_scalar(t: &dyn MersType) -> bool {
+    let t = t.as_any();
+    t.is::<StringT>() || t.is::<IntT>() || t.is::<FloatT>() || t.is::<TrueT>() || t.is::<FalseT>()
+}
+
+/// converts a TOML value to a value of type `t`, or returns an error mentioning the `path` to the value which didn't match.
+fn from_toml(
+    v: &toml::Value,
+    t: &Type,
+    path: &mut Vec<String>,
+    names: &HashMap<usize, String>,
+    i: &Info,
+) -> Result<Data, String> {
+    let mut first_error = None;
+    for single in t.types.iter() {
+        match from_toml_single(v, single.as_ref(), path, names, i) {
+            Ok(Some(d)) => return Ok(d),
+            Ok(None) => {}
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| {
+        format!(
+            "{}: expected {}, found {}",
+            if path.is_empty() {
+                "document".to_owned()
+            } else {
+                format!("`{}`", path.join("."))
+            },
+            t.with_info(i),
+            v.type_str()
+        )
+    }))
+}
+/// `Ok(None)` if `v` isn't this kind of value, `Err(_)` if it is, but something inside it doesn't match
+fn from_toml_single(
+    v: &toml::Value,
+    t: &dyn MersType,
+    path: &mut Vec<String>,
+    names: &HashMap<usize, String>,
+    i: &Info,
+) -> Result<Option<Data>, String> {
+    let t = t.as_any();
+    Ok(Some(match v {
+        toml::Value::String(s) if t.is::<StringT>() => Data::new(data::string::String(s.clone())),
+        toml::Value::Datetime(d) if t.is::<StringT>() => {
+            Data::new(data::string::String(d.to_string()))
+        }
+        toml::Value::Integer(n) => {
+            if let Some(IntT(min, max)) = t.downcast_ref::<IntT>() {
+                match isize::try_from(*n) {
+                    Ok(n) if *min <= n && n <= *max => Data::new(Int(n)),
+                    _ => return Ok(None),
+                }
+            } else if t.is::<FloatT>() {
+                Data::new(Float(*n as f64))
+            } else {
+                return Ok(None);
+            }
+        }
+        toml::Value::Float(n) if t.is::<FloatT>() => Data::new(Float(*n)),
+        toml::Value::Boolean(true) if t.is::<TrueT>() => Data::new(Bool(true)),
+        toml::Value::Boolean(false) if t.is::<FalseT>() => Data::new(Bool(false)),
+        toml::Value::Array(arr) => {
+            if let Some(l) = t.downcast_ref::<ListT>() {
+                let mut out = Vec::with_capacity(arr.len());
+                for (index, v) in arr.iter().enumerate() {
+                    path.push(index.to_string());
+                    out.push(from_toml(v, &l.0, path, names, i)?);
+                    path.pop();
+                }
+                Data::new(List(out))
+            } else if let Some(tuple) = t
+                .downcast_ref::<TupleT>()
+                .filter(|t| t.0.len() == arr.len())
+            {
+                let mut out = Vec::with_capacity(arr.len());
+                for (index, (v, t)) in arr.iter().zip(tuple.0.iter()).enumerate() {
+                    path.push(index.to_string());
+                    out.push(from_toml(v, t, path, names, i)?);
+                    path.pop();
+                }
+                Data::new(Tuple::from(out))
+            } else {
+                return Ok(None);
+            }
+        }
+        toml::Value::Table(table) => {
+            let Some(o) = t.downcast_ref::<ObjectT>() else {
+                return Ok(None);
+            };
+            let mut out = Vec::with_capacity(o.iter().len());
+            for (field, t) in o.iter() {
+                let name = names.get(field).map(|s| s.as_str()).unwrap_or_default();
+                path.push(name.to_owned());
+                if let Some(v) = table.get(name) {
+                    out.push((*field, from_toml(v, t, path, names, i)?));
+                } else if t.types.iter().any(|t| {
+                    t.as_any()
+                        .downcast_ref::<TupleT>()
+                        .is_some_and(|t| t.0.is_empty())
+                }) {
+                    out.push((*field, Data::empty_tuple()));
+                } else {
+                    return Err(format!("missing key `{}`", path.join(".")));
+                }
+                path.pop();
+            }
+            Data::new(Object::new(out))
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// converts a value to TOML. `None` for `()`, which is only allowed (and omitted) in objects.
+fn to_toml(d: &Data, names: &HashMap<usize, String>) -> Option<toml::Value> {
+    let d = d.get();
+    let d = d.as_any();
+    Some(if let Some(s) = d.downcast_ref::<data::string::String>() {
+        toml::Value::String(s.0.clone())
+    } else if let Some(n) = d.downcast_ref::<Int>() {
+        toml::Value::Integer(n.0 as i64)
+    } else if let Some(n) = d.downcast_ref::<Float>() {
+        toml::Value::Float(n.0)
+    } else if let Some(b) = d.downcast_ref::<Bool>() {
+        toml::Value::Boolean(b.0)
+    } else if let Some(l) = d.downcast_ref::<List>() {
+        toml::Value::Array(l.0.iter().filter_map(|v| to_toml(v, names)).collect())
+    } else if let Some(t) = d.downcast_ref::<Tuple>() {
+        if t.0.is_empty() {
+            return None;
+        }
+        toml::Value::Array(
+            t.0.iter()
+                .filter_map(|v| to_toml(&v.read(), names))
+                .collect(),
+        )
+    } else if let Some(o) = d.downcast_ref::<Object>() {
+        toml::Value::Table(
+            o.iter()
+                .filter_map(|(field, v)| {
+                    Some((names.get(field)?.clone(), to_toml(&v.read(), names)?))
+                })
+                .collect(),
+        )
+    } else {
+        return None;
+    })
+}
@@ -57,6 +57,9 @@ pub trait MersStatement: Debug + Send + Sync {
         comp: CompInfo,
     ) -> Result<Box<dyn super::run::MersStatement>, CheckError> {
         info.global.depth += 1;
+        if let Some(debugger) = &info.global.debugger {
+            debugger.record_names(&self.source_range(), info);
+        }
         if self.has_scope() {
             info.create_scope();
         }
@@ -135,6 +138,8 @@ pub struct LocalGlobalInfo {
     >,
     pub object_fields: Arc<Mutex<HashMap<String, usize>>>,
     pub object_fields_rev: Arc<Mutex<Vec<String>>>,
+    /// if set, the names of the variables visible at each statement are recorded so the debugger can show them.
+    pub debugger: Option<Arc<super::run::debugger::Debugger>>,
 }
 impl LocalGlobalInfo {
     pub fn new(object_fields: Arc<Mutex<HashMap<String, usize>>>) -> Self {
@@ -144,6 +149,7 @@ impl LocalGlobalInfo {
             save_info_at: Default::default(),
             object_fields,
             object_fields_rev: Default::default(),
+            debugger: None,
         }
     }
 }
@@ -158,6 +164,7 @@ impl info::Local for Local {
             save_info_at: Default::default(),
             object_fields: Default::default(),
             object_fields_rev: Default::default(),
+            debugger: None,
         }
     }
     fn init_var(&mut self, id: Self::VariableIdentifier, value: Self::VariableData) {
@@ -26,11 +26,11 @@ impl MersStatement for Block {
         Ok(o)
     }
     fn run_custom(&self, info: &mut super::Info) -> Result<Data, CheckError> {
-        self.statements
-            .iter()
-            .map(|s| s.run(info))
-            .last()
-            .unwrap_or_else(|| Ok(Data::new(data::tuple::Tuple::empty())))
+        let mut o = Data::new(data::tuple::Tuple::empty());
+        for s in &self.statements {
+            o = s.run(info)?;
+        }
+        Ok(o)
     }
     fn has_scope(&self) -> bool {
         true
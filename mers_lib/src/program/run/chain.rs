@@ -1,10 +1,10 @@
 use crate::{
-    data::{Data, Type},
+    data::{Data, MersData, Type},
     errors::{CheckError, EColor, SourceRange},
     parsing::Source,
 };
 
-use super::MersStatement;
+use super::{MersStatement, RunLocalGlobalInfo};
 
 #[derive(Debug)]
 pub struct Chain {
@@ -29,7 +29,7 @@ impl MersStatement for Chain {
         let arg = self.first.check(info, None)?;
         let func = self.chained.check(info, None)?;
         info.global.enable_hooks = prev_enable_hooks;
-        check(
+        let o = check(
             &arg,
             &func,
             info,
@@ -37,7 +37,11 @@ impl MersStatement for Chain {
             self.first.source_range(),
             self.chained.source_range(),
             self.as_part_of_include.as_ref(),
-        )
+        )?;
+        for func in &func.types {
+            func.check_call(self.first.as_ref(), &arg, info)?;
+        }
+        Ok(o)
     }
     fn run_custom(&self, info: &mut super::Info) -> Result<Data, CheckError> {
         let f = self.first.run(info)?;
@@ -142,7 +146,16 @@ pub fn run(
     as_part_of_include: Option<&Source>,
 ) -> Result<Data, CheckError> {
     let func = func.get();
-    match func.execute(arg, &info.global) {
+    if let Some(debugger) = &info.global.debugger {
+        debugger.enter_call(&pos_in_src, &func_pos, info);
+    }
+    let prev_call_site = info.global.call_site.replace(pos_in_src.clone());
+    let out = func.execute(arg, &info.global);
+    info.global.call_site = prev_call_site;
+    if let Some(debugger) = &info.global.debugger {
+        debugger.exit_call();
+    }
+    match out {
         Some(Ok(v)) => Ok(v),
         Some(Err(e)) => Err(if let Some(_) = &as_part_of_include {
             CheckError::new().err_with_diff_src(e).src(vec![(
@@ -159,3 +172,22 @@ pub fn run(
             .src(vec![(func_pos, Some(EColor::ChainWithNonFunction))])),
     }
 }
+
+/// Calls `func` from a builtin function (like `for_each` or `thread`) which was called at `call_site` (`RunLocalGlobalInfo::call_site` when the builtin ran).
+/// Like `run` for chains, this adds `call_site` to the stack trace of errors, so they show which call to the builtin called `func`.
+/// Returns `None` if `func` isn't a function.
+pub fn run_callback(
+    func: &dyn MersData,
+    arg: Data,
+    gi: &RunLocalGlobalInfo,
+    call_site: Option<&SourceRange>,
+) -> Option<Result<Data, CheckError>> {
+    Some(func.execute(arg, gi)?.map_err(|e| {
+        match call_site {
+            Some(call_site) => CheckError::new()
+                .err(e)
+                .src(vec![(call_site.clone(), Some(EColor::StacktraceDescend))]),
+            None => e,
+        }
+    }))
+}
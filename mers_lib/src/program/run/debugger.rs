@@ -0,0 +1,450 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    path::PathBuf,
+    sync::{mpsc, Arc, Condvar, Mutex, MutexGuard, RwLock},
+    thread::ThreadId,
+};
+
+use crate::{
+    data::{Data, MersDataWInfo},
+    errors::{CheckError, SourceRange},
+    info::DisplayInfo,
+    parsing::{Source, SourceFrom},
+    program::parsed,
+};
+
+use super::Info;
+
+/// Lets a frontend (for example a DAP server) pause running mers code at breakpoints,
+/// step through it line by line and inspect the variables in each call frame.
+///
+/// To use it, set `global.debugger` in the parsed `Info` (before compiling, so that variable names can be recorded)
+/// and in the run `Info` (before running).
+/// Whenever a thread stops, a `DebugEvent::Stopped` is sent to the receiver returned by `Debugger::new`,
+/// and the thread blocks until `resume` (or `terminate`) is called.
+///
+/// Threads are identified by numbers starting at `1`, in the order in which they first ran mers code.
+/// Frames are indexed from the innermost (`0`) to the outermost one.
+pub struct Debugger {
+    state: Mutex<DebuggerState>,
+    resumed: Condvar,
+    events: Mutex<mpsc::Sender<DebugEvent>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugEvent {
+    /// The thread stopped and is waiting for `resume`
+    Stopped { thread: usize, reason: StopReason },
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Entry,
+    Breakpoint,
+    Step,
+    Pause,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resume {
+    Continue,
+    /// stop at the next line, even if it is in another function
+    StepIn,
+    /// stop at the next line in this function or in one of its callers
+    StepOver,
+    /// stop at the next line in one of the callers
+    StepOut,
+}
+#[derive(Clone, Debug)]
+pub struct StackFrame {
+    pub name: String,
+    /// `None` if the code wasn't loaded from a file
+    pub file: Option<PathBuf>,
+    /// starts at `1`, `0` if the frame hasn't reached any line yet
+    pub line: usize,
+    /// starts at `1`, `0` if the frame hasn't reached any line yet
+    pub column: usize,
+}
+#[derive(Clone, Debug)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    pub value_type: String,
+}
+
+struct DebuggerState {
+    breakpoints: HashMap<PathBuf, HashSet<usize>>,
+    stop_on_entry: bool,
+    terminated: bool,
+    threads: Vec<DebugThread>,
+    /// source ptr -> source info
+    sources: HashMap<usize, DebugSource>,
+    /// (source ptr, statement start) -> variables visible at that statement, as (name, (scope, var)).
+    var_names: HashMap<(usize, usize), Arc<VarNames>>,
+    object_fields: Option<ObjectFields>,
+}
+type VarNames = Vec<(String, (usize, usize))>;
+type ObjectFields = (Arc<Mutex<HashMap<String, usize>>>, Arc<Mutex<Vec<String>>>);
+struct DebugSource {
+    /// keeps the `Source` alive, so its address can't be reused by another source
+    _source: Arc<Source>,
+    file: Option<PathBuf>,
+    /// position (in the original source) where each line starts
+    line_starts: Vec<usize>,
+}
+struct DebugThread {
+    id: ThreadId,
+    mode: Mode,
+    paused: bool,
+    frames: Vec<Frame>,
+}
+#[derive(Clone, Copy)]
+enum Mode {
+    Continue,
+    Entry,
+    Pause,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+}
+struct Frame {
+    name: String,
+    /// (source ptr, line, column)
+    location: Option<(usize, usize, usize)>,
+    vars: Vec<(String, Arc<RwLock<Data>>)>,
+}
+
+impl Debugger {
+    pub fn new() -> (Arc<Self>, mpsc::Receiver<DebugEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        (
+            Arc::new(Self {
+                state: Mutex::new(DebuggerState {
+                    breakpoints: HashMap::new(),
+                    stop_on_entry: false,
+                    terminated: false,
+                    threads: vec![],
+                    sources: HashMap::new(),
+                    var_names: HashMap::new(),
+                    object_fields: None,
+                }),
+                resumed: Condvar::new(),
+                events: Mutex::new(sender),
+            }),
+            receiver,
+        )
+    }
+    /// If true, the first thread stops before running its first line.
+    pub fn stop_on_entry(&self, stop_on_entry: bool) {
+        self.state().stop_on_entry = stop_on_entry;
+    }
+    /// Replaces all breakpoints in `file` with breakpoints at the given lines (starting at `1`).
+    pub fn set_breakpoints(&self, file: PathBuf, lines: impl IntoIterator<Item = usize>) {
+        let file = file.canonicalize().unwrap_or(file);
+        self.state()
+            .breakpoints
+            .insert(file, lines.into_iter().collect());
+    }
+    /// (id, name) of every thread that has run mers code
+    pub fn threads(&self) -> Vec<(usize, String)> {
+        (1..=self.state().threads.len())
+            .map(|id| {
+                (
+                    id,
+                    if id == 1 {
+                        "main".to_owned()
+                    } else {
+                        format!("thread {id}")
+                    },
+                )
+            })
+            .collect()
+    }
+    /// Returns true if the thread exists and is currently stopped.
+    pub fn is_stopped(&self, thread: usize) -> bool {
+        self.state()
+            .threads
+            .get(thread.wrapping_sub(1))
+            .is_some_and(|t| t.paused)
+    }
+    /// The call stack of a thread, innermost frame first.
+    /// Only reliable while the thread is stopped.
+    pub fn stack_trace(&self, thread: usize) -> Vec<StackFrame> {
+        let state = self.state();
+        let Some(t) = state.threads.get(thread.wrapping_sub(1)) else {
+            return vec![];
+        };
+        t.frames
+            .iter()
+            .rev()
+            .map(|frame| {
+                let (file, line, column) = match frame.location {
+                    Some((src, line, column)) => (
+                        state.sources.get(&src).and_then(|s| s.file.clone()),
+                        line,
+                        column,
+                    ),
+                    None => (None, 0, 0),
+                };
+                StackFrame {
+                    name: frame.name.clone(),
+                    file,
+                    line,
+                    column,
+                }
+            })
+            .collect()
+    }
+    /// The variables in a frame of a stopped thread.
+    pub fn variables(&self, thread: usize, frame: usize) -> Vec<Variable> {
+        let state = self.state();
+        let (Some(t), Some((object_fields, object_fields_rev))) = (
+            state.threads.get(thread.wrapping_sub(1)),
+            &state.object_fields,
+        ) else {
+            return vec![];
+        };
+        let Some(frame) = t.frames.iter().rev().nth(frame) else {
+            return vec![];
+        };
+        let info = DisplayInfo {
+            object_fields,
+            object_fields_rev,
+        };
+        frame
+            .vars
+            .iter()
+            .map(|(name, var)| {
+                // the paused thread may be holding a lock, so we must not block here
+                let (value, value_type) = var
+                    .try_read()
+                    .ok()
+                    .and_then(|v| {
+                        let v = v.data.try_read().ok()?;
+                        Some((
+                            v.with_display(&info).to_string(),
+                            v.as_type().with_display(&info).to_string(),
+                        ))
+                    })
+                    .unwrap_or_else(|| ("<locked>".to_owned(), "<locked>".to_owned()));
+                Variable {
+                    name: name.clone(),
+                    value,
+                    value_type,
+                }
+            })
+            .collect()
+    }
+    /// Continues a stopped thread.
+    pub fn resume(&self, thread: usize, how: Resume) {
+        let mut state = self.state();
+        if let Some(t) = state.threads.get_mut(thread.wrapping_sub(1)) {
+            let depth = t.frames.len();
+            t.mode = match how {
+                Resume::Continue => Mode::Continue,
+                Resume::StepIn => Mode::StepIn,
+                Resume::StepOver => Mode::StepOver(depth),
+                Resume::StepOut => Mode::StepOut(depth),
+            };
+            t.paused = false;
+        }
+        self.resumed.notify_all();
+    }
+    /// Stops a running thread at the next statement it runs.
+    pub fn pause(&self, thread: usize) {
+        if let Some(t) = self.state().threads.get_mut(thread.wrapping_sub(1)) {
+            t.mode = Mode::Pause;
+        }
+    }
+    /// Makes every thread stop with an error the next time it runs a statement.
+    pub fn terminate(&self) {
+        self.state().terminated = true;
+        self.resumed.notify_all();
+    }
+
+    fn state(&self) -> MutexGuard<'_, DebuggerState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Called by `parsed::MersStatement::compile` to remember which variables are visible at this statement.
+    pub(crate) fn record_names(&self, pos: &SourceRange, info: &parsed::Info) {
+        let key = (Arc::as_ptr(pos.in_file()) as usize, pos.start().pos());
+        let mut state = self.state();
+        if state.var_names.contains_key(&key) {
+            return;
+        }
+        let mut names: Vec<(String, (usize, usize))> = vec![];
+        // scope 0 contains the variables added by the `Config`, which are not interesting here
+        for scope in info.scopes.iter().skip(1).rev() {
+            for (name, id) in &scope.vars {
+                if !names.iter().any(|(n, _)| n == name) {
+                    names.push((name.clone(), *id));
+                }
+            }
+        }
+        names.sort_by_key(|(_, id)| *id);
+        state.var_names.insert(key, Arc::new(names));
+    }
+    /// Called by `run::MersStatement::run` before running any statement.
+    /// If this statement is on a new line and should be stopped at, this blocks until the thread is resumed.
+    pub(crate) fn statement(&self, pos: &SourceRange, info: &Info) -> Result<(), CheckError> {
+        let mut state = self.state();
+        if state.terminated {
+            return Err(terminated_error());
+        }
+        if state.object_fields.is_none() {
+            state.object_fields = Some((
+                Arc::clone(&info.global.object_fields),
+                Arc::clone(&info.global.object_fields_rev),
+            ));
+        }
+        let (src, line, column) = state.locate(pos);
+        let breakpoint = state
+            .sources
+            .get(&src)
+            .and_then(|s| s.file.as_ref())
+            .and_then(|file| state.breakpoints.get(file))
+            .is_some_and(|lines| lines.contains(&line));
+        let ti = state.thread_index();
+        let thread = &mut state.threads[ti];
+        let depth = thread.frames.len();
+        let frame = thread.frames.last_mut().unwrap();
+        let new_line = frame.location.is_none_or(|(s, l, _)| (s, l) != (src, line));
+        frame.location = Some((src, line, column));
+        let reason = match thread.mode {
+            Mode::Pause => Some(StopReason::Pause),
+            _ if !new_line => None,
+            Mode::Entry => Some(StopReason::Entry),
+            _ if breakpoint => Some(StopReason::Breakpoint),
+            Mode::StepIn => Some(StopReason::Step),
+            Mode::StepOver(d) if depth <= d => Some(StopReason::Step),
+            Mode::StepOut(d) if depth < d => Some(StopReason::Step),
+            Mode::Continue | Mode::StepOver(_) | Mode::StepOut(_) => None,
+        };
+        if let Some(reason) = reason {
+            let vars = state.visible_vars(pos, info);
+            let thread = &mut state.threads[ti];
+            thread.frames.last_mut().unwrap().vars = vars;
+            thread.mode = Mode::Continue;
+            thread.paused = true;
+            _ = self.events.lock().unwrap().send(DebugEvent::Stopped {
+                thread: ti + 1,
+                reason,
+            });
+            while !state.terminated && state.threads[ti].paused {
+                state = self.resumed.wait(state).unwrap();
+            }
+            if state.terminated {
+                return Err(terminated_error());
+            }
+        }
+        Ok(())
+    }
+    /// Called by `run::chain::run` before calling a function.
+    pub(crate) fn enter_call(&self, pos: &SourceRange, func_pos: &SourceRange, info: &Info) {
+        let mut state = self.state();
+        let ti = state.thread_index();
+        let vars = state.visible_vars(pos, info);
+        let src = func_pos.in_file().src();
+        let name = src
+            .get(func_pos.start().pos()..func_pos.end().pos())
+            .unwrap_or("")
+            .trim();
+        let name = if name.is_empty() {
+            "<function>".to_owned()
+        } else if name.chars().count() > 40 {
+            format!("{}...", name.chars().take(37).collect::<String>())
+        } else {
+            name.to_owned()
+        };
+        let thread = &mut state.threads[ti];
+        thread.frames.last_mut().unwrap().vars = vars;
+        thread.frames.push(Frame {
+            name,
+            location: None,
+            vars: vec![],
+        });
+    }
+    /// Called by `run::chain::run` after a function returned.
+    pub(crate) fn exit_call(&self) {
+        let mut state = self.state();
+        let ti = state.thread_index();
+        let frames = &mut state.threads[ti].frames;
+        if frames.len() > 1 {
+            frames.pop();
+        }
+    }
+}
+
+impl DebuggerState {
+    fn thread_index(&mut self) -> usize {
+        let id = std::thread::current().id();
+        if let Some(i) = self.threads.iter().position(|t| t.id == id) {
+            return i;
+        }
+        let mode = if self.threads.is_empty() && self.stop_on_entry {
+            Mode::Entry
+        } else {
+            Mode::Continue
+        };
+        self.threads.push(DebugThread {
+            id,
+            mode,
+            paused: false,
+            frames: vec![Frame {
+                name: if self.threads.is_empty() {
+                    "<main>".to_owned()
+                } else {
+                    "<thread>".to_owned()
+                },
+                location: None,
+                vars: vec![],
+            }],
+        });
+        self.threads.len() - 1
+    }
+    /// (source ptr, line, column)
+    fn locate(&mut self, pos: &SourceRange) -> (usize, usize, usize) {
+        let source = pos.in_file();
+        let ptr = Arc::as_ptr(source) as usize;
+        let src = self.sources.entry(ptr).or_insert_with(|| DebugSource {
+            _source: Arc::clone(source),
+            file: match source.src_from() {
+                SourceFrom::File(path) => {
+                    Some(path.canonicalize().unwrap_or_else(|_| path.clone()))
+                }
+                SourceFrom::Unspecified => None,
+            },
+            line_starts: std::iter::once(0)
+                .chain(source.src_og().match_indices('\n').map(|(i, _)| i + 1))
+                .collect(),
+        });
+        let pos = source.pos_in_og(pos.start().pos(), true);
+        let line = src.line_starts.partition_point(|start| *start <= pos);
+        (ptr, line, pos - src.line_starts[line - 1] + 1)
+    }
+    fn visible_vars(&self, pos: &SourceRange, info: &Info) -> Vec<(String, Arc<RwLock<Data>>)> {
+        let key = (Arc::as_ptr(pos.in_file()) as usize, pos.start().pos());
+        let Some(names) = self.var_names.get(&key) else {
+            return vec![];
+        };
+        names
+            .iter()
+            .filter_map(|(name, (scope, var))| {
+                Some((
+                    name.clone(),
+                    Arc::clone(info.scopes.get(*scope)?.vars.get(*var)?),
+                ))
+            })
+            .collect()
+    }
+}
+
+fn terminated_error() -> CheckError {
+    CheckError::new().msg_str("program was terminated by the debugger".to_owned())
+}
+
+impl Debug for Debugger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Debugger")
+    }
+}
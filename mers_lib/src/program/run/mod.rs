@@ -2,14 +2,16 @@ use std::{
     collections::HashMap,
     fmt::Debug,
     io::{Read, Write},
+    num::NonZeroUsize,
     sync::{atomic::AtomicBool, Arc, Mutex, RwLock},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    data::{self, Data, Type},
+    data::{self, Data, MersData, MersDataWInfo, Type},
     errors::{CheckError, EColor, SourceRange},
     info::{self, DisplayInfo},
+    program::configs::with_multithreading::{Channel, ChannelT},
 };
 
 #[cfg(feature = "run")]
@@ -23,6 +25,8 @@ pub mod chain;
 #[cfg(feature = "run")]
 pub mod custom_type;
 #[cfg(feature = "run")]
+pub mod debugger;
+#[cfg(feature = "run")]
 pub mod field;
 #[cfg(feature = "run")]
 pub mod field_chain;
@@ -58,6 +62,10 @@ pub trait MersStatement: Debug + Send + Sync {
             info.create_scope();
         }
         let o = self.check_custom(info, init_to);
+        if let (Some(checked_types), Ok(t), None) = (&info.global.checked_types, &o, init_to) {
+            // assignment targets (`init_to.is_some()`) return references to values which aren't assigned yet, so they are never verified
+            checked_types.add(self, t);
+        }
         if info.global.enable_hooks {
             // Hooks - keep in sync with run/mod.rs/compile() hooks section
             'hook_save_info_at: {
@@ -110,6 +118,12 @@ pub trait MersStatement: Debug + Send + Sync {
                     )]));
             }
         }
+        if let Some(debugger) = &info.global.debugger {
+            // a block doesn't do anything itself, so stop at its inner statements instead
+            if !self.as_any().is::<block::Block>() {
+                debugger.statement(&self.source_range(), info)?;
+            }
+        }
         if self.has_scope() {
             info.create_scope();
         }
@@ -117,6 +131,9 @@ pub trait MersStatement: Debug + Send + Sync {
         if self.has_scope() {
             info.end_scope();
         }
+        if let (Some(checked_types), Ok(data)) = (&info.global.verify_types, &o) {
+            checked_types.verify(self, data, info)?;
+        }
         o
     }
     fn source_range(&self) -> SourceRange;
@@ -131,6 +148,106 @@ pub type CheckInfo = info::Info<CheckLocal>;
 pub struct RunLocal {
     pub vars: Vec<Arc<RwLock<Data>>>,
 }
+/// The types `check` computed for each statement, so they can be compared to the values produced at runtime.
+/// Statements are identified by their address, so the program must not be moved or recompiled between checking and running.
+#[derive(Clone, Default, Debug)]
+pub struct CheckedTypes(Arc<Mutex<HashMap<usize, Type>>>);
+impl CheckedTypes {
+    fn key<S: MersStatement + ?Sized>(statement: &S) -> usize {
+        statement as *const S as *const () as usize
+    }
+    fn add<S: MersStatement + ?Sized>(&self, statement: &S, t: &Type) {
+        // statements in functions are checked once for every argument type, so we combine all their output types
+        self.0
+            .lock()
+            .unwrap()
+            .entry(Self::key(statement))
+            .or_insert_with(Type::empty)
+            .add_all(t);
+    }
+    /// the type of a reference's value is more specific than the reference's type
+    /// (a `&List<Int>` may currently contain only `Int<1>`s), so for references and channels
+    /// (and tuples containing them), only compare what they contain.
+    fn value_included(data: &dyn MersData, data_type: &Type, checked_type: &Type) -> bool {
+        if let Some(r) = data.as_any().downcast_ref::<data::reference::Reference>() {
+            if let Some(checked_inner) = checked_type.dereference() {
+                let inner = r.read();
+                let inner = inner.get();
+                return Self::value_included(&**inner, &inner.as_type(), &checked_inner);
+            }
+        } else if let Some(c) = data.as_any().downcast_ref::<Channel>() {
+            // like references, channels only know the types of the values which were in them
+            let elem = c.elem_type();
+            return checked_type.types.iter().any(|checked| {
+                checked
+                    .as_any()
+                    .downcast_ref::<ChannelT>()
+                    .is_some_and(|checked| elem.is_included_in(&checked.0))
+            });
+        } else if let Some(t) = data.as_any().downcast_ref::<data::tuple::Tuple>() {
+            if data_type.is_included_in(checked_type) {
+                return true;
+            }
+            return checked_type.types.iter().any(|checked| {
+                checked
+                    .as_any()
+                    .downcast_ref::<data::tuple::TupleT>()
+                    .is_some_and(|checked| {
+                        checked.0.len() == t.0.len()
+                            && t.0.iter().zip(checked.0.iter()).all(|(v, checked)| {
+                                let v = v.read();
+                                let v = v.get();
+                                Self::value_included(&**v, &v.as_type(), checked)
+                            })
+                    })
+            });
+        }
+        data_type.is_included_in(checked_type)
+    }
+    fn verify<S: MersStatement + ?Sized>(
+        &self,
+        statement: &S,
+        data: &Data,
+        info: &Info,
+    ) -> Result<(), CheckError> {
+        // don't hold the lock while comparing types: function types may call `check`, which would then call `add`.
+        let Some(checked_type) = self.0.lock().unwrap().get(&Self::key(statement)).cloned() else {
+            return Ok(());
+        };
+        let data = data.get();
+        let data_type = data.as_type();
+        if Self::value_included(&**data, &data_type, &checked_type) {
+            Ok(())
+        } else {
+            Err(CheckError::new()
+                .src(vec![(
+                    statement.source_range(),
+                    Some(EColor::TypeVerificationFailed),
+                )])
+                .msg(vec![
+                    (
+                        "type verification failed: this statement returned ".to_owned(),
+                        None,
+                    ),
+                    (
+                        data.with_info(info).to_string(),
+                        Some(EColor::TypeVerificationFailed),
+                    ),
+                    (" of type ".to_owned(), None),
+                    (
+                        data_type.with_info(info).to_string(),
+                        Some(EColor::TypeVerificationFailed),
+                    ),
+                    (
+                        ", which isn't included in its checked type ".to_owned(),
+                        None,
+                    ),
+                    (checked_type.with_info(info).to_string(), None),
+                ]))
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct RunLocalGlobalInfo {
     /// if set, if `Instant::now()` is equal to or after the set `Instant`, stop the program with an error.
@@ -140,6 +257,25 @@ pub struct RunLocalGlobalInfo {
     pub stdin: Arc<Mutex<Option<Box<dyn Read + Send + Sync>>>>,
     pub stdout: Arc<Mutex<Option<(Box<dyn Write + Send + Sync>, Box<dyn Write + Send + Sync>)>>>,
     pub allow_process_exit_via_exit: Arc<AtomicBool>,
+    /// if set, the debugger is informed about every statement and function call and may pause the program.
+    pub debugger: Option<Arc<debugger::Debugger>>,
+    /// if set, after every statement, verify that the type of the value it returned is included in the type `check` computed for it.
+    /// To fill this, check the program with the same `CheckedTypes` in `CheckLocalGlobalInfo::checked_types`.
+    pub verify_types: Option<CheckedTypes>,
+    /// if set, the functions from `with_env` use this instead of the real environment of the process.
+    pub env: Arc<Mutex<Option<crate::program::configs::with_env::VirtualEnv>>>,
+    /// the maximum number of threads `par_map` and `par_for_each` use. if `None`, uses `std::thread::available_parallelism()`.
+    pub parallelism: Option<NonZeroUsize>,
+    /// the maximum size of a request or response body (in bytes) the functions from `with_http` read
+    pub http_max_body_size: usize,
+    /// the read and write timeout for connections accepted by `http_serve`
+    pub http_serve_timeout: Duration,
+    /// the minimum level a log record (from `with_log`) must have to be emitted
+    pub log_level: Arc<Mutex<crate::program::configs::with_log::LogLevel>>,
+    /// if set, log records are passed to this function instead of being printed to stderr
+    pub log_sink: Arc<Mutex<Option<crate::program::configs::with_log::LogSink>>>,
+    /// the chain statement which called the function that is currently running, if any
+    pub call_site: Option<SourceRange>,
 }
 #[derive(Debug)]
 #[allow(unused)]
@@ -150,6 +286,15 @@ struct RunLocalGlobalInfoDebug<'a> {
     pub stdin: bool,
     pub stdout: bool,
     pub allow_process_exit_via_exit: bool,
+    pub debugger: bool,
+    pub verify_types: bool,
+    pub env: bool,
+    pub parallelism: &'a Option<NonZeroUsize>,
+    pub http_max_body_size: usize,
+    pub http_serve_timeout: Duration,
+    pub log_level: crate::program::configs::with_log::LogLevel,
+    pub log_sink: bool,
+    pub call_site: &'a Option<SourceRange>,
 }
 impl Debug for RunLocalGlobalInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -165,6 +310,15 @@ impl Debug for RunLocalGlobalInfo {
                 allow_process_exit_via_exit: self
                     .allow_process_exit_via_exit
                     .load(std::sync::atomic::Ordering::Relaxed),
+                debugger: self.debugger.is_some(),
+                verify_types: self.verify_types.is_some(),
+                env: self.env.lock().unwrap().is_some(),
+                parallelism: &self.parallelism,
+                http_max_body_size: self.http_max_body_size,
+                http_serve_timeout: self.http_serve_timeout,
+                log_level: *self.log_level.lock().unwrap(),
+                log_sink: self.log_sink.lock().unwrap().is_some(),
+                call_site: &self.call_site,
             }
         )
     }
@@ -178,6 +332,15 @@ impl RunLocalGlobalInfo {
             stdin: Arc::new(Mutex::new(None)),
             stdout: Arc::new(Mutex::new(None)),
             allow_process_exit_via_exit: Arc::new(AtomicBool::new(true)),
+            debugger: None,
+            verify_types: None,
+            env: Arc::new(Mutex::new(None)),
+            parallelism: None,
+            http_max_body_size: crate::program::configs::with_http::DEFAULT_MAX_BODY_SIZE,
+            http_serve_timeout: crate::program::configs::with_http::DEFAULT_SERVE_TIMEOUT,
+            log_level: Default::default(),
+            log_sink: Default::default(),
+            call_site: None,
         }
     }
 }
@@ -210,6 +373,8 @@ pub struct CheckLocalGlobalInfo {
         >,
     >,
     pub unused_try_statements: Arc<Mutex<Vec<(SourceRange, Vec<Option<SourceRange>>)>>>,
+    /// if set, the output type of every statement is saved here (see `RunLocalGlobalInfo::verify_types`).
+    pub checked_types: Option<CheckedTypes>,
     pub object_fields: Arc<Mutex<HashMap<String, usize>>>,
     pub object_fields_rev: Arc<Mutex<Vec<String>>>,
 }
@@ -231,6 +396,7 @@ impl CheckLocalGlobalInfo {
             show_warnings: None,
             save_info_at: Default::default(),
             unused_try_statements: Default::default(),
+            checked_types: None,
             object_fields,
             object_fields_rev: Default::default(),
         }
@@ -258,6 +424,15 @@ impl info::Local for RunLocal {
             stdin: Default::default(),
             stdout: Default::default(),
             allow_process_exit_via_exit: Arc::new(AtomicBool::new(false)),
+            debugger: None,
+            verify_types: None,
+            env: Default::default(),
+            parallelism: None,
+            http_max_body_size: crate::program::configs::with_http::DEFAULT_MAX_BODY_SIZE,
+            http_serve_timeout: crate::program::configs::with_http::DEFAULT_SERVE_TIMEOUT,
+            log_level: Default::default(),
+            log_sink: Default::default(),
+            call_site: None,
         }
     }
     fn init_var(&mut self, id: Self::VariableIdentifier, value: Self::VariableData) {
@@ -306,6 +481,7 @@ impl info::Local for CheckLocal {
             show_warnings: None,
             save_info_at: Default::default(),
             unused_try_statements: Default::default(),
+            checked_types: None,
             object_fields: Default::default(),
             object_fields_rev: Default::default(),
         }
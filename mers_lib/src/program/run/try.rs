@@ -120,18 +120,23 @@ impl MersStatement for Try {
     }
     fn run_custom(&self, info: &mut Info) -> Result<Data, CheckError> {
         let arg = self.arg.run(info)?;
-        let ar = arg.get();
-        let a = ar.as_ref();
-        let arg_type = a.as_type();
-        for func in self.funcs.iter() {
-            let func = func.run(info)?;
-            let func = func.get();
-            match func.executable().map(|func| func.o(&arg_type)) {
-                Some(Ok(_)) => {
-                    drop(ar);
-                    return func.execute(arg, &info.global).unwrap();
-                }
-                None | Some(Err(_)) => (),
+        let arg_type = arg.get().as_type();
+        for func_statement in self.funcs.iter() {
+            let func = func_statement.run(info)?;
+            let matches = matches!(
+                func.get().executable().map(|func| func.o(&arg_type)),
+                Some(Ok(_))
+            );
+            if matches {
+                return super::chain::run(
+                    arg,
+                    func,
+                    info,
+                    self.pos_in_src.clone(),
+                    self.arg.source_range(),
+                    func_statement.source_range(),
+                    None,
+                );
             }
         }
         panic!("try: no function found")
@@ -4,7 +4,11 @@ use mers_lib::data::MersDataWInfo;
 use mers_lib::prelude_compile::*;
 
 use mers_lib::{
-    data::{self, Data, Type},
+    data::{
+        self,
+        int::{INT_MAX, INT_MIN},
+        Data, Type,
+    },
     errors::CheckError,
 };
 
@@ -63,6 +67,807 @@ fn identity_function() -> Res {
     Ok(())
 }
 
+#[test]
+fn runtime_error_stack_trace() {
+    let err = run_code(
+        Config::new().bundle_std(),
+        "f := a -> a.panic\ng := b -> b.try(s -> s.f)\n\"bad\".g",
+    )
+    .expect_err("panic should cause a runtime error");
+    let trace = err
+        .stack_trace()
+        .iter()
+        .map(|r| r.in_file().src()[r.start().pos()..r.end().pos()].to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        trace,
+        vec!["a.panic", "s.f", "b.try(s -> s.f)", "\"bad\".g"]
+    );
+}
+
+#[test]
+fn runtime_error_stack_trace_through_builtins() {
+    // functions called by builtins get an entry for the call of the builtin, even if the function is called later
+    for (code, expected) in [
+        (
+            "m := (\"x\", \"y\").map(v -> v.panic)\nm.as_list",
+            vec!["v.panic", "(\"x\", \"y\").map(v -> v.panic)", "m.as_list"],
+        ),
+        (
+            "t := {() -> \"x\".panic}.thread\nt.thread_await",
+            vec![
+                "\"x\".panic",
+                "{() -> \"x\".panic}.thread",
+                "t.thread_await",
+            ],
+        ),
+        (
+            "(\"x\", \"y\").for_each(v -> v.panic)",
+            vec![
+                "v.panic",
+                "(\"x\", \"y\").for_each(v -> v.panic)",
+                "(\"x\", \"y\").for_each(v -> v.panic)",
+            ],
+        ),
+        (
+            "((\"x\", \"y\"), v -> v.panic).par_map",
+            vec![
+                "v.panic",
+                "((\"x\", \"y\"), v -> v.panic).par_map",
+                "((\"x\", \"y\"), v -> v.panic).par_map",
+            ],
+        ),
+    ] {
+        let err = run_code(Config::new().bundle_std(), code)
+            .expect_err("panic should cause a runtime error");
+        let trace = err
+            .stack_trace()
+            .iter()
+            .map(|r| r.in_file().src()[r.start().pos()..r.end().pos()].to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(trace, expected, "{code}");
+    }
+}
+
+#[test]
+fn runtime_error_in_block_stops_block() {
+    let out = run_code(
+        Config::new().bundle_std(),
+        "x := 1, { \"bad\".panic, &x = 2 }, x",
+    );
+    assert!(out.is_err(), "panic in a block should stop the program");
+}
+
+#[test]
+fn verify_types() -> Res {
+    run_code_verified(
+        Config::new().bundle_std(),
+        "f := a -> (a, a)\nx := 5.f\nl := (1, 2, 3).as_list\n&l.push(3)\nm := (1, 2, 3).map(v -> (v, 2).mul)\nf4 := () -> 4\nt := f4.thread\n(x, l, m.as_list, t.thread_await, \"12\".parse_int)",
+    )?;
+    Ok(())
+}
+
+#[test]
+fn verify_types_mismatch() {
+    // claims to return an `Int`, but returns a `String`
+    let cfg = Config::new().add_var(
+        "lie",
+        data::function::Function::new_static(
+            vec![(
+                Type::empty_tuple(),
+                Type::new(data::int::IntT(INT_MIN, INT_MAX)),
+            )],
+            |_, _| Ok(Data::new(data::string::String("no".to_owned()))),
+        ),
+    );
+    let e = run_code_verified(cfg, "x := ().lie, x").unwrap_err();
+    assert!(
+        e.display_notheme()
+            .to_string()
+            .contains("type verification failed"),
+        "{}",
+        e.display_notheme()
+    );
+    // claims to return the generic function `a`, but returns a different function `b`
+    let a = data::function::Function::new_generic(
+        |_, _| Ok(Type::empty_tuple()),
+        |_, _| Ok(Data::empty_tuple()),
+    );
+    let b = data::function::Function::new_generic(
+        |_, _| Ok(Type::empty_tuple()),
+        |_, _| Ok(Data::empty_tuple()),
+    );
+    let cfg = Config::new().add_var(
+        "lie_func",
+        data::function::Function::new_static(
+            vec![(Type::empty_tuple(), Type::new(a.get_as_type()))],
+            move |_, _| Ok(Data::new(b.clone())),
+        ),
+    );
+    let e = run_code_verified(cfg, "f := ().lie_func, f").unwrap_err();
+    assert!(
+        e.display_notheme()
+            .to_string()
+            .contains("type verification failed"),
+        "{}",
+        e.display_notheme()
+    );
+}
+
+#[test]
+fn regex() -> Res {
+    assert_eq!(
+        run_code(
+            Config::new().bundle_std(),
+            "\"[0-9]+\".regex.try(r [Regex] -> (r, \"a1b22c\", \"#\").regex_replace, {regex_error: e} -> e)"
+        )?,
+        TypedData(
+            Type::new(data::string::StringT),
+            Data::new(data::string::String("a#b#c".to_owned())),
+            mers_lib::info::Info::neverused()
+        )
+    );
+    assert!(run_code(
+        Config::new().bundle_std(),
+        "\"(\".regex.try(r [Regex] -> \"\", {regex_error: e} -> e)"
+    )?
+    .1
+    .get()
+    .as_any()
+    .downcast_ref::<data::string::String>()
+    .is_some_and(|s| !s.0.is_empty()));
+    Ok(())
+}
+
+#[test]
+fn format() -> Res {
+    assert_eq!(
+        run_code(
+            Config::new().bundle_std(),
+            "(\"{}|{0:>4}|{1:*^5}|{2:06.2}\", (\"a\", \"b\", -1.5)).format"
+        )?,
+        TypedData(
+            Type::new(data::string::StringT),
+            Data::new(data::string::String("a|   a|**b**|-01.50".to_owned())),
+            mers_lib::info::Info::neverused()
+        )
+    );
+    assert_eq!(
+        run_code(
+            Config::new().bundle_std(),
+            "(\"{name:<4}|{{}}\", {name: \"xy\"}).format"
+        )?,
+        TypedData(
+            Type::new(data::string::StringT),
+            Data::new(data::string::String("xy  |{}".to_owned())),
+            mers_lib::info::Info::neverused()
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn format_checks_literal_templates() {
+    for code in [
+        "(\"{} {} {}\", (1, 2)).format",
+        "(\"{}\", (1, 2)).format",
+        "(\"{a} {b}\", {a: 1}).format",
+        "(\"{}\", {a: 1}).format",
+        "(\"{\", (1)).format",
+        "(\"{:99999999999999}\", (1)).format",
+        "(\"{:.99999999999}\", (1.5)).format",
+    ] {
+        assert!(
+            run_code(Config::new().bundle_std(), code).is_err(),
+            "{code} should not pass the checks"
+        );
+    }
+}
+
+#[test]
+fn time() -> Res {
+    assert_eq!(
+        run_code(
+            Config::new().bundle_std(),
+            "a := \"2024-05-01T12:30:00+02:00\".time_parse.try(t [Timestamp] -> t, e -> ().time_now)\nb := \"2024-05-01 10:00:00\".time_parse.try(t [Timestamp] -> t, e -> ().time_now)\n(a, b).time_sub.duration_millis"
+        )?,
+        TypedData(
+            Type::new(data::int::IntT(INT_MIN, INT_MAX)),
+            Data::new(data::int::Int(30 * 60 * 1000)),
+            mers_lib::info::Info::neverused()
+        )
+    );
+    assert_eq!(
+        run_code(
+            Config::new().bundle_std(),
+            "\"yesterday\".time_parse.try(t [Timestamp] -> false, {time_parse_error: e} -> true)"
+        )?,
+        TypedData(
+            data::bool::bool_type(),
+            Data::new(data::bool::Bool(true)),
+            mers_lib::info::Info::neverused()
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn seeded_rng() -> Res {
+    assert_eq!(
+        run_code(
+            Config::new().bundle_pure(),
+            "a := 7.rng_new, b := 7.rng_new, ((a, 1, 6).rng_int, (b, 1, 6).rng_int).eq"
+        )?,
+        TypedData(
+            data::bool::bool_type(),
+            Data::new(data::bool::Bool(true)),
+            mers_lib::info::Info::neverused()
+        )
+    );
+    let out = run_code(Config::new().bundle_pure(), "(7.rng_new, 1, 6).rng_int")?;
+    assert!(out.0.is_same_type_as(&Type::new(data::int::IntT(1, 6))));
+    Ok(())
+}
+
+#[test]
+fn virtual_env() -> Res {
+    let mut src = Source::new_from_string(
+        "(\"GREETING\", \"hi\").env_set\n\"/tmp\".set_current_dir\n(\"HOME\".env_get, \"GREETING\".env_get, ().current_dir, ().pid, ().env_vars.len)".to_owned(),
+    );
+    let srca = Arc::new(src.clone());
+    let parsed = parse(&mut src, &srca)?;
+    let (mut i1, mut i2, mut i3) = Config::new().bundle_std().infos();
+    let env = mers_lib::program::configs::with_env::VirtualEnv {
+        vars: [("HOME".to_owned(), "/home/mers".to_owned())].into(),
+        current_dir: "/".into(),
+        pid: 7,
+        hostname: None,
+    };
+    *i2.global.env.lock().unwrap() = Some(env);
+    let compiled = parsed.compile(&mut i1, Default::default())?;
+    compiled.check(&mut i3, Default::default())?;
+    let out = compiled.run(&mut i2)?;
+    assert_eq!(
+        out.get().with_info(&i2).to_string(),
+        "((/home/mers), (hi), /tmp, 7, 2)"
+    );
+    assert!(std::env::var("GREETING").is_err());
+    Ok(())
+}
+
+#[test]
+fn env_set_invalid() -> Res {
+    let out = run_code(
+        Config::new().bundle_std(),
+        "((\"A=B\", \"x\").env_set, (\"\", \"x\").env_set, \"\".env_remove)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "({env_set_error: the name of an environment variable can't contain `=` or NUL characters, but was \"A=B\"}, {env_set_error: the name of an environment variable can't be empty}, {env_remove_error: the name of an environment variable can't be empty})"
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn env_non_unicode() -> Res {
+    use std::os::unix::ffi::OsStrExt;
+    std::env::set_var(
+        "MERS_TEST_NON_UNICODE",
+        std::ffi::OsStr::from_bytes(b"a\xffb"),
+    );
+    let out = run_code(
+        Config::new().bundle_std(),
+        "(\"MERS_TEST_NON_UNICODE\".env_get, ().env_vars.filter(v -> { (k, v) := v, (k, \"MERS_TEST_NON_UNICODE\").eq }).as_list)",
+    );
+    std::env::remove_var("MERS_TEST_NON_UNICODE");
+    let out = out?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "((a\u{FFFD}b), [(MERS_TEST_NON_UNICODE, a\u{FFFD}b)])"
+    );
+    Ok(())
+}
+
+#[test]
+fn list_sort_and_search() -> Res {
+    let out = run_code(
+        Config::new().bundle_pure(),
+        "l := [List<Int>] (3, 1, 5, 3, 2).as_list\n&l.sort\n&l.dedup\n(&l, (9, 7)).extend\ns := (\"ccc\", \"a\", \"bb\").as_list\n(&s, v -> v.len).sort_by_key\n(l, (l, 5).binary_search, (l, 4).binary_search, (l, -2).slice, s)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "([1, 2, 3, 5, 9, 7], (3), (), [9, 7], [a, bb, ccc])"
+    );
+    assert!(run_code(
+        Config::new().bundle_pure(),
+        "l := (1, \"a\").as_list\n&l.sort"
+    )
+    .is_err());
+    // a comparator which isn't a total order doesn't abort the program
+    let out = run_code(
+        Config::new().bundle_pure(),
+        "r := 1.rng_new\nl := (1, 40).range_inc.as_list\n(&l, (a, b) -> (r, -1, 1).rng_int).sort_by\n(&l, v -> (r, 0, 9).rng_int).sort_by_key\n(l.len, l.sum)",
+    )?;
+    assert_eq!(out.1.get().with_info(&out.2).to_string(), "(40, 820)");
+    Ok(())
+}
+
+#[test]
+fn iter_adapters_and_consumers() -> Res {
+    let out = run_code(
+        Config::new().bundle_pure(),
+        "l := (1, 2, 3, 4, 5).as_list\n(((l, 1).skip, (\"a\", \"b\")).zip.collect, (l, 2).chunks.collect, (l, v -> (v, 4).lt).take_while.sum, (l, \"\", (a, v) -> (a, v).concat).fold, (l, v -> (v, 2).gt).position)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "([(2, a), (3, b)], [[1, 2], [3, 4], [5]], 6, 12345, (2))"
+    );
+    assert!(run_code(Config::new().bundle_pure(), "(1, \"a\").sum").is_err());
+    let out = run_code(
+        Config::new().bundle_pure(),
+        "l := (1, 2, 3).as_list\n((l, 9223372036854775807).chunks.collect, (l, 9223372036854775807).windows.collect)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "([[1, 2, 3]], [])"
+    );
+    Ok(())
+}
+
+#[test]
+fn channel() -> Res {
+    let out = run_code(
+        Config::new().bundle_std(),
+        "q := [List<Int>] ().as_list\nc := (q, 1).channel\nt := {() -> { (1, 2, 3).for_each(v -> { (c, v).send, () }), c.close }}.thread\n(c.recv, c.recv, c.recv, c.recv, c.try_recv, (c, 4).send)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "((1), (2), (3), (), (), false)"
+    );
+    assert!(run_code(
+        Config::new().bundle_std(),
+        "q := [List<Int>] ().as_list\n(q.channel, \"a\").send"
+    )
+    .is_err());
+    // an infinite timeout waits forever, NaN doesn't wait
+    let out = run_code(
+        Config::new().bundle_std(),
+        "q := [List<Int>] ().as_list\nc := q.channel\n(c, 5).send\n((c, (1.0, 0.0).div).recv_timeout, (c, (0.0, 0.0).div).recv_timeout)",
+    )?;
+    assert_eq!(out.1.get().with_info(&out.2).to_string(), "((5), ())");
+    // at runtime, a channel's type is the type of the values which were in it, so `try` can't pick the wrong branch
+    let out = run_code_verified(
+        Config::new().bundle_std(),
+        r#"a := ("a", "b").as_list.channel
+b := (1.5).as_list.channel
+n := [List<Int>] (1, 2).as_list
+n := n.channel
+c := if "x".len.eq(1) { a } else { b }
+(c.try(c [Channel<Float>] -> (c, 2.5).send, c [Channel<String>] -> (c, "c").send), a.recv, a.recv, a.recv, (n, 3).send, n.recv)"#,
+    )?;
+    assert_eq!(
+        out.get()
+            .with_info(&mers_lib::program::run::Info::neverused())
+            .to_string(),
+        "(true, (a), (b), (c), true, (1))"
+    );
+    Ok(())
+}
+
+#[test]
+fn par_map() -> Res {
+    let cfg = || {
+        Config::new()
+            .bundle_std()
+            .set_parallelism(2.try_into().unwrap())
+    };
+    let out = run_code(cfg(), "((1, 2, 3, 4, 5).as_list, v -> (v, v).mul).par_map")?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "[1, 4, 9, 16, 25]"
+    );
+    assert!(run_code(
+        cfg(),
+        "((1, 2, 3).as_list, v -> if (v, 2).eq (\"two\".panic) else v).par_for_each"
+    )
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn byte_encodings() -> Res {
+    let out = run_code(
+        Config::new().bundle_pure(),
+        "b := \"héllo\".string_to_bytes\n(b.hex_encode, b.base64_encode, \"aMOpbGxv\".base64_decode.try(b [List<Byte>] -> b.bytes_to_string, e -> e), \"+f\".hex_decode, (255b, 104b).bytes_to_string_lossy)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "(68c3a96c6c6f, aMOpbGxv, héllo, {hex_decode_error: invalid hex digits \"+f\"}, \u{fffd}h)"
+    );
+    Ok(())
+}
+
+#[test]
+fn unicode_strings() -> Res {
+    let out = run_code(
+        Config::new().bundle_pure(),
+        "s := \"héllo\"\n(s.len, s.char_len, (s, 1, 3).char_substring, (s, 0, 2).substring, s.to_upper, (\"7\", 3, \"0\").pad_left, s.str_reverse, s.to_codepoints.from_codepoints)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "(6, 5, él, h, HÉLLO, 007, olléh, (héllo))"
+    );
+    let out = run_code(
+        Config::new().bundle_pure(),
+        "((\"ab\", 3).repeat, (\"x\", 6, \"é-\").pad_left, (\"x\", 4, \"ab\").pad_right, (\"\", 9223372036854775807).repeat.len)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "(ababab, é-é-éx, xaba, 0)"
+    );
+    // results which would be too large are errors, not crashes
+    for code in [
+        "(\"ab\", 9223372036854775807).repeat",
+        "(\"ab\", 9223372036854775807).pad_left",
+        "(\"ab\", 9223372036854775807, \"xyz\").pad_right",
+    ] {
+        assert!(run_code(Config::new().bundle_pure(), code).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn math_and_bitwise() -> Res {
+    let out = run_code(
+        Config::new().bundle_base(),
+        "(16.sqrt, (8, 2).log, (12, 18).gcd, (4, -6).lcm, (12, 10).bit_xor, 5.bit_not, (1, 63).shift_left, (-16, 2).shift_right)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "(4, 3, 6, 12, 6, -6, (), -4)"
+    );
+    let out = run_code(
+        Config::new().bundle_base(),
+        "x := [Int<0..255>] 3\n(x, 15).bit_and",
+    )?;
+    assert!(out.0.is_same_type_as(&Type::new(data::int::IntT(0, 15))));
+    Ok(())
+}
+
+#[test]
+fn net_loopback() -> Res {
+    let out = run_code(
+        Config::new().with_net().bundle_pure(),
+        r#"
+l := "127.0.0.1:0".tcp_bind.try(l [TcpListener] -> l, e -> "net".panic)
+c := l.net_local_addr.try(a [String] -> a, e -> "net".panic).tcp_connect.try(c [TcpStream] -> c, e -> "net".panic)
+s := l.tcp_accept.try(s [TcpStream] -> s, e -> "net".panic)
+(c, "ping\n").tcp_write_string
+u := "127.0.0.1:0".udp_bind.try(u [UdpSocket] -> u, e -> "net".panic)
+(u, u.net_local_addr.try(a [String] -> a, e -> "net".panic), "pong".string_to_bytes).udp_send_to
+c.net_close
+(s.tcp_read_line, s.tcp_read_line, u.udp_recv.try(v [(List<Byte>, String)] -> { (b, a) := v, b.bytes_to_string }, e -> e), c.tcp_read_line)
+"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "((ping\n), (), pong, {net_error: TcpStream was closed})"
+    );
+    Ok(())
+}
+
+#[test]
+fn net_read_does_not_block_write() -> Res {
+    let out = run_code(
+        Config::new().with_net().with_multithreading().bundle_pure(),
+        r#"
+l := "127.0.0.1:0".tcp_bind.try(l [TcpListener] -> l, e -> "net".panic)
+c := l.net_local_addr.try(a [String] -> a, e -> "net".panic).tcp_connect.try(c [TcpStream] -> c, e -> "net".panic)
+s := l.tcp_accept.try(s [TcpStream] -> s, e -> "net".panic)
+r := {() -> s.tcp_read_line}.thread
+(s, "hello\n").tcp_write_string
+h := c.tcp_read_line
+(c, "reply\n").tcp_write_string
+w := {() -> c.tcp_read_line.try(l [(String)] -> "line", e -> "closed")}.thread
+c.net_close
+(h, r.thread_await, w.thread_await)
+"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "((hello\n), (reply\n), closed)"
+    );
+    Ok(())
+}
+
+#[test]
+fn http_request_and_serve() -> Res {
+    let out = run_code(
+        Config::new().bundle_std(),
+        r#"
+l := "127.0.0.1:0".tcp_bind.try(l [TcpListener] -> l, e -> "bind".panic)
+url := ("http:\//", l.net_local_addr.try(a [String] -> a, e -> "addr".panic), "/echo").concat
+server := {() -> (l, req -> {
+  {method: method, path: path, body: body} := req
+  l.net_close
+  {status: 201, body: (method, " ", path, " ", body).concat}
+}).http_serve}.thread
+res := ("POST", url, (("X-A", "b")), "data").http_request.try(r [{status: Int, headers: List<(String, String)>, body: String}] -> {
+  {status: status, body: body} := r
+  (status, body)
+}, e -> e)
+(res, server.thread_await)
+"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "((201, POST /echo data), ())"
+    );
+    Ok(())
+}
+
+#[test]
+fn http_limits_and_invalid_headers() -> Res {
+    let out = run_code(
+        Config::new()
+            .bundle_std()
+            .set_http_max_body_size(8)
+            .set_http_serve_timeout(std::time::Duration::from_millis(100)),
+        r#"
+l := "127.0.0.1:0".tcp_bind.try(l [TcpListener] -> l, e -> "bind".panic)
+addr := l.net_local_addr.try(a [String] -> a, e -> "addr".panic)
+server := {() -> (l, req -> {
+  {path: path} := req
+  if (path, "/status").eq {
+    {status: 1000, body: "ok"}
+  } else {
+    l.net_close
+    {status: 200, headers: (("X-A", "a\r\nX-B: b")), body: "ok"}
+  }
+}).http_serve}.thread
+raw := req -> {
+  c := addr.tcp_connect.try(c [TcpStream] -> c, e -> "connect".panic)
+  (c, req).tcp_write_string
+  c.tcp_read_line
+}
+status := r -> r.try(r [{status: Int, headers: List<(String, String)>, body: String}] -> {
+  {status: status} := r
+  status
+}, e -> e)
+url := ("http:\//", addr, "/").concat
+(
+  "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffff\r\n".raw,
+  "POST / HTTP/1.1\r\nContent-Length: 100\r\n\r\n".raw,
+  "GET / HTTP/1.1\r\n".raw,
+  ("GET / HTTP/1.1\r\nX: ", ("a", 65517).repeat).concat.raw,
+  "GET /status HTTP/1.1\r\n\r\n".raw,
+  ("GET", url, (("X-A", "a\r\nX-B: b")), "").http_request.status,
+  ("GET", url, (), "").http_request.status,
+  server.thread_await,
+)
+"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "((HTTP/1.1 413 Payload Too Large\r\n), (HTTP/1.1 413 Payload Too Large\r\n), (HTTP/1.1 408 Request Timeout\r\n), (HTTP/1.1 431 Request Header Fields Too Large\r\n), (HTTP/1.1 500 Internal Server Error\r\n), {http_error: the value of the header X-A contains a line break or NUL character}, {http_error: the body is larger than the limit of 8 bytes}, ())"
+    );
+    Ok(())
+}
+
+#[test]
+fn command_options() -> Res {
+    let out = run_code(
+        Config::new().bundle_std(),
+        r#"
+p := ("printf", ("a\nb\n")).spawn_command.try(p [ChildProcess] -> p, e -> "spawn".panic)
+big := ("head", ("-c", "1000000"), {stdin: ("x", 1000000).repeat}).spawn_command.try(p [ChildProcess] -> p, e -> "spawn".panic)
+out := big.childproc_read_all
+(
+  ("sh", ("-c", "pwd; echo $FOO >&2"), {cwd: "/", env: (("FOO", "bar")), stderr: "merge"}).run_command,
+  ("cat", (), {stdin: p}).run_command,
+  ("sleep", ("5"), {timeout: 0.1}).run_command,
+  out.len,
+)
+"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "((0, /\nbar\n, ), (0, a\nb\n, ), {run_command_error: command timed out after 100ms}, 1000000)"
+    );
+    Ok(())
+}
+
+#[test]
+fn file_lines_and_chunks() -> Res {
+    let path = std::env::temp_dir().join("mers_test_file_lines_and_chunks.txt");
+    std::fs::write(&path, "one\ntwo\r\nthree\n").unwrap();
+    let out = run_code(
+        Config::new().bundle_std(),
+        format!(
+            "f := {:?}.fs_open.try(f [File] -> f, e -> \"open\".panic)\nl := f.fs_lines\n(l.take(1).as_list, l.map(l -> l.len).as_list, (f, 2).fs_chunks.as_list)",
+            path.to_string_lossy()
+        ),
+    )?;
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "([one], [3, 5], [])"
+    );
+    Ok(())
+}
+
+#[test]
+fn path_functions() -> Res {
+    let out = run_code(
+        Config::new().bundle_std(),
+        "((\"a/b\", \"c.txt\").path_join.path_extension, \"a/b\".path_parent, (\"x.tar\", \"gz\").path_with_extension, (\"x.tar\", \"a/gz\").path_with_extension, \"a/b\".path_components, #source_file)",
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "((txt), (a), (x.gz), (), [a, b], ())"
+    );
+    Ok(())
+}
+
+#[test]
+fn source_file_and_dir() -> Res {
+    let path = std::env::temp_dir().join(format!("mers-source-{}.mers", std::process::id()));
+    std::fs::write(&path, "(#source_file, #source_dir)").unwrap();
+    let src = Source::new_from_file(path.clone());
+    std::fs::remove_file(&path).unwrap();
+    let mut src = src.unwrap();
+    let srca = Arc::new(src.clone());
+    let parsed = parse(&mut src, &srca)?;
+    let (mut i1, mut i2, mut i3) = Config::new().infos();
+    let compiled = parsed.compile(&mut i1, Default::default())?;
+    compiled.check(&mut i3, Default::default())?;
+    let out = compiled.run(&mut i2)?;
+    let path = std::path::absolute(&path).unwrap();
+    assert_eq!(
+        out.get().with_info(&i2).to_string(),
+        format!("({}, {})", path.display(), path.parent().unwrap().display())
+    );
+    Ok(())
+}
+
+#[test]
+fn csv() -> Res {
+    let out = run_code(
+        Config::new().bundle_pure(),
+        r#"text := "a,b\n1,\"x, \"\"y\"\"\"\n"
+(text.csv_parse, (text, {b: ""}).csv_parse_objects, "a,\"b".csv_rows.as_list, (("1", "a,b"),).csv_write)"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        r#"([[a, b], [1, x, "y"]], [{b: x, "y"}], [{csv_error: quoted field is never closed, line: 1}], 1,"a,b"
+)"#
+    );
+    let out = run_code(
+        Config::new().bundle_pure(),
+        r#"(("a;b", ";;").csv_parse, ("a", {a: ""}, "").csv_parse_objects, ("a;b", "\"").csv_rows, ((("1", "2"),), "\n").csv_write, ("a;b", ";").csv_parse)"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        r#"({csv_error: csv delimiter must be a single character (and not a quote or line break), but got ";;", line: 1}, {csv_error: csv delimiter must be a single character (and not a quote or line break), but got "", line: 1}, {csv_error: csv delimiter must be a single character (and not a quote or line break), but got "\"", line: 1}, {csv_error: csv delimiter must be a single character (and not a quote or line break), but got "\n", line: 1}, [[a, b]])"#
+    );
+    Ok(())
+}
+
+#[test]
+fn toml() -> Res {
+    let out = run_code(
+        Config::new().bundle_pure(),
+        r#"text := "name = \"a\"\n[server]\nport = 80\n"
+(
+  (text, c [{name: String, server: {port: Int<0..65535>, debug: Bool/()}}] -> c).toml_parse,
+  (text, c [{name: Int}] -> c).toml_parse,
+  {a: 1, b: {c: "x"}, d: ()}.toml_encode,
+)"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "({name: a, server: {port: 80, debug: ()}}, {toml_error: `name`: expected Int, found string}, a = 1\n\n[b]\nc = \"x\"\n)"
+    );
+    assert!(run_code(Config::new().bundle_pure(), "{a: ((), 1)}.toml_encode").is_err());
+    assert!(run_code(Config::new().bundle_pure(), "{a: [()]}.toml_encode").is_err());
+    Ok(())
+}
+
+#[test]
+fn hashing() -> Res {
+    let out = run_code(
+        Config::new().bundle_pure(),
+        r#"("abc".hash_sha256, "abc".string_to_bytes.hash_md5, "abc".crc32, "a".hash_fnv1a_bytes)"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        "(ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad, 900150983cd24fb0d6963f7d28e17f72, 352441c2, [175, 99, 220, 76, 134, 1, 236, 140])"
+    );
+    Ok(())
+}
+
+#[test]
+fn log_sink() -> Res {
+    let records = Arc::new(std::sync::Mutex::new(vec![]));
+    let sink = Arc::clone(&records);
+    run_code(
+        Config::new().with_log().set_log_sink(move |r| {
+            sink.lock().unwrap().push((
+                r.level.to_string(),
+                r.line_and_column().unwrap().0,
+                r.message,
+            ))
+        }),
+        "\"a\".log_debug\n\"b\".log_info\n\"debug\".log_set_level\n\n(1, 2).log_debug",
+    )?;
+    assert_eq!(
+        *records.lock().unwrap(),
+        vec![
+            ("info".to_owned(), 2, "b".to_owned()),
+            ("debug".to_owned(), 5, "(1, 2)".to_owned())
+        ]
+    );
+    let out = run_code(
+        Config::new().with_log(),
+        r#"("verbose".log_set_level, "warn".log_set_level, ().log_level)"#,
+    )?;
+    assert_eq!(
+        out.1.get().with_info(&out.2).to_string(),
+        r#"({log_error: unknown log level "verbose", expected "debug", "info", "warn", "error" or "off"}, (), warn)"#
+    );
+    Ok(())
+}
+
+#[test]
+fn assertions() -> Res {
+    let out = run_code(
+        Config::new().bundle_base(),
+        "true.assert\n((1, 2), (1, 2)).assert_eq\nx := if true { 5 } else { \"s\" }\n(x, x [Int] -> x).assert_type",
+    )?;
+    assert_eq!(out.1.get().with_info(&out.2).to_string(), "5");
+    assert!(run_code(Config::new().bundle_base(), "(1, 2).assert_eq").is_err());
+    assert!(run_code(
+        Config::new().bundle_base(),
+        "(\"s\", x [Int] -> x).assert_type"
+    )
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn stdin_read_errors() -> Res {
+    /// returns one byte, then fails
+    struct FailingReader(bool);
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if std::mem::replace(&mut self.0, true) {
+                Err(std::io::Error::other("broken pipe"))
+            } else {
+                buf[0] = b'a';
+                Ok(1)
+            }
+        }
+    }
+    for code in [
+        "().lines.as_list",
+        "4.stdin_chunks.as_list",
+        "().read_all_stdin",
+    ] {
+        let mut src = Source::new_from_string(code.to_owned());
+        let srca = Arc::new(src.clone());
+        let parsed = parse(&mut src, &srca)?;
+        let (mut i1, mut i2, mut i3) = Config::new().bundle_std().infos();
+        *i2.global.stdin.lock().unwrap() = Some(Box::new(FailingReader(false)));
+        let compiled = parsed.compile(&mut i1, Default::default())?;
+        compiled.check(&mut i3, Default::default())?;
+        let e = compiled.run(&mut i2).expect_err(code);
+        let e = e.display_notheme().to_string();
+        assert!(e.contains("broken pipe"), "{code}: {e}");
+    }
+    Ok(())
+}
+
 type Res = Result<(), CheckError>;
 
 fn run_code(cfg: Config, code: impl Into<String>) -> Result<TypedData, CheckError> {
@@ -76,6 +881,19 @@ fn run_code(cfg: Config, code: impl Into<String>) -> Result<TypedData, CheckErro
     Ok(TypedData(output_type, output_data, i2))
 }
 
+fn run_code_verified(cfg: Config, code: impl Into<String>) -> Result<Data, CheckError> {
+    let mut src = Source::new_from_string(code.into());
+    let srca = Arc::new(src.clone());
+    let parsed = parse(&mut src, &srca)?;
+    let (mut i1, mut i2, mut i3) = cfg.infos();
+    let checked_types = mers_lib::program::run::CheckedTypes::default();
+    i3.global.checked_types = Some(checked_types.clone());
+    i2.global.verify_types = Some(checked_types);
+    let compiled = parsed.compile(&mut i1, Default::default())?;
+    compiled.check(&mut i3, Default::default())?;
+    compiled.run(&mut i2)
+}
+
 struct TypedData(Type, Data, mers_lib::program::run::Info);
 impl Debug for TypedData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use mers_lib::prelude_compile::*;
+use mers_lib::program::run::debugger::{DebugEvent, Debugger, Resume, StopReason};
+use serde_json::{json, Value};
+
+type Program = (
+    Box<dyn mers_lib::program::run::MersStatement>,
+    mers_lib::program::run::Info,
+);
+
+/// A debug adapter for mers, speaking the Debug Adapter Protocol over stdin/stdout.
+/// The debugged program's output is sent to the client as `output` events.
+fn main() {
+    let client = Arc::new(Client {
+        out: Mutex::new(std::io::stdout()),
+        seq: AtomicI64::new(1),
+    });
+    let (debugger, events) = Debugger::new();
+    {
+        let client = Arc::clone(&client);
+        std::thread::spawn(move || {
+            for event in events {
+                match event {
+                    DebugEvent::Stopped { thread, reason } => client.event(
+                        "stopped",
+                        json!({
+                            "reason": match reason {
+                                StopReason::Entry => "entry",
+                                StopReason::Breakpoint => "breakpoint",
+                                StopReason::Step => "step",
+                                StopReason::Pause => "pause",
+                            },
+                            "threadId": thread,
+                            "allThreadsStopped": false,
+                        }),
+                    ),
+                }
+            }
+        });
+    }
+    let mut program: Option<Program> = None;
+    let mut configured = false;
+    let mut frame_ids = FrameIds::default();
+    let mut stdin = BufReader::new(std::io::stdin().lock());
+    loop {
+        let req = match read_message(&mut stdin) {
+            Ok(Some(req)) => req,
+            Ok(None) => break,
+            Err(e) => {
+                client.event(
+                    "output",
+                    json!({ "category": "console", "output": format!("mersdap: {e}\n") }),
+                );
+                match e {
+                    ReadError::InvalidJson(_) => continue,
+                    ReadError::Fatal(_) => break,
+                }
+            }
+        };
+        let args = &req["arguments"];
+        let thread = args["threadId"].as_u64().unwrap_or(1) as usize;
+        let command = req["command"].as_str().unwrap_or("");
+        let response = match command {
+            "initialize" => {
+                client.respond(
+                    &req,
+                    Ok(json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsTerminateRequest": true,
+                    })),
+                );
+                client.event("initialized", json!({}));
+                continue;
+            }
+            "launch" => launch(args, &debugger, &client).map(|p| {
+                program = Some(p);
+                json!({})
+            }),
+            "setBreakpoints" => match args["source"]["path"].as_str() {
+                Some(path) => {
+                    let lines = args["breakpoints"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|bp| bp["line"].as_u64())
+                        .map(|line| line as usize)
+                        .collect::<Vec<_>>();
+                    debugger.set_breakpoints(PathBuf::from(path), lines.iter().copied());
+                    Ok(json!({
+                        "breakpoints": lines
+                            .iter()
+                            .map(|line| json!({ "verified": true, "line": line }))
+                            .collect::<Vec<_>>(),
+                    }))
+                }
+                None => Err("setBreakpoints requires source.path".to_owned()),
+            },
+            "configurationDone" => {
+                configured = true;
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({
+                "threads": debugger
+                    .threads()
+                    .into_iter()
+                    .map(|(id, name)| json!({ "id": id, "name": name }))
+                    .collect::<Vec<_>>(),
+            })),
+            "stackTrace" => {
+                let frames = debugger.stack_trace(thread);
+                Ok(json!({
+                    "totalFrames": frames.len(),
+                    "stackFrames": frames
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, frame)| {
+                            let mut f = json!({
+                                "id": frame_ids.id(thread, i),
+                                "name": frame.name,
+                                "line": frame.line,
+                                "column": frame.column,
+                            });
+                            if let Some(file) = frame.file {
+                                f["source"] = json!({ "path": file.to_string_lossy() });
+                            }
+                            f
+                        })
+                        .collect::<Vec<_>>(),
+                }))
+            }
+            "scopes" => Ok(json!({
+                "scopes": [{
+                    "name": "Locals",
+                    // `0` means "no variables", so frame ids are offset by one
+                    "variablesReference": args["frameId"].as_u64().unwrap_or(0) + 1,
+                    "expensive": false,
+                }],
+            })),
+            "variables" => match args["variablesReference"]
+                .as_u64()
+                .and_then(|r| r.checked_sub(1))
+                .map(|id| frame_ids.frame(id))
+            {
+                Some(Some((thread, frame))) => Ok(json!({
+                    "variables": debugger
+                        .variables(thread, frame)
+                        .into_iter()
+                        .map(|v| json!({
+                            "name": v.name,
+                            "value": v.value,
+                            "type": v.value_type,
+                            "variablesReference": 0,
+                        }))
+                        .collect::<Vec<_>>(),
+                })),
+                Some(None) => Err("unknown variablesReference".to_owned()),
+                None => Err("variables requires a nonzero variablesReference".to_owned()),
+            },
+            "continue" | "next" | "stepIn" | "stepOut" => {
+                debugger.resume(
+                    thread,
+                    match command {
+                        "continue" => Resume::Continue,
+                        "next" => Resume::StepOver,
+                        "stepIn" => Resume::StepIn,
+                        _ => Resume::StepOut,
+                    },
+                );
+                Ok(json!({ "allThreadsContinued": false }))
+            }
+            "pause" => {
+                debugger.pause(thread);
+                Ok(json!({}))
+            }
+            "terminate" | "disconnect" => {
+                debugger.terminate();
+                Ok(json!({}))
+            }
+            _ => Err(format!("unsupported request '{command}'")),
+        };
+        client.respond(&req, response);
+        if command == "disconnect" {
+            break;
+        }
+        if configured {
+            if let Some((compiled, mut info)) = program.take() {
+                let client = Arc::clone(&client);
+                std::thread::spawn(move || {
+                    let exit_code = match compiled.run(&mut info) {
+                        Ok(_) => 0,
+                        Err(e) => {
+                            client.event(
+                                "output",
+                                json!({
+                                    "category": "stderr",
+                                    "output": format!("Error while running:\n{}\n", e.display_notheme()),
+                                }),
+                            );
+                            1
+                        }
+                    };
+                    client.event("exited", json!({ "exitCode": exit_code }));
+                    client.event("terminated", json!({}));
+                });
+            }
+        }
+    }
+}
+
+/// parses, compiles and checks the program
+fn launch(args: &Value, debugger: &Arc<Debugger>, client: &Arc<Client>) -> Result<Program, String> {
+    let path = args["program"]
+        .as_str()
+        .ok_or_else(|| "launch requires a program path".to_owned())?;
+    let mut src = Source::new_from_file(PathBuf::from(path))
+        .map_err(|e| format!("Can't read file {path:?}: {e}"))?;
+    let srca = Arc::new(src.clone());
+    let parsed = parse(&mut src, &srca).map_err(|e| e.display_notheme().to_string())?;
+    let (mut i1, mut i2, i3) = Config::new().bundle_std().infos();
+    i1.global.debugger = Some(Arc::clone(debugger));
+    let compiled = compile(&*parsed, i1).map_err(|e| e.display_notheme().to_string())?;
+    check(&*compiled, i3).map_err(|e| e.display_notheme().to_string())?;
+    i2.global.debugger = Some(Arc::clone(debugger));
+    *i2.global.stdin.lock().unwrap() = Some(Box::new(std::io::empty()));
+    *i2.global.stdout.lock().unwrap() = Some((
+        Box::new(OutputEvents(Arc::clone(client), "stdout", vec![])),
+        Box::new(OutputEvents(Arc::clone(client), "stderr", vec![])),
+    ));
+    i2.global
+        .allow_process_exit_via_exit
+        .store(false, Ordering::Relaxed);
+    debugger.stop_on_entry(args["stopOnEntry"].as_bool().unwrap_or(false));
+    Ok((compiled, i2))
+}
+
+/// Hands out the ids of stack frames, which must be unique across all threads.
+/// A frame (identified by its thread and its index in that thread's stack) keeps its id.
+#[derive(Default)]
+struct FrameIds {
+    ids: HashMap<(usize, usize), usize>,
+    frames: Vec<(usize, usize)>,
+}
+impl FrameIds {
+    fn id(&mut self, thread: usize, frame: usize) -> usize {
+        *self.ids.entry((thread, frame)).or_insert_with(|| {
+            self.frames.push((thread, frame));
+            self.frames.len() - 1
+        })
+    }
+    /// the thread and frame index of a frame id
+    fn frame(&self, id: u64) -> Option<(usize, usize)> {
+        self.frames.get(usize::try_from(id).ok()?).copied()
+    }
+}
+
+/// Sends messages to the client
+struct Client {
+    out: Mutex<std::io::Stdout>,
+    seq: AtomicI64,
+}
+impl Client {
+    fn send(&self, mut msg: Value) {
+        msg["seq"] = json!(self.seq.fetch_add(1, Ordering::Relaxed));
+        let body = msg.to_string();
+        let mut out = self.out.lock().unwrap();
+        let _ = write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let _ = out.flush();
+    }
+    fn event(&self, event: &str, body: Value) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+    fn respond(&self, req: &Value, body: Result<Value, String>) {
+        let mut msg = json!({
+            "type": "response",
+            "request_seq": req["seq"],
+            "command": req["command"],
+            "success": body.is_ok(),
+        });
+        match body {
+            Ok(body) => msg["body"] = body,
+            Err(message) => msg["message"] = json!(message),
+        }
+        self.send(msg);
+    }
+}
+
+/// Writing to this sends `output` events to the client whenever it is flushed
+struct OutputEvents(Arc<Client>, &'static str, Vec<u8>);
+impl Write for OutputEvents {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.2.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.2.is_empty() {
+            self.0.event(
+                "output",
+                json!({ "category": self.1, "output": String::from_utf8_lossy(&self.2) }),
+            );
+            self.2.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Why `read_message` didn't return a message
+enum ReadError {
+    /// The body of a message isn't valid JSON. The next message can still be read.
+    InvalidJson(serde_json::Error),
+    /// Reading failed or the headers are invalid, so no more messages can be read.
+    Fatal(String),
+}
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidJson(e) => write!(f, "ignoring a message which isn't valid JSON: {e}"),
+            Self::Fatal(e) => write!(f, "can't read messages anymore: {e}"),
+        }
+    }
+}
+
+/// reads the next message. returns `None` once the input ends.
+fn read_message(stdin: &mut impl BufRead) -> Result<Option<Value>, ReadError> {
+    let mut len = None;
+    let mut first = true;
+    loop {
+        let mut line = String::new();
+        match stdin.read_line(&mut line) {
+            Ok(0) if first => return Ok(None),
+            Ok(0) => return Err(ReadError::Fatal("input ended in the headers".to_owned())),
+            Ok(_) => {}
+            Err(e) => return Err(ReadError::Fatal(e.to_string())),
+        }
+        first = false;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(l) = line.strip_prefix("Content-Length:") {
+            len = Some(
+                l.trim()
+                    .parse::<usize>()
+                    .map_err(|_| ReadError::Fatal(format!("invalid header {line:?}")))?,
+            );
+        }
+    }
+    let len = len.ok_or_else(|| ReadError::Fatal("missing Content-Length header".to_owned()))?;
+    let mut body = vec![];
+    // `take` instead of a buffer of size `len`, so that a huge length doesn't allocate
+    Read::take(&mut *stdin, len as u64)
+        .read_to_end(&mut body)
+        .map_err(|e| ReadError::Fatal(e.to_string()))?;
+    if body.len() < len {
+        return Err(ReadError::Fatal("input ended in a message".to_owned()));
+    }
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(ReadError::InvalidJson)
+}
@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+
+const PROGRAM: &str = r#"x := 5
+y := "hello"
+f := a -> {
+  b := (a, a)
+  b
+}
+z := x.f
+z.println
+"#;
+
+struct Dap {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    seq: u64,
+    events: VecDeque<Value>,
+}
+impl Dap {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_mersdap"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        Self {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            seq: 0,
+            events: VecDeque::new(),
+        }
+    }
+    fn read(&mut self) -> Value {
+        let mut len = 0;
+        loop {
+            let mut line = String::new();
+            assert!(
+                self.stdout.read_line(&mut line).unwrap() > 0,
+                "mersdap exited"
+            );
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            len = line
+                .strip_prefix("Content-Length: ")
+                .unwrap()
+                .parse()
+                .unwrap();
+        }
+        let mut body = vec![0; len];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+        let body = json!({ "seq": self.seq, "type": "request", "command": command, "arguments": arguments }).to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+        loop {
+            let msg = self.read();
+            if msg["type"] == "response" && msg["request_seq"] == self.seq {
+                assert_eq!(msg["success"], true, "{msg}");
+                return msg["body"].clone();
+            }
+            self.events.push_back(msg);
+        }
+    }
+    fn event(&mut self, event: &str) -> Value {
+        loop {
+            let msg = match self.events.pop_front() {
+                Some(msg) => msg,
+                None => self.read(),
+            };
+            if msg["event"] == event {
+                return msg["body"].clone();
+            }
+        }
+    }
+    /// launches the program with a breakpoint at `line`
+    fn launch(name: &str, line: usize) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("mersdap-test-{name}-{}.mers", std::process::id()));
+        std::fs::write(&path, PROGRAM).unwrap();
+        let mut dap = Self::start();
+        dap.request("initialize", json!({ "adapterID": "mers" }));
+        dap.event("initialized");
+        dap.request("launch", json!({ "program": path }));
+        dap.request(
+            "setBreakpoints",
+            json!({ "source": { "path": path }, "breakpoints": [{ "line": line }] }),
+        );
+        dap.request("configurationDone", json!({}));
+        dap
+    }
+    fn stopped(&mut self, reason: &str) -> Vec<Value> {
+        let stopped = self.event("stopped");
+        assert_eq!(stopped["reason"], reason);
+        let frames = self.request("stackTrace", json!({ "threadId": stopped["threadId"] }));
+        frames["stackFrames"].as_array().unwrap().clone()
+    }
+    fn variables(&mut self, frame: &Value) -> Vec<(String, String)> {
+        let scopes = self.request("scopes", json!({ "frameId": frame["id"] }));
+        let vars = self.request(
+            "variables",
+            json!({ "variablesReference": scopes["scopes"][0]["variablesReference"] }),
+        );
+        vars["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| {
+                (
+                    v["name"].as_str().unwrap().to_owned(),
+                    v["value"].as_str().unwrap().to_owned(),
+                )
+            })
+            .collect()
+    }
+    fn finish(mut self) {
+        assert_eq!(self.event("output")["output"], "(5, 5)\n");
+        assert_eq!(self.event("exited")["exitCode"], 0);
+        self.event("terminated");
+        self.request("disconnect", json!({}));
+        self.child.wait().unwrap();
+    }
+}
+
+#[test]
+fn breakpoint_step_in_and_variables() {
+    let mut dap = Dap::launch("step-in", 7);
+    let frames = dap.stopped("breakpoint");
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0]["line"], 7);
+    let vars = dap.variables(&frames[0]);
+    assert!(vars.contains(&("x".to_owned(), "5".to_owned())), "{vars:?}");
+    assert!(vars.iter().any(|(name, _)| name == "y"), "{vars:?}");
+    assert!(vars.iter().any(|(name, _)| name == "f"), "{vars:?}");
+    assert!(!vars.iter().any(|(name, _)| name == "println"), "{vars:?}");
+
+    // stops where the argument is assigned first
+    dap.request("stepIn", json!({ "threadId": 1 }));
+    let frames = dap.stopped("step");
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0]["line"], 3);
+    assert_eq!(frames[0]["name"], "f");
+    assert_eq!(frames[1]["line"], 7);
+
+    dap.request("next", json!({ "threadId": 1 }));
+    let frames = dap.stopped("step");
+    assert_eq!(frames[0]["line"], 4);
+    assert!(dap
+        .variables(&frames[0])
+        .contains(&("a".to_owned(), "5".to_owned())));
+
+    dap.request("next", json!({ "threadId": 1 }));
+    let frames = dap.stopped("step");
+    assert_eq!(frames[0]["line"], 5);
+    assert!(dap
+        .variables(&frames[0])
+        .contains(&("b".to_owned(), "(5, 5)".to_owned())));
+
+    dap.request("continue", json!({ "threadId": 1 }));
+    dap.finish();
+}
+
+#[test]
+fn step_out_and_over() {
+    let mut dap = Dap::launch("step-out", 4);
+    let frames = dap.stopped("breakpoint");
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0]["line"], 4);
+
+    dap.request("stepOut", json!({ "threadId": 1 }));
+    let frames = dap.stopped("step");
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0]["line"], 8);
+    assert!(dap
+        .variables(&frames[0])
+        .contains(&("z".to_owned(), "(5, 5)".to_owned())));
+
+    dap.request("next", json!({ "threadId": 1 }));
+    dap.finish();
+}
+
+#[test]
+fn malformed_message_is_reported() {
+    let mut dap = Dap::start();
+    write!(dap.stdin, "Content-Length: 5\r\n\r\n{{oops").unwrap();
+    dap.request("initialize", json!({ "adapterID": "mers" }));
+    let output = dap.event("output");
+    assert_eq!(output["category"], "console");
+    assert!(
+        output["output"]
+            .as_str()
+            .unwrap()
+            .starts_with("mersdap: ignoring a message which isn't valid JSON"),
+        "{output}"
+    );
+    dap.request("disconnect", json!({}));
+    dap.child.wait().unwrap();
+}